@@ -0,0 +1,467 @@
+// Converts images between float formats (.hdr and .pfm) and packed ones (rgb9e5 .dds, .ktx2 in
+// any format, or raw dumps of packed values), and prints how much the conversion lost.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use shared_exponent_formats::dds::{read_dds, write_dds, DdsTexture};
+use shared_exponent_formats::hdr::{read_hdr, write_hdr, HdrFormat, HdrImage};
+use shared_exponent_formats::ktx2::{read_ktx2, write_ktx2, Ktx2Texture};
+use shared_exponent_formats::pfm::{read_pfm, write_pfm, Endian, PfmChannels, PfmImage};
+use shared_exponent_formats::rgbe8::MAX_RGBE8;
+use shared_exponent_formats::shaders::Format;
+
+const USAGE: &str = "\
+usage: sefmt-convert <input> <output> [--format <format>] [--size <width>x<height>]
+
+Reads .hdr, .pfm, .dds, .ktx2 or .raw, and writes any of them, picked by the extension.
+.dds files are rgb9e5, .ktx2 and .raw hold --format. .raw files are the packed values one after
+the other in little endian, with no header, so reading them also needs --size.
+
+  --format  rgb9e5, xyz8e5, xyz9e2, xyz13e6, xyz14e3 or xyz18e7, rgb9e5 by default
+  --size    the size of a .raw input, like 1920x1080";
+
+struct Options {
+    input: String,
+    output: String,
+    format: Format,
+    size: Option<(u32, u32)>,
+}
+
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 3]>,
+}
+
+// The pixels as the output stores them, and the range it can store.
+struct Stored {
+    pixels: Vec<[f32; 3]>,
+    min: f32,
+    max: f32,
+}
+
+// Error statistics of the stored image against the input, per component. NaN and components
+// past the range of the output are counted, and left out of the errors as they are clamped.
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    components: usize,
+    nan: usize,
+    clamped: usize,
+    max_error: f64,
+    sum_error: f64,
+    sum_squared_error: f64,
+    peak: f64,
+}
+
+impl Stats {
+    fn new(input: &[[f32; 3]], stored: &[[f32; 3]], min: f32, max: f32) -> Stats {
+        let mut stats = Stats::default();
+        for (input, stored) in input.iter().flatten().zip(stored.iter().flatten()) {
+            stats.components += 1;
+            if input.is_nan() {
+                stats.nan += 1;
+            } else if *input < min || *input > max {
+                stats.clamped += 1;
+            } else {
+                let error = (*input as f64 - *stored as f64).abs();
+                stats.max_error = stats.max_error.max(error);
+                stats.sum_error += error;
+                stats.sum_squared_error += error * error;
+                stats.peak = stats.peak.max((*input as f64).abs());
+            }
+        }
+        stats
+    }
+
+    fn in_range(&self) -> usize {
+        self.components - self.nan - self.clamped
+    }
+
+    fn avg_error(&self) -> f64 {
+        self.sum_error / self.in_range().max(1) as f64
+    }
+
+    // Against the largest component, infinite when nothing was lost. None for black images, which
+    // have no peak.
+    fn psnr(&self) -> Option<f64> {
+        if self.peak == 0.0 {
+            return None;
+        }
+        let mse = self.sum_squared_error / self.in_range().max(1) as f64;
+        Some(10.0 * (self.peak * self.peak / mse).log10())
+    }
+
+    fn print(&self) {
+        let psnr = match self.psnr() {
+            Some(psnr) => format!("{psnr:.2} dB"),
+            None => "n/a".to_string(),
+        };
+        println!(
+            "max error {:.3e}, avg error {:.3e}, psnr {psnr} (peak {:.3e})",
+            self.max_error,
+            self.avg_error(),
+            self.peak
+        );
+        println!(
+            "{} of {} components clamped, {} NaN",
+            self.clamped, self.components, self.nan
+        );
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut paths = Vec::new();
+    let mut format = Format::Rgb9e5;
+    let mut size = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or("--format needs a value")?;
+                format = Format::from_name(name).ok_or(format!("unknown format {name}"))?;
+            }
+            "--size" => {
+                let value = args.next().ok_or("--size needs a value")?;
+                let parsed = value
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+                size = Some(parsed.ok_or(format!("invalid size {value}"))?);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => paths.push(arg.clone()),
+        }
+    }
+    let [input, output] = <[String; 2]>::try_from(paths).map_err(|_| "expected two files")?;
+    Ok(Options {
+        input,
+        output,
+        format,
+        size,
+    })
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn decode_words(format: Format, words: &[u32]) -> Vec<[f32; 3]> {
+    let texel_words = format.gpu_format().texture_format.texel_size() as usize / 4;
+    words
+        .chunks(texel_words)
+        .map(|texel| format.decode([texel[0], texel.get(1).copied().unwrap_or(0)]))
+        .collect()
+}
+
+fn read_image(options: &Options) -> Result<Image, Box<dyn Error>> {
+    let path = &options.input;
+    let reader = BufReader::new(File::open(path)?);
+    let image = match &extension(path)[..] {
+        "hdr" => {
+            let image = read_hdr(reader)?;
+            Image {
+                width: image.width,
+                height: image.height,
+                pixels: image.to_vec3(),
+            }
+        }
+        "pfm" => {
+            let image = read_pfm(reader)?;
+            Image {
+                width: image.width,
+                height: image.height,
+                pixels: image.pixels,
+            }
+        }
+        // The largest level of the first face.
+        "dds" => {
+            let texture = read_dds(reader)?;
+            Image {
+                width: texture.width,
+                height: texture.height,
+                pixels: decode_words(Format::Rgb9e5, texture.level(0, 0)),
+            }
+        }
+        "ktx2" => {
+            let texture = read_ktx2(reader)?;
+            Image {
+                width: texture.width,
+                height: texture.height,
+                pixels: decode_words(texture.format, texture.image(0, 0, 0)),
+            }
+        }
+        "raw" => {
+            let (width, height) = options.size.ok_or("a .raw input needs --size")?;
+            let bytes = std::fs::read(path)?;
+            let format = options.format;
+            let len = (width as usize)
+                .checked_mul(height as usize)
+                .and_then(|len| len.checked_mul(format.bytes() as usize));
+            if len != Some(bytes.len()) {
+                return Err(format!("{path} isn't {width}x{height} {}", format.name()).into());
+            }
+            // The length is checked, so every chunk holds a whole value.
+            let pixels = bytes
+                .chunks_exact(format.bytes() as usize)
+                .map(|bytes| format.decode(format.from_le_bytes(bytes).unwrap()))
+                .collect();
+            Image {
+                width,
+                height,
+                pixels,
+            }
+        }
+        _ => return Err(format!("unsupported input {path}").into()),
+    };
+    Ok(image)
+}
+
+fn write_image(options: &Options, image: &Image) -> Result<Stored, Box<dyn Error>> {
+    let path = &options.output;
+    let extension = extension(path);
+    if !["hdr", "pfm", "dds", "ktx2", "raw"].contains(&&extension[..]) {
+        return Err(format!("unsupported output {path}").into());
+    }
+    if ["dds", "ktx2"].contains(&&extension[..]) && (image.width == 0 || image.height == 0) {
+        return Err(format!("{extension} files can't store an empty image").into());
+    }
+    let format = match &extension[..] {
+        "dds" => Format::Rgb9e5,
+        _ => options.format,
+    };
+    let words: Vec<[u32; 2]> = image.pixels.iter().map(|&xyz| format.encode(xyz)).collect();
+    let pixels: Vec<[f32; 3]> = words.iter().map(|&v| format.decode(v)).collect();
+    let min = if format.signed() { -format.max() } else { 0.0 };
+    let texel_words = format.gpu_format().texture_format.texel_size() as usize / 4;
+    let texels: Vec<u32> = words
+        .iter()
+        .flat_map(|v| v[..texel_words].to_vec())
+        .collect();
+
+    // Written in memory first, so failures don't leave a file behind.
+    let mut writer = Vec::new();
+    let result = match &extension[..] {
+        "hdr" => {
+            let hdr =
+                HdrImage::from_vec3(image.width, image.height, HdrFormat::Rgbe, &image.pixels)?;
+            write_hdr(&mut writer, &hdr)?;
+            Stored {
+                pixels: hdr.to_vec3(),
+                min: 0.0,
+                max: MAX_RGBE8,
+            }
+        }
+        "pfm" => {
+            let pfm = PfmImage {
+                width: image.width,
+                height: image.height,
+                pixels: image.pixels.clone(),
+            };
+            write_pfm(&mut writer, &pfm, PfmChannels::Color, Endian::Little)?;
+            Stored {
+                pixels: pfm.pixels,
+                min: f32::MIN,
+                max: f32::MAX,
+            }
+        }
+        "dds" => {
+            write_dds(
+                &mut writer,
+                &DdsTexture::new(image.width, image.height, texels),
+            )?;
+            Stored {
+                pixels,
+                min,
+                max: format.max(),
+            }
+        }
+        "ktx2" => {
+            let texture = Ktx2Texture::new(format, image.width, image.height, texels);
+            write_ktx2(&mut writer, &texture)?;
+            Stored {
+                pixels,
+                min,
+                max: format.max(),
+            }
+        }
+        "raw" => {
+            for &v in &words {
+                writer.write_all(&format.to_le_bytes(v)[..format.bytes() as usize])?;
+            }
+            Stored {
+                pixels,
+                min,
+                max: format.max(),
+            }
+        }
+        _ => unreachable!(),
+    };
+    std::fs::write(path, writer)?;
+    Ok(result)
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let options = parse_args(args)?;
+    let image = read_image(&options)?;
+    let stored = write_image(&options, &image)?;
+    println!(
+        "{} -> {}: {}x{}",
+        options.input, options.output, image.width, image.height
+    );
+    Stats::new(&image.pixels, &stored.pixels, stored.min, stored.max).print();
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use shared_exponent_formats::rgb9e5::{rgb9e5_to_vec3, vec3_to_rgb9e5};
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_stats() {
+        let input = [[1.0, 2.0, f32::NAN], [-1.0, 70000.0, 0.5]];
+        let stored = [[1.0, 2.0, 0.0], [0.0, 65408.0, 0.25]];
+        let stats = Stats::new(&input, &stored, 0.0, 65408.0);
+        assert_eq!((stats.components, stats.nan, stats.clamped), (6, 1, 2));
+        assert_eq!(stats.max_error, 0.25);
+        assert_eq!(stats.avg_error(), 0.25 / 3.0);
+        assert_eq!(stats.peak, 2.0);
+        assert_eq!(stats.psnr(), Some(10.0 * (4.0 / (0.0625 / 3.0f64)).log10()));
+        assert_eq!(
+            Stats::new(&input[..1], &stored[..1], 0.0, 1e9).psnr(),
+            Some(f64::INFINITY)
+        );
+        let black = [[0.0; 3]; 2];
+        assert_eq!(Stats::new(&black, &black, 0.0, 1e9).psnr(), None);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args(&["a.pfm", "--format", "xyz13e6", "b.raw"])).unwrap();
+        assert_eq!(
+            (&options.input[..], &options.output[..]),
+            ("a.pfm", "b.raw")
+        );
+        assert_eq!(options.format, Format::Xyz13e6);
+        let options = parse_args(&args(&["a.raw", "b.hdr", "--size", "640x480"])).unwrap();
+        assert_eq!(
+            (options.format, options.size),
+            (Format::Rgb9e5, Some((640, 480)))
+        );
+        assert!(parse_args(&args(&["a.pfm"])).is_err());
+        assert!(parse_args(&args(&["a", "b", "--format", "rgb9e6"])).is_err());
+        assert!(parse_args(&args(&["a", "b", "--size", "640"])).is_err());
+        assert!(parse_args(&args(&["a", "b", "--quality"])).is_err());
+    }
+
+    // A pfm through every output and back.
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("sefmt-convert-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+
+        let pixels: Vec<[f32; 3]> = (0..12).map(|i| [i as f32 * 0.3, 1.0, -0.5]).collect();
+        let image = PfmImage {
+            width: 4,
+            height: 3,
+            pixels: pixels.clone(),
+        };
+        let file = File::create(path("in.pfm")).unwrap();
+        write_pfm(file, &image, PfmChannels::Color, Endian::Big).unwrap();
+
+        for (output, format) in [
+            ("out.dds", "rgb9e5"),
+            ("out.ktx2", "xyz8e5"),
+            ("out.raw", "xyz13e6"),
+            ("out.hdr", "rgb9e5"),
+        ] {
+            let options = args(&[&path("in.pfm"), &path(output), "--format", format]);
+            run(&options).unwrap();
+            let options = args(&[
+                &path(output),
+                &path("back.pfm"),
+                "--format",
+                format,
+                "--size",
+                "4x3",
+            ]);
+            run(&options).unwrap();
+            let back = read_pfm(BufReader::new(File::open(path("back.pfm")).unwrap())).unwrap();
+            assert_eq!((back.width, back.height), (4, 3));
+            if output == "out.dds" {
+                let expected: Vec<[f32; 3]> = pixels
+                    .iter()
+                    .map(|&rgb| rgb9e5_to_vec3(vec3_to_rgb9e5(rgb)))
+                    .collect();
+                assert_eq!(back.pixels, expected);
+            }
+            for (back, input) in back.pixels.iter().zip(&pixels) {
+                // Only rgb9e5 and rgbe8 clamp the negative component.
+                let signed = output == "out.ktx2" || output == "out.raw";
+                for i in 0..3 {
+                    let input = if signed { input[i] } else { input[i].max(0.0) };
+                    assert!((back[i] - input).abs() < 0.02, "{output} {back:?}");
+                }
+            }
+        }
+
+        // Outputs that can't be written fail without creating the file.
+        let empty = PfmImage {
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+        };
+        let file = File::create(path("empty.pfm")).unwrap();
+        write_pfm(file, &empty, PfmChannels::Color, Endian::Little).unwrap();
+        for (input, output) in [
+            ("empty.pfm", "empty.dds"),
+            ("empty.pfm", "empty.ktx2"),
+            ("in.pfm", "out.png"),
+        ] {
+            assert!(run(&args(&[&path(input), &path(output)])).is_err());
+            assert!(!dir.join(output).exists(), "{output}");
+        }
+        run(&args(&[&path("empty.pfm"), &path("empty.raw")])).unwrap();
+
+        // A size whose byte count doesn't fit usize is an error, not an overflow.
+        let options = args(&[
+            &path("out.raw"),
+            &path("big.pfm"),
+            "--size",
+            "4294967295x4294967295",
+            "--format",
+            "xyz13e6",
+        ]);
+        let error = run(&options).unwrap_err();
+        assert!(error.to_string().contains("isn't 4294967295x4294967295"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,453 @@
+// Reads and writes packed values by hand, for debugging gpu captures: decodes hex words, encodes
+// vectors, and draws the bit layout of every format or of custom `SharedExponentFormat`
+// parameters.
+
+use std::process::ExitCode;
+
+use shared_exponent_formats::custom_shared_format::SharedExponentFormat;
+use shared_exponent_formats::shader_gen::{pack, unpack, BitRange, FormatDesc};
+use shared_exponent_formats::shaders::Format;
+
+const USAGE: &str = "\
+usage: sefmt decode (--format <format> | --custom <e>,<m>) <value or words...>
+       sefmt encode (--format <format> | --custom <e>,<m>) <x> <y> <z>
+       sefmt layout (--format <format> | --custom <e>,<m>)
+
+  decode  prints the vector, exponent, mantissas and signs of a packed value, given as one
+          number or as the 32 bit words of the texture format, in hex (0x...) or decimal
+  encode  prints the packed bits of a vector, and how far they decode from it
+  layout  draws the bits of the format
+
+  --format  rgb9e5, xyz8e5, xyz9e2, xyz13e6, xyz14e3 or xyz18e7
+  --custom  an unsigned SharedExponentFormat with e exponent bits and m mantissa bits, stored
+            like rgb9e5: the mantissas from bit 0 up, then the exponent";
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Format(Format),
+    Custom(SharedExponentFormat),
+}
+
+impl Target {
+    fn name(self) -> String {
+        match self {
+            Target::Format(format) => format.name().to_string(),
+            Target::Custom(custom) => {
+                format!("custom e{} m{}", custom.exponent_bits, custom.mantissa_bits)
+            }
+        }
+    }
+
+    // The checked layout of the format. Custom formats use the default packing of the shader
+    // generator, which is the one of rgb9e5.
+    fn desc(self) -> FormatDesc {
+        match self {
+            Target::Format(format) => FormatDesc::from_format(format),
+            Target::Custom(custom) => FormatDesc::new("custom", &custom, false),
+        }
+    }
+}
+
+fn total_bits(desc: &FormatDesc) -> u32 {
+    let signs = if desc.signed { 3 } else { 0 };
+    desc.exponent_bits + 3 * desc.mantissa_bits + signs
+}
+
+// The ranges of every field with its name, named like rgb9e5 for unsigned formats.
+fn fields(desc: &FormatDesc) -> Vec<(String, &[BitRange])> {
+    let layout = &desc.layout;
+    let names = if desc.signed {
+        ["x", "y", "z"]
+    } else {
+        ["r", "g", "b"]
+    };
+    let mut fields = vec![("e".to_string(), &layout.exponent[..])];
+    for i in 0..3 {
+        fields.push((names[i].to_string(), &layout.mantissas[i][..]));
+        if let Some(signs) = &layout.signs {
+            fields.push((format!("s{}", names[i]), std::slice::from_ref(&signs[i])));
+        }
+    }
+    fields
+}
+
+struct Decoded {
+    xyz: [f32; 3],
+    exponent: u32,
+    mantissas: [u32; 3],
+    signs: [bool; 3],
+}
+
+fn decode_words(target: Target, words: &[u32]) -> Decoded {
+    let layout = target.desc().layout;
+    let exponent = unpack(words, &layout.exponent);
+    let mantissas = [0, 1, 2].map(|i| unpack(words, &layout.mantissas[i]));
+    let xyz = match target {
+        Target::Format(format) => format.decode([words[0], words.get(1).copied().unwrap_or(0)]),
+        Target::Custom(custom) => custom.decode3(mantissas, exponent as u8),
+    };
+    Decoded {
+        xyz,
+        exponent,
+        mantissas,
+        signs: match &layout.signs {
+            Some(signs) => [0, 1, 2].map(|i| unpack(words, &[signs[i]]) != 0),
+            None => [false; 3],
+        },
+    }
+}
+
+fn encode_words(target: Target, xyz: [f32; 3]) -> Vec<u32> {
+    let layout = target.desc().layout;
+    match target {
+        Target::Format(format) => format.encode(xyz)[..layout.words as usize].to_vec(),
+        Target::Custom(custom) => {
+            let (mantissas, exponent) = custom.encode3(xyz);
+            let mut words = vec![0; layout.words as usize];
+            pack(&mut words, &layout.exponent, exponent as u32);
+            for (ranges, m) in layout.mantissas.iter().zip(mantissas) {
+                pack(&mut words, ranges, m);
+            }
+            words
+        }
+    }
+}
+
+// The last word is only as wide as the format.
+fn hex_words(desc: &FormatDesc, words: &[u32]) -> String {
+    let bits = total_bits(desc);
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let digits = (bits - i as u32 * 32).min(32).div_ceil(4) as usize;
+            format!("0x{word:0digits$x}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_decoded(target: Target, words: &[u32], decoded: &Decoded) -> String {
+    let desc = target.desc();
+    let signs: Vec<&str> = decoded
+        .signs
+        .iter()
+        .map(|&s| if s { "-" } else { "+" })
+        .collect();
+    let mut out = format!("format    {}\n", target.name());
+    out += &format!("words     {}\n", hex_words(&desc, words));
+    out += &format!("vector    {:?}\n", decoded.xyz);
+    out += &format!(
+        "exponent  {} (2^{} after the bias of {})\n",
+        decoded.exponent,
+        decoded.exponent as i32 - desc.exp_bias,
+        desc.exp_bias
+    );
+    out += &format!("mantissas {:?}\n", decoded.mantissas);
+    if desc.signed {
+        out += &format!("signs     [{}]\n", signs.join(", "));
+    }
+    out
+}
+
+fn parse_number(text: &str) -> Result<u128, String> {
+    let parsed = match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        Some(hex) => u128::from_str_radix(&hex.replace('_', ""), 16),
+        None => text.replace('_', "").parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {text}"))
+}
+
+fn decode(target: Target, args: &[String]) -> Result<String, String> {
+    let desc = target.desc();
+    let bits = total_bits(&desc);
+    let count = desc.layout.words as usize;
+    let value = match args {
+        [value] => parse_number(value)?,
+        // 32 bit words, lowest first.
+        [_, ..] if args.len() == count => {
+            args.iter().enumerate().try_fold(0, |value, (i, word)| {
+                let word = parse_number(word)?;
+                if word > u32::MAX as u128 {
+                    return Err(format!("{word:#x} is more than 32 bits"));
+                }
+                Ok(value | word << (i * 32))
+            })?
+        }
+        _ => return Err(format!("expected a value or {count} words")),
+    };
+    if value >> bits != 0 {
+        return Err(format!("{value:#x} is more than {bits} bits"));
+    }
+    let words: Vec<u32> = (0..count).map(|i| (value >> (i * 32)) as u32).collect();
+    Ok(print_decoded(target, &words, &decode_words(target, &words)))
+}
+
+fn encode(target: Target, args: &[String]) -> Result<String, String> {
+    let [x, y, z] = args else {
+        return Err("expected 3 components".to_string());
+    };
+    let mut xyz = [0.0f32; 3];
+    for (component, text) in xyz.iter_mut().zip([x, y, z]) {
+        *component = text.parse().map_err(|_| format!("invalid number {text}"))?;
+    }
+
+    let words = encode_words(target, xyz);
+    let decoded = decode_words(target, &words);
+    let error = [0, 1, 2].map(|i| decoded.xyz[i] - xyz[i]);
+    let max_error = error.iter().fold(0.0f32, |max, e| max.max(e.abs()));
+    let mut out = print_decoded(target, &words, &decoded);
+    out += &format!("error     {error:?}, max {max_error:e}\n");
+    Ok(out)
+}
+
+// The fields of one word as (name, highest bit, lowest bit), from the highest bit down, with
+// unused bits as unnamed gaps. Fields split over several ranges are named by their bits.
+fn word_segments(desc: &FormatDesc, word: u32) -> Vec<(String, u32, u32)> {
+    let mut ranges = Vec::new();
+    for (name, field) in fields(desc) {
+        let mut consumed = 0;
+        for range in field {
+            let name = match (field.len(), range.bits) {
+                (1, _) => name.clone(),
+                (_, 1) => format!("{name}[{consumed}]"),
+                _ => format!("{name}[{}:{consumed}]", consumed + range.bits - 1),
+            };
+            if range.word == word {
+                ranges.push((name, range.offset + range.bits - 1, range.offset));
+            }
+            consumed += range.bits;
+        }
+    }
+    ranges.sort_by_key(|&(_, _, lo)| std::cmp::Reverse(lo));
+
+    let end = (total_bits(desc) - word * 32).min(32);
+    let mut segments = Vec::new();
+    let mut bit = end;
+    for (name, hi, lo) in ranges {
+        if hi + 1 < bit {
+            segments.push((String::new(), bit - 1, hi + 1));
+        }
+        segments.push((name, hi, lo));
+        bit = lo;
+    }
+    if bit > 0 {
+        segments.push((String::new(), bit - 1, 0));
+    }
+    segments
+}
+
+// Every bit is 3 characters wide, with the bit numbers of each field above it. Fields too narrow
+// for their name are widened.
+fn layout(target: Target) -> String {
+    let desc = target.desc();
+    let mut out = format!("{}, {} bits\n", target.name(), total_bits(&desc));
+    for word in 0..desc.layout.words {
+        let mut numbers = String::new();
+        let mut border = String::from("+");
+        let mut names = String::from("|");
+        for (name, hi, lo) in &word_segments(&desc, word) {
+            let width = (((hi - lo + 1) * 3 - 1) as usize).max(name.len());
+            let label = if hi == lo {
+                format!("{hi:^width$}")
+            } else {
+                format!("{hi:<w$}{lo:>w2$}", w = width / 2, w2 = width - width / 2)
+            };
+            numbers += &format!(" {label}");
+            border += &format!("{}+", "-".repeat(width));
+            names += &format!("{name:^width$}|");
+        }
+        out += &format!("word {word}\n{numbers}\n{border}\n{names}\n{border}\n");
+    }
+    out
+}
+
+fn parse_custom(value: &str) -> Result<SharedExponentFormat, String> {
+    let parsed = value
+        .split_once(',')
+        .and_then(|(e, m)| Some((e.trim().parse::<u8>().ok()?, m.trim().parse::<u8>().ok()?)));
+    let Some((exponent_bits, mantissa_bits)) = parsed else {
+        return Err(format!("invalid custom format {value}, expected like 5,9"));
+    };
+    // The limits of SharedExponentFormat::new.
+    if !(1..=7).contains(&exponent_bits) || !(1..=24).contains(&mantissa_bits) {
+        return Err("custom formats have 1 to 7 exponent and 1 to 24 mantissa bits".to_string());
+    }
+    if exponent_bits as u32 * mantissa_bits as u32 > 133 {
+        return Err("custom formats have at most 133 for exponent times mantissa bits".to_string());
+    }
+    Ok(SharedExponentFormat::new(exponent_bits, mantissa_bits))
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let (command, rest) = args.split_first().ok_or("expected a command")?;
+    let mut target = None;
+    let mut values = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = rest.next().ok_or("--format needs a value")?;
+                let format = Format::from_name(name).ok_or(format!("unknown format {name}"))?;
+                target = Some(Target::Format(format));
+            }
+            "--custom" => {
+                let value = rest.next().ok_or("--custom needs a value")?;
+                target = Some(Target::Custom(parse_custom(value)?));
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => values.push(arg.clone()),
+        }
+    }
+    let target = target.ok_or("expected --format or --custom")?;
+
+    match command.as_str() {
+        "decode" => decode(target, &values),
+        "encode" => encode(target, &values),
+        "layout" if values.is_empty() => Ok(layout(target)),
+        "layout" => Err("layout takes no values".to_string()),
+        _ => Err(format!("unknown command {command}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    match run(&args) {
+        Ok(out) => {
+            print!("{out}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    fn run_args(args: &str) -> Result<String, String> {
+        let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+        run(&args)
+    }
+
+    // The parts read through the layout match the format's own decompose, and the fields fill
+    // the format without gaps.
+    #[test]
+    fn test_fields() {
+        for format in Format::ALL {
+            let target = Target::Format(format);
+            let desc = target.desc();
+            assert_eq!(total_bits(&desc), format.bytes() as u32 * 8);
+            for word in 0..desc.layout.words {
+                let segments = word_segments(&desc, word);
+                assert!(segments.iter().all(|(name, _, _)| !name.is_empty()));
+            }
+            for bit in 0..total_bits(&desc) {
+                let v = [(1u64 << bit) as u32, ((1u64 << bit) >> 32) as u32];
+                let words = &v[..desc.layout.words as usize];
+                let decoded = decode_words(target, words);
+                let parts = format.decompose(v);
+                assert_eq!(decoded.exponent, parts.exponent, "{format:?} {bit}");
+                assert_eq!(decoded.mantissas, parts.mantissas, "{format:?} {bit}");
+                assert_eq!(decoded.signs, parts.signs, "{format:?} {bit}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        // 1.0, 0.5 and 0.0 in rgb9e5.
+        let value = 16 << 27 | 128 << 9 | 256;
+        let out = run_args(&format!("decode --format rgb9e5 {value:#x}")).unwrap();
+        assert_eq!(
+            out,
+            "format    rgb9e5\n\
+             words     0x80010100\n\
+             vector    [1.0, 0.5, 0.0]\n\
+             exponent  16 (2^1 after the bias of 15)\n\
+             mantissas [256, 128, 0]\n"
+        );
+
+        let words = shared_exponent_formats::xyz13e6::vec3_to_xyz13e6([-2.0, 0.25, 1.0]);
+        let out = run_args(&format!(
+            "decode --format xyz13e6 {:#x} {:#x}",
+            words.0, words.1
+        ));
+        let out = out.unwrap();
+        assert!(out.contains("vector    [-2.0, 0.25, 1.0]\n"), "{out}");
+        assert!(out.contains("signs     [-, +, +]\n"), "{out}");
+        let value = words.0 as u64 | (words.1 as u64) << 32;
+        assert_eq!(
+            run_args(&format!("decode --format xyz13e6 {value}")).unwrap(),
+            out
+        );
+
+        let out = run_args("decode --custom 5,9 0x80010100").unwrap();
+        assert!(out.contains("vector    [1.0, 0.5, 0.0]\n"), "{out}");
+        assert!(run_args("decode --format rgb9e5 0x100000000").is_err());
+        assert!(run_args("decode --format xyz13e6 0x1 0x10000").is_err());
+        assert!(run_args("decode --format xyz18e7 1 2 3").is_err());
+        assert!(run_args("decode --format rgb9e5 0xg").is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let out = run_args("encode --format xyz8e5 1.0 -2.0 0.3").unwrap();
+        let v = shared_exponent_formats::xyz8e5::vec3_to_xyz8e5([1.0, -2.0, 0.3]);
+        assert!(out.contains(&format!("words     {v:#010x}\n")), "{out}");
+        assert!(out.contains("vector    [1.0, -2.0, 0.296875]\n"), "{out}");
+        assert!(
+            out.ends_with("error     [0.0, 0.0, -0.003125012], max 3.125012e-3\n"),
+            "{out}"
+        );
+
+        // The custom format with rgb9e5's parameters packs the same bits.
+        for format in ["--format rgb9e5", "--custom 5,9"] {
+            let out = run_args(&format!("encode {format} 3.0 0.125 1000")).unwrap();
+            let v = shared_exponent_formats::rgb9e5::vec3_to_rgb9e5([3.0, 0.125, 1000.0]);
+            assert!(out.contains(&format!("words     {v:#010x}\n")), "{out}");
+        }
+        let out = run_args("encode --custom 5,24 1 2 3").unwrap();
+        let words = out.lines().nth(1).unwrap().split_whitespace().skip(1);
+        assert_eq!(words.map(str::len).collect::<Vec<_>>(), [10, 10, 6]);
+        assert!(out.contains("max 0e0\n"), "{out}");
+        assert!(run_args("encode --format rgb9e5 1 2").is_err());
+        assert!(run_args("encode --custom 8,9 1 2 3").is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let out = run_args("layout --format rgb9e5").unwrap();
+        let e = format!("{:^14}", "e");
+        let rgb = ["b", "g", "r"].map(|name| format!("{name:^26}"));
+        let border = format!("+{}+{}+", "-".repeat(14), vec!["-".repeat(26); 3].join("+"));
+        let numbers = format!(
+            " {:<7}{:>7} {:<13}{:>13} {:<13}{:>13} {:<13}{:>13}",
+            31, 27, 26, 18, 17, 9, 8, 0
+        );
+        assert_eq!(
+            out,
+            format!(
+                "rgb9e5, 32 bits\nword 0\n{numbers}\n{border}\n|{e}|{}|{}|{}|\n{border}\n",
+                rgb[0], rgb[1], rgb[2]
+            )
+        );
+
+        // The split fields of the second words.
+        let out = run_args("layout --format xyz18e7").unwrap();
+        assert!(out.contains("y[17:14]") && out.contains("y[13:0]"), "{out}");
+        let out = run_args("layout --format xyz14e3").unwrap();
+        assert!(out.contains("e[2:1]") && out.contains("e[0]"), "{out}");
+        let out = run_args("layout --custom 5,24").unwrap();
+        assert!(out.starts_with("custom e5 m24, 77 bits\n") && out.contains("word 2"));
+        assert!(out.contains("b[15:0]") && out.contains("b[23:16]"), "{out}");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::math::{floor_log2f, pow2f};
 use crate::nan_to_zero;
 
 #[derive(Debug, Clone, Copy)]
@@ -14,7 +15,7 @@ pub struct SharedExponentFormat {
 }
 
 impl SharedExponentFormat {
-    pub fn new(exponent_bits: u8, mantissa_bits: u8) -> Self {
+    pub const fn new(exponent_bits: u8, mantissa_bits: u8) -> Self {
         // TODO 8 exponent_bits should be possible
         debug_assert!(exponent_bits <= 7);
         debug_assert!(mantissa_bits <= 24);
@@ -35,9 +36,13 @@ impl SharedExponentFormat {
         let epsilon = (1.0 / mantissa_values as f32) / (1u128 << exp_bias as u128) as f32;
         //let epsilon = 1.0 / mantissa_values as f32 / 2.0f32.powi(exp_bias);
 
-        let max = ((max_mantissa as f32) / mantissa_values as f32
-            * (1u128 << max_exp as u128) as f32)
-            .floor();
+        // floor(max_mantissa / mantissa_values * 2^max_exp), which is only below 1.0 for one exponent
+        // bit.
+        let max = if max_exp >= mantissa_bits as i32 {
+            max_mantissa as f32 * pow2f(max_exp - mantissa_bits as i32)
+        } else {
+            (max_mantissa >> (mantissa_bits as i32 - max_exp)) as f32
+        };
 
         //let mut max: f32 = (max_mantissa as f32) / mantissa_values as f32 * 2.0f32.powi(max_exp);
         //max = max.min(f32::MAX).floor();
@@ -57,22 +62,24 @@ impl SharedExponentFormat {
 
     // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
     #[inline]
-    pub fn get_exp(&self, maxrgb: f32) -> (f32, u8) {
+    pub const fn get_exp(&self, maxrgb: f32) -> (f32, u8) {
         let maxrgb = nan_to_zero(maxrgb).clamp(0.0, self.max);
 
-        let mut exp_shared =
-            (-self.exp_bias - 1).max(maxrgb.log2().floor() as i32) + 1 + self.exp_bias;
+        let mut exp_shared = floor_log2f(maxrgb.max(pow2f(-self.exp_bias - 1))) + 1 + self.exp_bias;
 
         debug_assert!(exp_shared <= self.max_valid_biased_exp);
         debug_assert!(exp_shared >= 0);
 
-        let mut denom = ((exp_shared - self.exp_bias - self.mantissa_bits as i32) as f32).exp2();
+        let mut denom = pow2f(exp_shared - self.exp_bias - self.mantissa_bits as i32);
 
-        let maxm = (maxrgb / denom + 0.5).floor() as i32;
+        // Everything is positive, so truncating with `as` is the same as floor.
+        let maxm = (maxrgb / denom + 0.5) as i32;
         if maxm == self.mantissa_values {
             denom *= 2.0;
             exp_shared += 1;
-            exp_shared = exp_shared.min(self.max_valid_biased_exp);
+            if exp_shared > self.max_valid_biased_exp {
+                exp_shared = self.max_valid_biased_exp;
+            }
             debug_assert!(exp_shared <= self.max_valid_biased_exp);
         } else {
             debug_assert!(maxm <= self.max_mantissa);
@@ -82,22 +89,22 @@ impl SharedExponentFormat {
     }
 
     #[inline]
-    pub fn norm(&self, denom: f32, v: f32) -> u32 {
-        (nan_to_zero(v).clamp(0.0, self.max) / denom + 0.5).floor() as u32
+    pub const fn norm(&self, denom: f32, v: f32) -> u32 {
+        (nan_to_zero(v).clamp(0.0, self.max) / denom + 0.5) as u32
     }
 
     #[inline]
-    pub fn apply_exp(&self, v: u32, exp_shared: u8) -> f32 {
+    pub const fn apply_exp(&self, v: u32, exp_shared: u8) -> f32 {
         let max_valid_biased_exp = (1 << self.exponent_bits) - 1;
         let exp_bias = max_valid_biased_exp / 2;
 
         let exponent = exp_shared as i32 - exp_bias - self.mantissa_bits as i32;
-        let scale = (exponent as f32).exp2();
+        let scale = pow2f(exponent);
 
         v as f32 * scale
     }
 
-    pub fn encode3(&self, v: [f32; 3]) -> ([u32; 3], u8) {
+    pub const fn encode3(&self, v: [f32; 3]) -> ([u32; 3], u8) {
         let x = nan_to_zero(v[0]).clamp(0.0, self.max);
         let y = nan_to_zero(v[1]).clamp(0.0, self.max);
         let z = nan_to_zero(v[2]).clamp(0.0, self.max);
@@ -112,7 +119,7 @@ impl SharedExponentFormat {
         )
     }
 
-    pub fn decode3(&self, enc: [u32; 3], exp_shared: u8) -> [f32; 3] {
+    pub const fn decode3(&self, enc: [u32; 3], exp_shared: u8) -> [f32; 3] {
         [
             self.apply_exp(enc[0], exp_shared),
             self.apply_exp(enc[1], exp_shared),
@@ -128,6 +135,25 @@ pub mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_const() {
+        const FORMAT: SharedExponentFormat = SharedExponentFormat::new(5, 9);
+        const ENCODED: ([u32; 3], u8) = FORMAT.encode3([1.0, 0.5, 0.0]);
+        assert_eq!(FORMAT.max, crate::rgb9e5::MAX_RGB9E5);
+        assert_eq!(FORMAT.epsilon, crate::rgb9e5::EPSILON_RGB9E5);
+        assert_eq!(ENCODED, ([256, 128, 0], 16));
+        assert_eq!(FORMAT.decode3(ENCODED.0, ENCODED.1), [1.0, 0.5, 0.0]);
+
+        for exponent_bits in 1..=7 {
+            for mantissa_bits in 1..=19 {
+                let format = SharedExponentFormat::new(exponent_bits, mantissa_bits);
+                let max = (format.max_mantissa as f32) / format.mantissa_values as f32
+                    * (1u128 << format.max_exp as u128) as f32;
+                assert_eq!(format.max, max.floor());
+            }
+        }
+    }
+
     #[test]
     fn test_edge_cases() {
         for exponent_bits in 1..=7 {
@@ -0,0 +1,437 @@
+// DDS files holding rgb9e5 textures as DXGI_FORMAT_R9G9B9E5_SHAREDEXP, which d3d and most engines
+// load directly. Only this format is written and read, with the DX10 header extension since the
+// legacy pixel format has no code for it. 2D textures and cubemaps are supported, with or without
+// mip chains.
+// https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::gpu_format::TextureFormat;
+
+const MAGIC: u32 = u32::from_le_bytes(*b"DDS ");
+const DX10: u32 = u32::from_le_bytes(*b"DX10");
+const HEADER_SIZE: u32 = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+// DDSCAPS2_CUBEMAP with all six DDSCAPS2_CUBEMAP_POSITIVEX... face flags.
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFE00;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdsTexture {
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub cubemap: bool,
+    // For each face every mip level from the largest, with rows from top to bottom, as
+    // `vec3_to_rgb9e5` values. Cubemap faces are in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub levels: Vec<Vec<u32>>,
+}
+
+#[derive(Debug)]
+pub enum DdsError {
+    Io(io::Error),
+    Magic,
+    Header(&'static str),
+    // The DXGI format, when it isn't DXGI_FORMAT_R9G9B9E5_SHAREDEXP.
+    Format(u32),
+}
+
+impl fmt::Display for DdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DdsError::Io(error) => write!(f, "{error}"),
+            DdsError::Magic => write!(f, "not a dds file"),
+            DdsError::Header(problem) => write!(f, "unsupported dds header, {problem}"),
+            DdsError::Format(format) => write!(f, "dxgi format {format} isn't rgb9e5"),
+        }
+    }
+}
+
+impl std::error::Error for DdsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DdsError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DdsError {
+    fn from(error: io::Error) -> Self {
+        DdsError::Io(error)
+    }
+}
+
+// The size of mip level `level` of a texture `size` texels wide or high.
+pub const fn mip_size(size: u32, level: u32) -> u32 {
+    if level >= 32 || size >> level == 0 {
+        1
+    } else {
+        size >> level
+    }
+}
+
+// The length of a full mip chain, down to 1x1.
+pub const fn max_mip_levels(width: u32, height: u32) -> u32 {
+    let size = if width > height { width } else { height };
+    32 - size.leading_zeros()
+}
+
+impl DdsTexture {
+    // A 2D texture with one level.
+    pub fn new(width: u32, height: u32, texels: Vec<u32>) -> Self {
+        DdsTexture {
+            width,
+            height,
+            mip_levels: 1,
+            cubemap: false,
+            levels: vec![texels],
+        }
+    }
+
+    pub fn faces(&self) -> u32 {
+        if self.cubemap {
+            6
+        } else {
+            1
+        }
+    }
+
+    pub fn level(&self, face: u32, level: u32) -> &[u32] {
+        &self.levels[(face * self.mip_levels + level) as usize]
+    }
+
+    fn check(&self) -> io::Result<()> {
+        let invalid = |problem| Err(io::Error::new(io::ErrorKind::InvalidInput, problem));
+        if self.width == 0 || self.height == 0 {
+            return invalid("empty texture");
+        }
+        if self.cubemap && self.width != self.height {
+            return invalid("cubemap faces aren't square");
+        }
+        if self.mip_levels == 0 || self.mip_levels > max_mip_levels(self.width, self.height) {
+            return invalid("invalid mip count");
+        }
+        if self.levels.len() as u32 != self.faces() * self.mip_levels {
+            return invalid("wrong number of levels");
+        }
+        for (i, texels) in self.levels.iter().enumerate() {
+            let level = i as u32 % self.mip_levels;
+            if Some(texels.len()) != level_texels(self.width, self.height, level) {
+                return invalid("level doesn't match its size");
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn write_dds(mut writer: impl Write, texture: &DdsTexture) -> io::Result<()> {
+    texture.check()?;
+    let mipmapped = texture.mip_levels > 1;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT;
+    let mut caps = DDSCAPS_TEXTURE;
+    if mipmapped {
+        flags |= DDSD_MIPMAPCOUNT;
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    let (caps2, misc_flag) = if texture.cubemap {
+        caps |= DDSCAPS_COMPLEX;
+        (DDSCAPS2_CUBEMAP_ALL_FACES, D3D10_RESOURCE_MISC_TEXTURECUBE)
+    } else {
+        (0, 0)
+    };
+    let pitch = texture.width * TextureFormat::Rgb9e5Ufloat.texel_size();
+
+    let mut header = vec![
+        MAGIC,
+        HEADER_SIZE,
+        flags,
+        texture.height,
+        texture.width,
+        pitch,
+        0,
+        texture.mip_levels,
+    ];
+    header.extend([0; 11]);
+    header.extend([PIXEL_FORMAT_SIZE, DDPF_FOURCC, DX10, 0, 0, 0, 0, 0]);
+    header.extend([caps, caps2, 0, 0, 0]);
+    // The DX10 extension. The array size counts cubes, not faces.
+    header.extend([
+        TextureFormat::Rgb9e5Ufloat.dxgi_format(),
+        D3D10_RESOURCE_DIMENSION_TEXTURE2D,
+        misc_flag,
+        1,
+        0,
+    ]);
+
+    let bytes: Vec<u8> = header
+        .iter()
+        .chain(texture.levels.iter().flatten())
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    writer.write_all(&bytes)
+}
+
+// None when the texel count doesn't fit in memory.
+fn level_texels(width: u32, height: u32, level: u32) -> Option<usize> {
+    (mip_size(width, level) as usize)
+        .checked_mul(mip_size(height, level) as usize)
+        .filter(|texels| texels.checked_mul(4).is_some())
+}
+
+// Through `take`, so a corrupt header can't allocate more than the file holds.
+fn read_words(reader: &mut impl Read, len: usize) -> io::Result<Vec<u32>> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64 * 4).read_to_end(&mut bytes)?;
+    if bytes.len() != len * 4 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .collect())
+}
+
+pub fn read_dds(mut reader: impl Read) -> Result<DdsTexture, DdsError> {
+    let header = read_words(&mut reader, 1 + HEADER_SIZE as usize / 4)?;
+    if header[0] != MAGIC {
+        return Err(DdsError::Magic);
+    }
+    // Indices of the fields, after the magic.
+    let [size, flags, height, width] = [1, 2, 3, 4].map(|i| header[i]);
+    let mip_count = header[7];
+    let [pixel_format_flags, four_cc] = [20, 21].map(|i| header[i]);
+    let caps2 = header[28];
+    if size != HEADER_SIZE {
+        return Err(DdsError::Header("wrong header size"));
+    }
+    if pixel_format_flags & DDPF_FOURCC == 0 || four_cc != DX10 {
+        return Err(DdsError::Header("no DX10 header"));
+    }
+
+    let [dxgi_format, dimension, misc_flag, array_size] =
+        read_words(&mut reader, 5)?[..4].try_into().unwrap();
+    if dxgi_format != TextureFormat::Rgb9e5Ufloat.dxgi_format() {
+        return Err(DdsError::Format(dxgi_format));
+    }
+    if dimension != D3D10_RESOURCE_DIMENSION_TEXTURE2D {
+        return Err(DdsError::Header("not a 2D texture"));
+    }
+    if array_size != 1 {
+        return Err(DdsError::Header("texture arrays aren't supported"));
+    }
+    let cubemap = misc_flag & D3D10_RESOURCE_MISC_TEXTURECUBE != 0
+        || caps2 & DDSCAPS2_CUBEMAP_ALL_FACES == DDSCAPS2_CUBEMAP_ALL_FACES;
+    // Writers often leave the count at 0 for a single level.
+    let mip_levels = if flags & DDSD_MIPMAPCOUNT != 0 {
+        mip_count.max(1)
+    } else {
+        1
+    };
+    if width == 0 || height == 0 || mip_levels > max_mip_levels(width, height) {
+        return Err(DdsError::Header("invalid size or mip count"));
+    }
+    if cubemap && width != height {
+        return Err(DdsError::Header("cubemap faces aren't square"));
+    }
+
+    let faces = if cubemap { 6 } else { 1 };
+    let mut levels = Vec::new();
+    for _ in 0..faces {
+        for level in 0..mip_levels {
+            let size =
+                level_texels(width, height, level).ok_or(DdsError::Header("texture too large"))?;
+            levels.push(read_words(&mut reader, size)?);
+        }
+    }
+
+    Ok(DdsTexture {
+        width,
+        height,
+        mip_levels,
+        cubemap,
+        levels,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+    use crate::rgb9e5::vec3_to_rgb9e5;
+
+    fn to_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    // The header fields as the DDS documentation lists them, after "DDS ".
+    #[allow(clippy::too_many_arguments)]
+    fn header(
+        flags: u32,
+        height: u32,
+        width: u32,
+        mip_count: u32,
+        caps: u32,
+        caps2: u32,
+        dxgi_format: u32,
+        misc_flag: u32,
+    ) -> Vec<u32> {
+        let mut words = vec![
+            0x20534444,
+            124,
+            flags,
+            height,
+            width,
+            width * 4,
+            0,
+            mip_count,
+        ];
+        words.extend([0; 11]);
+        words.extend([32, 4, 0x30315844, 0, 0, 0, 0, 0]);
+        words.extend([caps, caps2, 0, 0, 0]);
+        words.extend([dxgi_format, 3, misc_flag, 1, 0]);
+        words
+    }
+
+    fn random_texels(len: u32) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..len)
+            .map(|_| vec3_to_rgb9e5([rng.gen(), rng.gen(), rng.gen::<f32>() * 100.0]))
+            .collect()
+    }
+
+    #[test]
+    fn test_2d() {
+        let texels = random_texels(12);
+        let texture = DdsTexture::new(4, 3, texels.clone());
+        let mut file = Vec::new();
+        write_dds(&mut file, &texture).unwrap();
+
+        let mut expected = header(0x100F, 3, 4, 1, 0x1000, 0, 67, 0);
+        expected.extend(&texels);
+        assert_eq!(file, to_bytes(&expected));
+        assert_eq!(read_dds(&file[..]).unwrap(), texture);
+
+        // A mip count of 0 without the flag, as some writers do.
+        let mut other = header(0x7, 3, 4, 0, 0x1000, 0, 67, 0);
+        other.extend(&texels);
+        assert_eq!(read_dds(&to_bytes(&other)[..]).unwrap(), texture);
+    }
+
+    #[test]
+    fn test_mips() {
+        // 5x2, 2x1 and 1x1.
+        let levels = vec![random_texels(10), random_texels(2), random_texels(1)];
+        let texture = DdsTexture {
+            width: 5,
+            height: 2,
+            mip_levels: 3,
+            cubemap: false,
+            levels: levels.clone(),
+        };
+        let mut file = Vec::new();
+        write_dds(&mut file, &texture).unwrap();
+
+        let mut expected = header(0x2100F, 2, 5, 3, 0x401008, 0, 67, 0);
+        expected.extend(levels.iter().flatten());
+        assert_eq!(file, to_bytes(&expected));
+        let read = read_dds(&file[..]).unwrap();
+        assert_eq!(read, texture);
+        assert_eq!(read.level(0, 2), &levels[2][..]);
+    }
+
+    #[test]
+    fn test_cubemap() {
+        // Every face has 2x2 and 1x1, the faces one after the other.
+        let levels: Vec<Vec<u32>> = (0..12).map(|i| random_texels(4 >> (i % 2 * 2))).collect();
+        let texture = DdsTexture {
+            width: 2,
+            height: 2,
+            mip_levels: 2,
+            cubemap: true,
+            levels: levels.clone(),
+        };
+        let mut file = Vec::new();
+        write_dds(&mut file, &texture).unwrap();
+
+        let mut expected = header(0x2100F, 2, 2, 2, 0x401008, 0xFE00, 67, 4);
+        expected.extend(levels.iter().flatten());
+        assert_eq!(file, to_bytes(&expected));
+        let read = read_dds(&file[..]).unwrap();
+        assert_eq!(read, texture);
+        assert_eq!(read.faces(), 6);
+        assert_eq!(read.level(5, 1), &levels[11][..]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let read = |words: &[u32]| read_dds(&to_bytes(words)[..]).unwrap_err();
+        let mut texture = header(0x100F, 1, 1, 1, 0x1000, 0, 67, 0);
+        texture.push(0);
+        assert!(read_dds(&to_bytes(&texture)[..]).is_ok());
+
+        let mut wrong = texture.clone();
+        wrong[0] = 0;
+        assert!(matches!(read(&wrong), DdsError::Magic));
+        let mut wrong = texture.clone();
+        // DXGI_FORMAT_R32_UINT.
+        wrong[32] = 42;
+        assert!(matches!(read(&wrong), DdsError::Format(42)));
+        let mut wrong = texture.clone();
+        wrong[21] = u32::from_le_bytes(*b"DXT1");
+        assert!(matches!(read(&wrong), DdsError::Header(_)));
+        let mut wrong = texture.clone();
+        wrong[2] |= 0x20000;
+        wrong[7] = 2;
+        assert!(matches!(read(&wrong), DdsError::Header(_)));
+        assert!(matches!(
+            read(&texture[..texture.len() - 1]),
+            DdsError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        // Sizes far larger than the file, and sizes whose bytes overflow.
+        let mut wrong = texture.clone();
+        wrong[3..5].fill(1 << 30);
+        if usize::BITS == 64 {
+            assert!(matches!(
+                read(&wrong),
+                DdsError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+            ));
+        }
+        wrong[3..5].fill(u32::MAX);
+        assert!(matches!(read(&wrong), DdsError::Header(_)));
+
+        let mut texture = DdsTexture::new(2, 2, vec![0; 4]);
+        texture.levels[0].pop();
+        let error = write_dds(Vec::new(), &texture).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let texture = DdsTexture::new(0, 0, Vec::new());
+        let error = write_dds(Vec::new(), &texture).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_mip_size() {
+        assert_eq!(max_mip_levels(1, 1), 1);
+        assert_eq!(max_mip_levels(5, 2), 3);
+        assert_eq!(max_mip_levels(256, 1), 9);
+        assert_eq!(mip_size(5, 1), 2);
+        assert_eq!(mip_size(5, 3), 1);
+        assert_eq!(mip_size(u32::MAX, 40), 1);
+    }
+}
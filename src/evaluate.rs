@@ -68,15 +68,13 @@ pub mod test_util {
                         report.max_abs_error_decoded = decoded;
                     }
 
-                    let a = Vec3::from(orig);
-                    let b = Vec3::from(decoded);
+                    let a = orig;
+                    let b = decoded;
                     let nor_dist = a.normalize_or_zero().distance(b.normalize_or_zero());
-                    if b.normalize_or_zero().length() != 0.0 {
-                        if nor_dist > report.max_nor_dist {
-                            report.max_nor_dist = nor_dist;
-                            report.max_nor_dist_orig = orig;
-                            report.max_nor_dist_decoded = decoded;
-                        }
+                    if b.normalize_or_zero().length() != 0.0 && nor_dist > report.max_nor_dist {
+                        report.max_nor_dist = nor_dist;
+                        report.max_nor_dist_orig = orig;
+                        report.max_nor_dist_decoded = decoded;
                     }
 
                     let dist = a.distance(b);
@@ -88,7 +86,7 @@ pub mod test_util {
                 }
             }
             report.avg_dist /= iterations as f32;
-            return report;
+            report
         }
 
         pub fn print(&self) {
@@ -124,7 +122,7 @@ pub mod test_util {
         for i in 1..n {
             let mut n = i as f32 * 0.25;
             n = n.exp2() - 1.0;
-            let report = Report::new(n, DEFUALT_ITERATIONS, false, |v| proc(v));
+            let report = Report::new(n, DEFUALT_ITERATIONS, false, &proc);
             set.push((n, report));
         }
         set
@@ -135,12 +133,11 @@ pub mod test_util {
         F: Fn(Vec3) -> Vec3,
     {
         let mut set = Vec::new();
-        for i in 0..6 {
-            let n = POWLUT[i];
+        for &n in POWLUT.iter().take(6) {
             if n > max {
                 break;
             }
-            let report = Report::new(n, iterations, signed, |v| proc(v));
+            let report = Report::new(n, iterations, signed, &proc);
             set.push((n, report));
         }
         set
@@ -233,33 +230,17 @@ pub mod print_table {
             max,
             epsilon,
             signed,
-            n01maxd: typ
-                .get(0)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
-            np1maxd: typ
-                .get(1)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
-            n1maxd: typ
-                .get(2)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
-            n10maxd: typ
-                .get(3)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
-            n100maxd: typ
-                .get(4)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
-            n1000maxd: typ
-                .get(5)
-                .and_then(|r| Some(r.1.max_dist))
-                .unwrap_or(f32::INFINITY),
+            n01maxd: typ.first().map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
+            np1maxd: typ.get(1).map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
+            n1maxd: typ.get(2).map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
+            n10maxd: typ.get(3).map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
+            n100maxd: typ.get(4).map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
+            n1000maxd: typ.get(5).map(|r| r.1.max_dist).unwrap_or(f32::INFINITY),
         }
     }
 
+    // The 8unorm epsilon is written out to more digits than an f32 holds.
+    #[allow(clippy::excessive_precision)]
     pub fn print_full_table() {
         let iters = DEFUALT_ITERATIONS * 100;
 
@@ -303,7 +284,7 @@ pub mod print_table {
             half::f16::MAX.into(),
             half::f16::EPSILON.into(),
             true,
-            typ_ranges(iters, half::f16::MAX.into(), true, |v| f16_roundtrip(v)),
+            typ_ranges(iters, half::f16::MAX.into(), true, f16_roundtrip),
         ));
 
         table.push(row(
@@ -345,7 +326,7 @@ pub mod print_table {
             1.0,
             0.00392156862745098,
             false,
-            typ_ranges(iters, 1.0, false, |v| unorm8_roundtrip(v)),
+            typ_ranges(iters, 1.0, false, unorm8_roundtrip),
         ));
 
         println!(
@@ -355,7 +336,7 @@ pub mod print_table {
                     Modify::new(Columns::new(5..)).with(Format::positioned(|s, p| if s == "inf" {
                         String::new()
                     } else if p.0 == 0 {
-                        format!("{}", s)
+                        s.to_string()
                     } else {
                         let v = s.parse::<f32>().unwrap();
                         format!("{:.2e}", v).replace("e0", "")
@@ -367,7 +348,7 @@ pub mod print_table {
                             let v = s.parse::<f32>().unwrap();
                             format!("{:.2e}", v)
                         } else {
-                            format!("{}", s)
+                            s.to_string()
                         }
                     }))
                 )
@@ -377,12 +358,11 @@ pub mod print_table {
                             let v = s.parse::<f32>().unwrap();
                             format!("{:.2e}", v)
                         } else {
-                            format!("{}", s)
+                            s.to_string()
                         }
                     }))
                 )
                 .with(Style::markdown())
-                .to_string()
         );
     }
 }
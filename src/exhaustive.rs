@@ -0,0 +1,450 @@
+// Checks every one of the 2^32 codes of the 32 bit formats. This takes around 8 minutes per
+// format on one core with optimizations, so the tests covering all codes are ignored and run with
+// `cargo test --release -- --ignored exhaustive`.
+//
+// For every code the decoded vector is encoded again, which gives the canonical code for that
+// vector. That code has to decode to exactly the same bits and encode to itself. Canonical codes
+// are collected per exponent to check that the largest component of every code with a larger
+// exponent is larger. Together with the fields decoding to distinct values within an exponent,
+// this means no two canonical codes decode to the same vector. -0.0 and 0.0 are different
+// vectors here, as the signed formats keep the sign. `canonicalize_*` has to give that same code.
+
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use crate::{rgb9e5, xyz8e5, xyz9e2};
+
+// Only this many violations are kept, `Report::violation_count` has the total.
+pub const MAX_VIOLATIONS: usize = 64;
+
+const CHUNK: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Format32 {
+    pub name: &'static str,
+    pub encode: fn([f32; 3]) -> u32,
+    pub decode: fn(u32) -> [f32; 3],
+    pub canonicalize: fn(u32) -> u32,
+    pub exponent_offset: u32,
+    // The mantissa and sign bits of each component, which are next to each other from bit 0.
+    pub field_bits: u32,
+}
+
+pub const RGB9E5: Format32 = Format32 {
+    name: rgb9e5::NAME,
+    encode: rgb9e5::vec3_to_rgb9e5,
+    decode: rgb9e5::rgb9e5_to_vec3,
+    canonicalize: rgb9e5::canonicalize_rgb9e5,
+    exponent_offset: 27,
+    field_bits: 9,
+};
+
+pub const XYZ8E5: Format32 = Format32 {
+    name: xyz8e5::NAME,
+    encode: xyz8e5::vec3_to_xyz8e5,
+    decode: xyz8e5::xyz8e5_to_vec3,
+    canonicalize: xyz8e5::canonicalize_xyz8e5,
+    exponent_offset: 27,
+    field_bits: 9,
+};
+
+pub const XYZ9E2: Format32 = Format32 {
+    name: xyz9e2::NAME,
+    encode: xyz9e2::vec3_to_xyz9e2,
+    decode: xyz9e2::xyz9e2_to_vec3,
+    canonicalize: xyz9e2::canonicalize_xyz9e2,
+    exponent_offset: 30,
+    field_bits: 10,
+};
+
+pub const FORMATS: [Format32; 3] = [RGB9E5, XYZ8E5, XYZ9E2];
+
+impl Format32 {
+    fn exponent(&self, code: u32) -> usize {
+        (code >> self.exponent_offset) as usize
+    }
+
+    fn exponents(&self) -> usize {
+        1 << (32 - self.exponent_offset)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    // `code` decodes to a vector that encodes to `canonical`, which doesn't decode to the same
+    // bits or doesn't encode to itself (`reencoded`).
+    RoundTrip {
+        code: u32,
+        canonical: u32,
+        reencoded: u32,
+    },
+    // `canonicalize` gives `canonicalized` for `code` rather than `expected`, the encoded value.
+    Canonicalize {
+        code: u32,
+        expected: u32,
+        canonicalized: u32,
+    },
+    // The canonical code `below` has a smaller exponent than `above`, but its largest component
+    // isn't smaller.
+    Order {
+        below: u32,
+        above: u32,
+    },
+    // Two codes with the same exponent decode a field to the same value.
+    Duplicate {
+        a: u32,
+        b: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub format: Format32,
+    pub codes: u64,
+    pub canonical: u64,
+    pub violation_count: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    fn new(format: Format32) -> Self {
+        Report {
+            format,
+            codes: 0,
+            canonical: 0,
+            violation_count: 0,
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.violation_count == 0
+    }
+
+    fn push(&mut self, violation: Violation) {
+        self.violation_count += 1;
+        if self.violations.len() < MAX_VIOLATIONS {
+            self.violations.push(violation);
+        }
+    }
+}
+
+// Prints the codes in hex along with what they decode to, with the bits of each f32.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} codes, {} canonical, {} violations",
+            self.format.name, self.codes, self.canonical, self.violation_count
+        )?;
+        let code = |f: &mut fmt::Formatter<'_>, name: &str, code: u32| {
+            let v = (self.format.decode)(code);
+            let [x, y, z] = bits3(v);
+            writeln!(
+                f,
+                "  {name} {code:#010X} -> {v:?} ({x:#010X}, {y:#010X}, {z:#010X})"
+            )
+        };
+        for violation in &self.violations {
+            match *violation {
+                Violation::RoundTrip {
+                    code: c,
+                    canonical,
+                    reencoded,
+                } => {
+                    writeln!(f, "round trip:")?;
+                    code(f, "code     ", c)?;
+                    code(f, "canonical", canonical)?;
+                    code(f, "reencoded", reencoded)?;
+                }
+                Violation::Canonicalize {
+                    code: c,
+                    expected,
+                    canonicalized,
+                } => {
+                    writeln!(f, "canonicalize:")?;
+                    code(f, "code         ", c)?;
+                    code(f, "expected     ", expected)?;
+                    code(f, "canonicalized", canonicalized)?;
+                }
+                Violation::Order { below, above } => {
+                    writeln!(f, "order:")?;
+                    code(f, "below", below)?;
+                    code(f, "above", above)?;
+                }
+                Violation::Duplicate { a, b } => {
+                    writeln!(f, "duplicate:")?;
+                    code(f, "a", a)?;
+                    code(f, "b", b)?;
+                }
+            }
+        }
+        if self.violations.len() < self.violation_count as usize {
+            writeln!(f, "  ...")?;
+        }
+        Ok(())
+    }
+}
+
+// The canonical codes with the smallest and largest max component for one exponent.
+#[derive(Debug, Clone, Copy)]
+struct ExponentRange {
+    min: f32,
+    min_code: u32,
+    max: f32,
+    max_code: u32,
+}
+
+fn max_abs(v: [f32; 3]) -> f32 {
+    v[0].abs().max(v[1].abs()).max(v[2].abs())
+}
+
+fn bits3(v: [f32; 3]) -> [u32; 3] {
+    v.map(f32::to_bits)
+}
+
+fn check_range(
+    format: &Format32,
+    codes: Range<u64>,
+    report: &mut Report,
+    ranges: &mut [Option<ExponentRange>],
+) {
+    for code in codes {
+        let code = code as u32;
+        report.codes += 1;
+        let decoded = (format.decode)(code);
+        let canonical = (format.encode)(decoded);
+        let redecoded = (format.decode)(canonical);
+        let reencoded = (format.encode)(redecoded);
+        if bits3(redecoded) != bits3(decoded) || reencoded != canonical {
+            report.push(Violation::RoundTrip {
+                code,
+                canonical,
+                reencoded,
+            });
+            continue;
+        }
+        let canonicalized = (format.canonicalize)(code);
+        if canonicalized != canonical {
+            report.push(Violation::Canonicalize {
+                code,
+                expected: canonical,
+                canonicalized,
+            });
+        }
+        if code != canonical {
+            continue;
+        }
+
+        report.canonical += 1;
+        let m = max_abs(decoded);
+        let range = ranges[format.exponent(code)].get_or_insert(ExponentRange {
+            min: m,
+            min_code: code,
+            max: m,
+            max_code: code,
+        });
+        if m < range.min {
+            range.min = m;
+            range.min_code = code;
+        }
+        if m > range.max {
+            range.max = m;
+            range.max_code = code;
+        }
+    }
+}
+
+fn check_order(ranges: &[Option<ExponentRange>], report: &mut Report) {
+    let ranges: Vec<ExponentRange> = ranges.iter().flatten().copied().collect();
+    for pair in ranges.windows(2) {
+        if pair[0].max >= pair[1].min {
+            report.push(Violation::Order {
+                below: pair[0].max_code,
+                above: pair[1].min_code,
+            });
+        }
+    }
+}
+
+fn check_fields(format: &Format32, report: &mut Report) {
+    for exponent in 0..format.exponents() as u32 {
+        for field in 0..3 {
+            let offset = field * format.field_bits;
+            let mut values: Vec<(u32, u32)> = (0..1 << format.field_bits)
+                .map(|bits| {
+                    let code = exponent << format.exponent_offset | bits << offset;
+                    ((format.decode)(code)[field as usize].to_bits(), code)
+                })
+                .collect();
+            values.sort_unstable();
+            for pair in values.windows(2) {
+                if pair[0].0 == pair[1].0 {
+                    report.push(Violation::Duplicate {
+                        a: pair[0].1,
+                        b: pair[1].1,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Checks the codes in `codes`, split over `threads` threads. The order is only checked between
+// exponents that have canonical codes in the range.
+pub fn verify_range(format: &Format32, codes: Range<u64>, threads: usize) -> Report {
+    let next = AtomicU64::new(codes.start);
+    let results: Vec<(Report, Vec<Option<ExponentRange>>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut report = Report::new(*format);
+                    let mut ranges = vec![None; format.exponents()];
+                    loop {
+                        let start = next.fetch_add(CHUNK, Ordering::Relaxed);
+                        if start >= codes.end {
+                            break;
+                        }
+                        let end = (start + CHUNK).min(codes.end);
+                        check_range(format, start..end, &mut report, &mut ranges);
+                    }
+                    (report, ranges)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut report = Report::new(*format);
+    let mut ranges: Vec<Option<ExponentRange>> = vec![None; format.exponents()];
+    for (thread_report, thread_ranges) in results {
+        report.codes += thread_report.codes;
+        report.canonical += thread_report.canonical;
+        report.violation_count += thread_report.violation_count;
+        let space = MAX_VIOLATIONS - report.violations.len();
+        report
+            .violations
+            .extend(thread_report.violations.into_iter().take(space));
+        for (range, thread_range) in ranges.iter_mut().zip(thread_ranges) {
+            *range = match (*range, thread_range) {
+                (Some(a), Some(b)) => Some(ExponentRange {
+                    min: a.min.min(b.min),
+                    min_code: if b.min < a.min {
+                        b.min_code
+                    } else {
+                        a.min_code
+                    },
+                    max: a.max.max(b.max),
+                    max_code: if b.max > a.max {
+                        b.max_code
+                    } else {
+                        a.max_code
+                    },
+                }),
+                (a, b) => a.or(b),
+            };
+        }
+    }
+    check_order(&ranges, &mut report);
+    check_fields(format, &mut report);
+    report
+}
+
+// Checks all 2^32 codes, using all available threads.
+pub fn verify(format: &Format32) -> Report {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    verify_range(format, 0..1 << 32, threads)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use crate::rgb9e5::{rgb9e5_to_vec3, vec3_to_rgb9e5};
+
+    use super::*;
+
+    #[test]
+    fn test_first_and_last_codes() {
+        for format in FORMATS {
+            for codes in [0..1 << 22, (1 << 32) - (1 << 22)..1 << 32] {
+                let report = verify_range(&format, codes, 2);
+                assert!(report.is_ok(), "{report}");
+                assert_eq!(report.codes, 1 << 22);
+            }
+        }
+    }
+
+    #[test]
+    fn test_violations() {
+        // Flipping the lowest mantissa bit means nothing round trips.
+        let format = Format32 {
+            encode: |v| vec3_to_rgb9e5(v) ^ 1,
+            ..RGB9E5
+        };
+        let report = verify_range(&format, 0..1 << 10, 1);
+        assert_eq!(report.violation_count, 1 << 10);
+        assert_eq!(report.violations.len(), MAX_VIOLATIONS);
+        assert_eq!(
+            report.violations[0],
+            Violation::RoundTrip {
+                code: 0,
+                canonical: 1,
+                reencoded: 0
+            }
+        );
+        assert!(report
+            .to_string()
+            .contains("0x00000001 -> [5.9604645e-8, 0.0, 0.0]"));
+
+        // Zero with exponent 1 isn't canonical.
+        let format = Format32 {
+            canonicalize: |code| code,
+            ..XYZ8E5
+        };
+        let report = verify_range(&format, 1 << 27..(1 << 27) + 1, 1);
+        assert_eq!(
+            report.violations,
+            [Violation::Canonicalize {
+                code: 1 << 27,
+                expected: 0,
+                canonicalized: 1 << 27
+            }]
+        );
+
+        // Ignoring the lowest mantissa bit makes pairs of codes decode the same.
+        let format = Format32 {
+            decode: |code| rgb9e5_to_vec3(code & !1),
+            ..RGB9E5
+        };
+        let report = verify_range(&format, 0..0, 1);
+        assert_eq!(report.violation_count, 32 * 256);
+        assert_eq!(report.violations[0], Violation::Duplicate { a: 0, b: 1 });
+        assert_eq!(report.violations[1], Violation::Duplicate { a: 2, b: 3 });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exhaustive_rgb9e5() {
+        let report = verify(&RGB9E5);
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.codes, 1 << 32);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exhaustive_xyz8e5() {
+        let report = verify(&XYZ8E5);
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.codes, 1 << 32);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_exhaustive_xyz9e2() {
+        let report = verify(&XYZ9E2);
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.codes, 1 << 32);
+    }
+}
@@ -0,0 +1,220 @@
+// Conversions between the formats and IEEE half floats, as the u16 bit patterns of RGB16F and
+// RGBA16F textures. Implemented with bit operations, so no f16 type is needed. Halves are
+// converted to f32 exactly and encoded as usual. The other way, rgb9e5 and xyz8e5 values are
+// all exact halves, and xyz13e6 is rounded to nearest even like a gpu stores f16. Values past the
+// half range saturate at ±65504 instead of becoming infinite.
+
+use crate::math::pow2f;
+use crate::rgb9e5::{rgb9e5_to_vec3, vec3_to_rgb9e5};
+use crate::xyz13e6::{vec3_to_xyz13e6, xyz13e6_to_vec3};
+use crate::xyz8e5::{vec3_to_xyz8e5, xyz8e5_to_vec3};
+
+pub const F16_MAX: u16 = 0x7BFF;
+pub const F16_ONE: u16 = 0x3C00;
+
+#[inline]
+pub const fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let exponent = ((h >> 10) & 0x1F) as u32;
+    let mantissa = (h & 0x3FF) as u32;
+    if exponent == 0 {
+        // Zero and subnormals are mantissa * 2^-24.
+        f32::from_bits(sign | (mantissa as f32 * pow2f(-24)).to_bits())
+    } else if exponent == 31 {
+        f32::from_bits(sign | 0x7F800000 | mantissa << 13)
+    } else {
+        f32::from_bits(sign | (exponent + 112) << 23 | mantissa << 13)
+    }
+}
+
+// v >> shift, rounded to nearest with ties to even.
+const fn shift_round_even(v: u32, shift: u32) -> u32 {
+    let q = v >> shift;
+    let r = v & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    q + (r > half || (r == half && q & 1 == 1)) as u32
+}
+
+// Rounds to the nearest half, with ties to even, except that values past F16_MAX (including
+// infinity) saturate to it. NaN stays NaN.
+#[inline]
+pub const fn f32_to_f16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let abs = bits & 0x7FFFFFFF;
+    if abs > 0x7F800000 {
+        sign | 0x7E00
+    } else if abs >= 0x477FF000 {
+        // 65520.0, the first value that rounds to infinity.
+        sign | F16_MAX
+    } else if abs >= 0x38800000 {
+        // Normal halves, from 2^-14 up. Rounding can carry into the exponent.
+        sign | shift_round_even(abs - (112 << 23), 13) as u16
+    } else if abs >= 0x33000000 {
+        // Subnormal halves in units of 2^-24, from 2^-25 which rounds to 0 or up.
+        let exponent = abs >> 23;
+        let mantissa = (abs & 0x7FFFFF) | 0x800000;
+        sign | shift_round_even(mantissa, 126 - exponent) as u16
+    } else {
+        sign
+    }
+}
+
+#[inline]
+pub const fn f16_to_vec3(h: [u16; 3]) -> [f32; 3] {
+    [f16_to_f32(h[0]), f16_to_f32(h[1]), f16_to_f32(h[2])]
+}
+
+#[inline]
+pub const fn vec3_to_f16(xyz: [f32; 3]) -> [u16; 3] {
+    [f32_to_f16(xyz[0]), f32_to_f16(xyz[1]), f32_to_f16(xyz[2])]
+}
+
+// The four channel versions write alpha as 1.0 and ignore it when reading.
+const fn with_alpha(h: [u16; 3]) -> [u16; 4] {
+    [h[0], h[1], h[2], F16_ONE]
+}
+
+const fn without_alpha(h: [u16; 4]) -> [u16; 3] {
+    [h[0], h[1], h[2]]
+}
+
+#[inline]
+pub const fn rgb9e5_to_f16(v: u32) -> [u16; 3] {
+    vec3_to_f16(rgb9e5_to_vec3(v))
+}
+
+#[inline]
+pub const fn rgb9e5_to_f16x4(v: u32) -> [u16; 4] {
+    with_alpha(rgb9e5_to_f16(v))
+}
+
+#[inline]
+pub const fn f16_to_rgb9e5(h: [u16; 3]) -> u32 {
+    vec3_to_rgb9e5(f16_to_vec3(h))
+}
+
+#[inline]
+pub const fn f16x4_to_rgb9e5(h: [u16; 4]) -> u32 {
+    f16_to_rgb9e5(without_alpha(h))
+}
+
+#[inline]
+pub const fn xyz8e5_to_f16(v: u32) -> [u16; 3] {
+    vec3_to_f16(xyz8e5_to_vec3(v))
+}
+
+#[inline]
+pub const fn xyz8e5_to_f16x4(v: u32) -> [u16; 4] {
+    with_alpha(xyz8e5_to_f16(v))
+}
+
+#[inline]
+pub const fn f16_to_xyz8e5(h: [u16; 3]) -> u32 {
+    vec3_to_xyz8e5(f16_to_vec3(h))
+}
+
+#[inline]
+pub const fn f16x4_to_xyz8e5(h: [u16; 4]) -> u32 {
+    f16_to_xyz8e5(without_alpha(h))
+}
+
+#[inline]
+pub const fn xyz13e6_to_f16(v: (u32, u16)) -> [u16; 3] {
+    vec3_to_f16(xyz13e6_to_vec3(v))
+}
+
+#[inline]
+pub const fn xyz13e6_to_f16x4(v: (u32, u16)) -> [u16; 4] {
+    with_alpha(xyz13e6_to_f16(v))
+}
+
+#[inline]
+pub const fn f16_to_xyz13e6(h: [u16; 3]) -> (u32, u16) {
+    vec3_to_xyz13e6(f16_to_vec3(h))
+}
+
+#[inline]
+pub const fn f16x4_to_xyz13e6(h: [u16; 4]) -> (u32, u16) {
+    f16_to_xyz13e6(without_alpha(h))
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use half::f16;
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_f16_to_f32() {
+        for h in 0..=u16::MAX {
+            let expected = f16::from_bits(h).to_f32();
+            let x = f16_to_f32(h);
+            assert!(x.to_bits() == expected.to_bits() || x.is_nan() && expected.is_nan());
+        }
+    }
+
+    #[test]
+    fn test_f32_to_f16() {
+        let mut rng = rand::thread_rng();
+        let edges = [
+            65504.0,
+            65519.99,
+            65520.0,
+            f32::INFINITY,
+            2.0f32.powi(-25),
+            1e-8,
+            0.0,
+        ];
+        let random = (0..1000000).map(|_| f32::from_bits(rng.gen()));
+        for x in edges.into_iter().flat_map(|x| [x, -x]).chain(random) {
+            let h = f32_to_f16(x);
+            let expected = f16::from_f32(x);
+            if x.is_nan() {
+                assert!(f16::from_bits(h).is_nan());
+            } else if expected.is_infinite() {
+                assert_eq!(h, F16_MAX | (x.is_sign_negative() as u16) << 15, "{x}");
+            } else {
+                assert_eq!(h, expected.to_bits(), "{x:e}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            // rgb9e5 and xyz8e5 are exact halves.
+            let v = rng.gen();
+            assert_eq!(f16_to_vec3(rgb9e5_to_f16(v)), rgb9e5_to_vec3(v));
+            let v = rng.gen();
+            let h = xyz8e5_to_f16(v);
+            assert_eq!(
+                f16_to_vec3(h).map(f32::to_bits),
+                xyz8e5_to_vec3(v).map(f32::to_bits)
+            );
+            assert_eq!(f16_to_xyz8e5(h), vec3_to_xyz8e5(xyz8e5_to_vec3(v)));
+
+            let v = (rng.gen(), rng.gen());
+            let expected = xyz13e6_to_vec3(v).map(|x| f16::from_f32(x).clamp(f16::MIN, f16::MAX));
+            assert_eq!(xyz13e6_to_f16(v), expected.map(f16::to_bits));
+
+            let h = [rng.gen(), rng.gen(), rng.gen()];
+            let xyz = h.map(|h| f16::from_bits(h).to_f32());
+            assert_eq!(f16_to_rgb9e5(h), vec3_to_rgb9e5(xyz));
+            assert_eq!(f16_to_xyz8e5(h), vec3_to_xyz8e5(xyz));
+            assert_eq!(f16_to_xyz13e6(h), vec3_to_xyz13e6(xyz));
+        }
+
+        let xyz = [1e6, -1e6, 1e-9];
+        assert_eq!(
+            xyz13e6_to_f16x4(vec3_to_xyz13e6(xyz)),
+            [F16_MAX, F16_MAX | 0x8000, 0, F16_ONE]
+        );
+        let h = rgb9e5_to_f16x4(vec3_to_rgb9e5([1.0, 0.5, 64.0]));
+        assert_eq!(h, [F16_ONE, 0x3800, 0x5400, F16_ONE]);
+        assert_eq!(f16x4_to_rgb9e5(h), vec3_to_rgb9e5([1.0, 0.5, 64.0]));
+    }
+}
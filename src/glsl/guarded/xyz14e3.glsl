@@ -0,0 +1,212 @@
+#ifndef SHARED_EXPONENT_XYZ14E3
+#define SHARED_EXPONENT_XYZ14E3
+
+const uint XYZ14E3_EXPONENT_BITS        = 3u;
+const int  XYZ14E3_MANTISSA_BITS        = 14;
+const uint XYZ14E3_MANTISSA_BITSU       = 14u;
+const int  XYZ14E3_EXP_BIAS             = 3;
+const uint XYZ14E3_MAX_VALID_BIASED_EXP = 7u;
+
+const uint  MAX_XYZ14E3_EXP             = 4u;
+const int   XYZ14E3_MANTISSA_VALUES     = 16384;
+const int   MAX_XYZ14E3_MANTISSA        = 16383;
+const uint  MAX_XYZ14E3_MANTISSAU       = 16383u;
+const float MAX_XYZ14E3_                = 16.0;
+const float EPSILON_XYZ14E3_            = 0.0000076293945;
+
+// MAX_XYZ14E3 would be 15.999023, this is used to scale to exactly 16.0
+const float XYZ14E3_NORM_MULT           = 1.000061;
+
+#ifndef SHARED_EXPONENT_FLOOR_LOG2
+#define SHARED_EXPONENT_FLOOR_LOG2
+int floor_log2(float x) {
+    uint f = floatBitsToUint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_IS_SIGN_NEGATIVE
+#define SHARED_EXPONENT_IS_SIGN_NEGATIVE
+uint is_sign_negative(float v) {
+    return (floatBitsToUint(v) >> 31u) & 1u;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_ABS_NAN_TO_ZERO
+#define SHARED_EXPONENT_ABS_NAN_TO_ZERO
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 abs_nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v) & 0x7FFFFFFFu;
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b, uvec3(0x7F800000u))));
+}
+#endif
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uvec2 vec3_to_xyz14e3(vec3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x);
+    uint ysign = is_sign_negative(xyz_in.y);
+    uint zsign = is_sign_negative(xyz_in.z);
+
+    vec3 xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ14E3_)) / XYZ14E3_NORM_MULT;
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ14E3_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ14E3_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ14E3_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uvec3 s = uvec3(floor(xyz / denom + 0.5));
+    uint e = uint(exp_shared);
+
+    // The lowest exponent bit is stored in the first word, the other two in the second.
+    uint out_a = ((e & 1u) << 31u) | (zsign << 30u) | (ysign << 29u) | (xsign << 28u) | (s.y << 14u) | (s.x << 0u);
+    uint out_b = ((e & 6u) << 13u) | (s.z << 0u);
+
+    return uvec2(out_a, out_b);
+}
+
+#ifndef SHARED_EXPONENT_BITFIELD_EXTRACT
+#define SHARED_EXPONENT_BITFIELD_EXTRACT
+// Provided for compatibility. With opengl 4.0 and later bitfieldExtract can be used instead.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+#endif
+
+vec3 xyz14e3_to_vec3(uvec2 v) {
+    uint biased_exp = bitfield_extract(v.x, 31u, 1u) | (bitfield_extract(v.y, 14u, 2u) << 1u);
+    int exponent = int(biased_exp) - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS;
+    float scale = exp2(float(exponent)) * XYZ14E3_NORM_MULT;
+
+    // bitfield_extract(v.x, 28u, 1u) << 31u is extracting the sign bit and
+    // shifts it over to the corresponding IEEE 754 sign location
+    return vec3(
+        uintBitsToFloat(floatBitsToUint(float(bitfield_extract(v.x,  0u, XYZ14E3_MANTISSA_BITSU))) | bitfield_extract(v.x, 28u, 1u) << 31u),
+        uintBitsToFloat(floatBitsToUint(float(bitfield_extract(v.x, 14u, XYZ14E3_MANTISSA_BITSU))) | bitfield_extract(v.x, 29u, 1u) << 31u),
+        uintBitsToFloat(floatBitsToUint(float(bitfield_extract(v.y,  0u, XYZ14E3_MANTISSA_BITSU))) | bitfield_extract(v.x, 30u, 1u) << 31u)
+    ) * scale;
+}
+
+#ifndef SHARED_EXPONENT_PACKED48_WORD
+#define SHARED_EXPONENT_PACKED48_WORD
+// Arrays of 48 bit values can be stored tightly in a uint buffer, with two values in every three
+// words. Value `index` is in words packed48_word(index) and packed48_word(index) + 1u, so an
+// array with an odd length needs one word of padding. Shader storage buffers can't be passed to
+// functions, so the helpers take and return these two words.
+uint packed48_word(uint index) {
+    return (index * 3u) >> 1u;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_PACKED48_LOAD
+#define SHARED_EXPONENT_PACKED48_LOAD
+uvec2 packed48_load(uint lo, uint hi, uint index) {
+    if ((index & 1u) == 0u) {
+        return uvec2(lo, hi & 0xFFFFu);
+    }
+    return uvec2((lo >> 16u) | (hi << 16u), hi >> 16u);
+}
+#endif
+
+#ifndef SHARED_EXPONENT_PACKED48_STORE
+#define SHARED_EXPONENT_PACKED48_STORE
+// The two words with value `index` replaced. Neighbouring values share a word, so they must not
+// be stored concurrently.
+uvec2 packed48_store(uint lo, uint hi, uint index, uvec2 v) {
+    if ((index & 1u) == 0u) {
+        return uvec2(v.x, (hi & 0xFFFF0000u) | (v.y & 0xFFFFu));
+    }
+    return uvec2((lo & 0xFFFFu) | (v.x << 16u), (v.x >> 16u) | (v.y << 16u));
+}
+#endif
+
+vec3 load_xyz14e3(uint lo, uint hi, uint index) {
+    return xyz14e3_to_vec3(packed48_load(lo, hi, index));
+}
+
+uvec2 store_xyz14e3(uint lo, uint hi, uint index, vec3 xyz) {
+    return packed48_store(lo, hi, index, vec3_to_xyz14e3(xyz));
+}
+
+#ifndef SHARED_EXPONENT_PARTS
+#define SHARED_EXPONENT_PARTS
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uvec3 mantissas;
+    uvec3 signs;
+};
+#endif
+
+#ifndef SHARED_EXPONENT_LDEXP_PARTS
+#define SHARED_EXPONENT_LDEXP_PARTS
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uvec3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uvec3 over = uvec3(greaterThan(m, uvec3(max_mantissa >> d)));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+#endif
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz14e3(uvec2 v) {
+    uvec3 m = uvec3(bitfield_extract(v.x, 0u, XYZ14E3_MANTISSA_BITSU), bitfield_extract(v.x, 14u, XYZ14E3_MANTISSA_BITSU), bitfield_extract(v.y, 0u, XYZ14E3_MANTISSA_BITSU));
+    uvec3 s = uvec3(bitfield_extract(v.x, 28u, 1u), bitfield_extract(v.x, 29u, 1u), bitfield_extract(v.x, 30u, 1u));
+    return SharedExponentParts(bitfield_extract(v.x, 31u, 1u) | (bitfield_extract(v.y, 14u, 2u) << 1u), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz14e3 checks them instead.
+uvec2 compose_xyz14e3(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ14E3_MAX_VALID_BIASED_EXP;
+    uvec3 m = parts.mantissas & MAX_XYZ14E3_MANTISSAU;
+    uvec3 s = parts.signs & 1u;
+    return uvec2(((e & 1u) << 31u) | (s.z << 30u) | (s.y << 29u) | (s.x << 28u) | (m.y << 14u) | (m.x << 0u), ((e & 6u) << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uvec2 negate_xyz14e3(uvec2 v) {
+    return uvec2(v.x ^ 0x70000000u, v.y);
+}
+
+uvec2 abs_xyz14e3(uvec2 v) {
+    return uvec2(v.x & ~0x70000000u, v.y);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz14e3 of the scaled value.
+uvec2 ldexp_xyz14e3(uvec2 v, int k) {
+    return compose_xyz14e3(ldexp_parts(decompose_xyz14e3(v), k, XYZ14E3_EXPONENT_BITS, XYZ14E3_MANTISSA_BITSU));
+}
+
+#endif
@@ -0,0 +1,168 @@
+#ifndef SHARED_EXPONENT_XYZ18E7
+#define SHARED_EXPONENT_XYZ18E7
+
+const uint XYZ18E7_EXPONENT_BITS        = 7u;
+const int  XYZ18E7_MANTISSA_BITS        = 18;
+const uint XYZ18E7_MANTISSA_BITSU       = 18u;
+const int  XYZ18E7_EXP_BIAS             = 63;
+const uint XYZ18E7_MAX_VALID_BIASED_EXP = 127u;
+
+const uint  MAX_XYZ18E7_EXP             = 64u;
+const int   XYZ18E7_MANTISSA_VALUES     = 262144;
+const int   MAX_XYZ18E7_MANTISSA        = 262143;
+const uint  MAX_XYZ18E7_MANTISSAU       = 262143u;
+const float MAX_XYZ18E7_                = 1.8446674e+19;
+const float EPSILON_XYZ18E7_            = 4.135903e-25;
+
+#ifndef SHARED_EXPONENT_FLOOR_LOG2
+#define SHARED_EXPONENT_FLOOR_LOG2
+int floor_log2(float x) {
+    uint f = floatBitsToUint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_IS_SIGN_NEGATIVE
+#define SHARED_EXPONENT_IS_SIGN_NEGATIVE
+uint is_sign_negative(float v) {
+    return (floatBitsToUint(v) >> 31u) & 1u;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_ABS_NAN_TO_ZERO
+#define SHARED_EXPONENT_ABS_NAN_TO_ZERO
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 abs_nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v) & 0x7FFFFFFFu;
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b, uvec3(0x7F800000u))));
+}
+#endif
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uvec2 vec3_to_xyz18e7(vec3 xyz) {
+    uint xsign = is_sign_negative(xyz.x);
+    uint ysign = is_sign_negative(xyz.y);
+    uint zsign = is_sign_negative(xyz.z);
+
+    xyz = min(abs_nan_to_zero(xyz), vec3(MAX_XYZ18E7_));
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ18E7_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ18E7_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ18E7_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uvec3 s = uvec3(floor(xyz / denom + 0.5));
+
+    uint out_a = (s.y << 18u) | (s.x << 0u);
+    uint out_b = (uint(exp_shared) << 25u) | (zsign << 24u) | (ysign << 23u) | (xsign << 22u) | (s.z << 4u) | (s.y >> 14u);
+
+    return uvec2(out_a, out_b);
+}
+
+#ifndef SHARED_EXPONENT_BITFIELD_EXTRACT
+#define SHARED_EXPONENT_BITFIELD_EXTRACT
+// Provided for compatibility. With opengl 4.0 and later bitfieldExtract can be used instead.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+#endif
+
+vec3 xyz18e7_to_vec3(uvec2 v) {
+    int exponent = int(bitfield_extract(v.y, 25u, XYZ18E7_EXPONENT_BITS)) - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS;
+    float scale = exp2(float(exponent));
+
+    uint xb = bitfield_extract(v.x, 0u, XYZ18E7_MANTISSA_BITSU);
+    uint yb = bitfield_extract(v.x, 18u, XYZ18E7_MANTISSA_BITSU) | bitfield_extract(v.y, 0u, 4u) << 14u;
+    uint zb = bitfield_extract(v.y, 4u, XYZ18E7_MANTISSA_BITSU);
+
+    // Extract the sign bits, then bitfield_extract(v.y, 22u, 1u) << 31u shifts it over to the corresponding IEEE 754 sign location.
+    return vec3(
+        uintBitsToFloat(floatBitsToUint(float(xb)) | bitfield_extract(v.y, 22u, 1u) << 31u),
+        uintBitsToFloat(floatBitsToUint(float(yb)) | bitfield_extract(v.y, 23u, 1u) << 31u),
+        uintBitsToFloat(floatBitsToUint(float(zb)) | bitfield_extract(v.y, 24u, 1u) << 31u)
+    ) * scale;
+}
+
+#ifndef SHARED_EXPONENT_PARTS
+#define SHARED_EXPONENT_PARTS
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uvec3 mantissas;
+    uvec3 signs;
+};
+#endif
+
+#ifndef SHARED_EXPONENT_LDEXP_PARTS
+#define SHARED_EXPONENT_LDEXP_PARTS
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uvec3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uvec3 over = uvec3(greaterThan(m, uvec3(max_mantissa >> d)));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+#endif
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz18e7(uvec2 v) {
+    uvec3 m = uvec3(bitfield_extract(v.x, 0u, XYZ18E7_MANTISSA_BITSU), bitfield_extract(v.x, 18u, 14u) | bitfield_extract(v.y, 0u, 4u) << 14u, bitfield_extract(v.y, 4u, XYZ18E7_MANTISSA_BITSU));
+    uvec3 s = uvec3(bitfield_extract(v.y, 22u, 1u), bitfield_extract(v.y, 23u, 1u), bitfield_extract(v.y, 24u, 1u));
+    return SharedExponentParts(bitfield_extract(v.y, 25u, XYZ18E7_EXPONENT_BITS), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz18e7 checks them instead.
+uvec2 compose_xyz18e7(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ18E7_MAX_VALID_BIASED_EXP;
+    uvec3 m = parts.mantissas & MAX_XYZ18E7_MANTISSAU;
+    uvec3 s = parts.signs & 1u;
+    return uvec2((m.y << 18u) | (m.x << 0u), (e << 25u) | (s.z << 24u) | (s.y << 23u) | (s.x << 22u) | (m.z << 4u) | (m.y >> 14u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uvec2 negate_xyz18e7(uvec2 v) {
+    return uvec2(v.x, v.y ^ 0x01C00000u);
+}
+
+uvec2 abs_xyz18e7(uvec2 v) {
+    return uvec2(v.x, v.y & ~0x01C00000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz18e7 of the scaled value.
+uvec2 ldexp_xyz18e7(uvec2 v, int k) {
+    return compose_xyz18e7(ldexp_parts(decompose_xyz18e7(v), k, XYZ18E7_EXPONENT_BITS, XYZ18E7_MANTISSA_BITSU));
+}
+
+#endif
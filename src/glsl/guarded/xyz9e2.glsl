@@ -0,0 +1,169 @@
+#ifndef SHARED_EXPONENT_XYZ9E2
+#define SHARED_EXPONENT_XYZ9E2
+
+const uint XYZ9E2_EXPONENT_BITS        = 2u;
+const int  XYZ9E2_MANTISSA_BITS        = 9;
+const uint XYZ9E2_MANTISSA_BITSU       = 9u;
+const int  XYZ9E2_EXP_BIAS             = 3;
+const uint XYZ9E2_MAX_VALID_BIASED_EXP = 3u;
+
+const uint  MAX_XYZ9E2_EXP             = 0u;
+const int   XYZ9E2_MANTISSA_VALUES     = 512;
+const int   MAX_XYZ9E2_MANTISSA        = 511;
+const uint  MAX_XYZ9E2_MANTISSAU       = 511u;
+const float MAX_XYZ9E2_                = 1.0;
+const float EPSILON_XYZ9E2_            = 0.00024414063;
+
+// MAX_XYZ9E2 would be 0.9980469, this is used to scale to exactly 1.0
+const float XYZ9E2_NORM_MULT           = 1.0019569;
+
+#ifndef SHARED_EXPONENT_FLOOR_LOG2
+#define SHARED_EXPONENT_FLOOR_LOG2
+int floor_log2(float x) {
+    uint f = floatBitsToUint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_IS_SIGN_NEGATIVE
+#define SHARED_EXPONENT_IS_SIGN_NEGATIVE
+uint is_sign_negative(float v) {
+    return (floatBitsToUint(v) >> 31u) & 1u;
+}
+#endif
+
+#ifndef SHARED_EXPONENT_ABS_NAN_TO_ZERO
+#define SHARED_EXPONENT_ABS_NAN_TO_ZERO
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 abs_nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v) & 0x7FFFFFFFu;
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b, uvec3(0x7F800000u))));
+}
+#endif
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint vec3_to_xyz9e2(vec3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x) << 9u;
+    uint ysign = is_sign_negative(xyz_in.y) << 9u;
+    uint zsign = is_sign_negative(xyz_in.z) << 9u;
+
+    vec3 xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ9E2_)) / XYZ9E2_NORM_MULT;
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ9E2_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ9E2_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ9E2_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uvec3 s = uvec3(floor(xyz / denom + 0.5));
+
+    return (uint(exp_shared) << 30u) | ((s.z | zsign) << 20u) | ((s.y | ysign) << 10u) | ((s.x | xsign) << 0u);
+}
+
+#ifndef SHARED_EXPONENT_BITFIELD_EXTRACT
+#define SHARED_EXPONENT_BITFIELD_EXTRACT
+// Provided for compatibility. With opengl 4.0 and later bitfieldExtract can be used instead.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+#endif
+
+vec3 xyz9e2_to_vec3(uint v) {
+    int exponent = int(bitfield_extract(v, 30u, XYZ9E2_EXPONENT_BITS)) - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS;
+    float scale = exp2(float(exponent)) * XYZ9E2_NORM_MULT;
+
+    // Extract both the mantissa and sign at the same time.
+    uint xb = bitfield_extract(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u);
+    uint yb = bitfield_extract(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u);
+    uint zb = bitfield_extract(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u);
+
+    // xb & 0x1FFu masks out for just the mantissa
+    // xb & 0x200u << 22u masks out just the sign bit and shifts it over
+    // to the corresponding IEEE 754 sign location
+    return vec3(
+        uintBitsToFloat(floatBitsToUint(float(xb & 0x1FFu)) | (xb & 0x200u) << 22u),
+        uintBitsToFloat(floatBitsToUint(float(yb & 0x1FFu)) | (yb & 0x200u) << 22u),
+        uintBitsToFloat(floatBitsToUint(float(zb & 0x1FFu)) | (zb & 0x200u) << 22u)
+    ) * scale;
+}
+
+#ifndef SHARED_EXPONENT_PARTS
+#define SHARED_EXPONENT_PARTS
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uvec3 mantissas;
+    uvec3 signs;
+};
+#endif
+
+#ifndef SHARED_EXPONENT_LDEXP_PARTS
+#define SHARED_EXPONENT_LDEXP_PARTS
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uvec3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uvec3 over = uvec3(greaterThan(m, uvec3(max_mantissa >> d)));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+#endif
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz9e2(uint v) {
+    // Extract both the mantissa and sign at the same time.
+    uvec3 b = uvec3(bitfield_extract(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u), bitfield_extract(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u), bitfield_extract(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u));
+    return SharedExponentParts(bitfield_extract(v, 30u, XYZ9E2_EXPONENT_BITS), b & MAX_XYZ9E2_MANTISSAU, b >> XYZ9E2_MANTISSA_BITSU);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz9e2 checks them instead.
+uint compose_xyz9e2(SharedExponentParts parts) {
+    uvec3 b = (parts.mantissas & MAX_XYZ9E2_MANTISSAU) | ((parts.signs & 1u) << XYZ9E2_MANTISSA_BITSU);
+    return ((parts.exponent & XYZ9E2_MAX_VALID_BIASED_EXP) << 30u) | (b.z << 20u) | (b.y << 10u) | (b.x << 0u);
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uint negate_xyz9e2(uint v) {
+    return v ^ 0x20080200u;
+}
+
+uint abs_xyz9e2(uint v) {
+    return v & ~0x20080200u;
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz9e2 of the scaled value.
+uint ldexp_xyz9e2(uint v, int k) {
+    return compose_xyz9e2(ldexp_parts(decompose_xyz9e2(v), k, XYZ9E2_EXPONENT_BITS, XYZ9E2_MANTISSA_BITSU));
+}
+
+#endif
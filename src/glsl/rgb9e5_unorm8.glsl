@@ -0,0 +1,98 @@
+// rgb9e5 for GLSL ES 1.0 (WebGL 1), stored in the 4 bytes of an RGBA8 texture or render target.
+// Only float math is used, since GLSL ES 1.0 has no uint, bit shifts or floatBitsToUint. Needs
+// highp floats, and gives the same values as the rust functions where highp is IEEE single
+// precision. The bytes are in little endian order, so the texture data is the same as a buffer
+// of u32 values from vec3_to_rgb9e5. Textures need NEAREST filtering, as interpolating
+// the bytes doesn't interpolate the values.
+//
+// This is not meant to be combined with the other GLSL files, which require GLSL 3.0 or later.
+// The constants are floats here.
+
+const highp float RGB9E5_EXPONENT_BITS        = 5.0;
+const highp float RGB9E5_MANTISSA_BITS        = 9.0;
+const highp float RGB9E5_EXP_BIAS             = 15.0;
+const highp float RGB9E5_MAX_VALID_BIASED_EXP = 31.0;
+
+const highp float MAX_RGB9E5_EXP              = 16.0;
+const highp float RGB9E5_MANTISSA_VALUES      = 512.0;
+const highp float MAX_RGB9E5_MANTISSA         = 511.0;
+const highp float MAX_RGB9E5_                 = 65408.0;
+const highp float EPSILON_RGB9E5_             = 0.000000059604645;
+
+// The byte stored in an unorm8 channel.
+highp vec4 unorm8_to_bytes(highp vec4 c) {
+    return floor(c * 255.0 + 0.5);
+}
+
+// 2^e for an integer e in [-32, 32], by repeated doubling or halving. Unlike exp2 this is exact
+// on any GLSL ES 1.0 implementation.
+highp float pow2_unorm8(highp float e) {
+    highp float p = 1.0;
+    for (int i = 0; i < 32; i++) {
+        if (float(i) < e) {
+            p *= 2.0;
+        } else if (float(i) < -e) {
+            p *= 0.5;
+        }
+    }
+    return p;
+}
+
+highp vec3 rgb9e5_unorm8_to_vec3(highp vec4 c) {
+    highp vec4 b = unorm8_to_bytes(c);
+
+    // Each mantissa continues in the low bits of the next byte.
+    highp vec3 m = vec3(
+        b.x + mod(b.y, 2.0) * 256.0,
+        floor(b.y / 2.0) + mod(b.z, 4.0) * 128.0,
+        floor(b.z / 4.0) + mod(b.w, 8.0) * 64.0
+    );
+    highp float exponent = floor(b.w / 8.0) - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS;
+
+    return m * pow2_unorm8(exponent);
+}
+
+// floor(log2(x)) for x below 2^16, and -16 for anything below 2^-16. GLSL ES 1.0 leaves the
+// precision of log2 to the implementation, so this halves or doubles a power of two until it is
+// the largest one not above x, which is exact.
+highp float floor_log2_unorm8(highp float x) {
+    highp float e = 0.0;
+    highp float p = 1.0;
+    for (int i = 0; i < 16; i++) {
+        if (p > x) {
+            p *= 0.5;
+            e -= 1.0;
+        } else if (p * 2.0 <= x) {
+            p *= 2.0;
+            e += 1.0;
+        }
+    }
+    return e;
+}
+
+// NaN can't be detected without access to the bits, so unlike vec3_to_rgb9e5 the result for
+// NaN is undefined. Infinity is clamped to MAX_RGB9E5_.
+highp vec4 vec3_to_rgb9e5_unorm8(highp vec3 rgb_in) {
+    highp vec3 rgb = clamp(rgb_in, vec3(0.0), vec3(MAX_RGB9E5_));
+
+    highp float maxrgb = max(rgb.r, max(rgb.g, rgb.b));
+    highp float exp_shared = max(-RGB9E5_EXP_BIAS - 1.0, floor_log2_unorm8(maxrgb)) + 1.0 + RGB9E5_EXP_BIAS;
+    highp float denom = pow2_unorm8(exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS);
+
+    highp float maxm = floor(maxrgb / denom + 0.5);
+    if (maxm == RGB9E5_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1.0;
+    }
+
+    highp vec3 n = floor(rgb / denom + 0.5);
+
+    highp vec4 b = vec4(
+        mod(n.r, 256.0),
+        floor(n.r / 256.0) + mod(n.g, 128.0) * 2.0,
+        floor(n.g / 128.0) + mod(n.b, 64.0) * 4.0,
+        floor(n.b / 64.0) + exp_shared * 8.0
+    );
+
+    return b / 255.0;
+}
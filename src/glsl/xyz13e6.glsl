@@ -21,13 +21,20 @@ uint is_sign_negative(float v) {
     return (floatBitsToUint(v) >> 31u) & 1u;
 }
 
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 abs_nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v) & 0x7FFFFFFFu;
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b, uvec3(0x7F800000u))));
+}
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 uvec2 vec3_to_xyz13e6(vec3 xyz) {
     uint xsign = is_sign_negative(xyz.x);
     uint ysign = is_sign_negative(xyz.y);
     uint zsign = is_sign_negative(xyz.z);
 
-    xyz = min(abs(xyz), vec3(MAX_XYZ13E6_));
+    xyz = min(abs_nan_to_zero(xyz), vec3(MAX_XYZ13E6_));
 
     float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
     int exp_shared = max(-XYZ13E6_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ13E6_EXP_BIAS;
@@ -64,4 +71,104 @@ vec3 xyz13e6_to_vec3(uvec2 v) {
         uintBitsToFloat(floatBitsToUint(float(bitfield_extract(v.x, 13u, XYZ13E6_MANTISSA_BITSU))) | bitfield_extract(v.y, 14u, 1u) << 31u),
         uintBitsToFloat(floatBitsToUint(float(bitfield_extract(v.y,  0u, XYZ13E6_MANTISSA_BITSU))) | bitfield_extract(v.y, 15u, 1u) << 31u)
     ) * scale;
+}
+
+// Arrays of 48 bit values can be stored tightly in a uint buffer, with two values in every three
+// words. Value `index` is in words packed48_word(index) and packed48_word(index) + 1u, so an
+// array with an odd length needs one word of padding. Shader storage buffers can't be passed to
+// functions, so the helpers take and return these two words.
+uint packed48_word(uint index) {
+    return (index * 3u) >> 1u;
+}
+
+uvec2 packed48_load(uint lo, uint hi, uint index) {
+    if ((index & 1u) == 0u) {
+        return uvec2(lo, hi & 0xFFFFu);
+    }
+    return uvec2((lo >> 16u) | (hi << 16u), hi >> 16u);
+}
+
+// The two words with value `index` replaced. Neighbouring values share a word, so they must not
+// be stored concurrently.
+uvec2 packed48_store(uint lo, uint hi, uint index, uvec2 v) {
+    if ((index & 1u) == 0u) {
+        return uvec2(v.x, (hi & 0xFFFF0000u) | (v.y & 0xFFFFu));
+    }
+    return uvec2((lo & 0xFFFFu) | (v.x << 16u), (v.x >> 16u) | (v.y << 16u));
+}
+
+vec3 load_xyz13e6(uint lo, uint hi, uint index) {
+    return xyz13e6_to_vec3(packed48_load(lo, hi, index));
+}
+
+uvec2 store_xyz13e6(uint lo, uint hi, uint index, vec3 xyz) {
+    return packed48_store(lo, hi, index, vec3_to_xyz13e6(xyz));
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uvec3 mantissas;
+    uvec3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uvec3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uvec3 over = uvec3(greaterThan(m, uvec3(max_mantissa >> d)));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz13e6(uvec2 v) {
+    uvec3 m = uvec3(bitfield_extract(v.x, 0u, XYZ13E6_MANTISSA_BITSU), bitfield_extract(v.x, 13u, XYZ13E6_MANTISSA_BITSU), bitfield_extract(v.y, 0u, XYZ13E6_MANTISSA_BITSU));
+    uvec3 s = uvec3(bitfield_extract(v.y, 13u, 1u), bitfield_extract(v.y, 14u, 1u), bitfield_extract(v.y, 15u, 1u));
+    return SharedExponentParts(bitfield_extract(v.x, 26u, XYZ13E6_EXPONENT_BITS), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz13e6 checks them instead.
+uvec2 compose_xyz13e6(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ13E6_MAX_VALID_BIASED_EXP;
+    uvec3 m = parts.mantissas & MAX_XYZ13E6_MANTISSAU;
+    uvec3 s = parts.signs & 1u;
+    return uvec2((e << 26u) | (m.y << 13u) | (m.x << 0u), (s.z << 15u) | (s.y << 14u) | (s.x << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uvec2 negate_xyz13e6(uvec2 v) {
+    return uvec2(v.x, v.y ^ 0x0000E000u);
+}
+
+uvec2 abs_xyz13e6(uvec2 v) {
+    return uvec2(v.x, v.y & ~0x0000E000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz13e6 of the scaled value.
+uvec2 ldexp_xyz13e6(uvec2 v, int k) {
+    return compose_xyz13e6(ldexp_parts(decompose_xyz13e6(v), k, XYZ13E6_EXPONENT_BITS, XYZ13E6_MANTISSA_BITSU));
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+// Which gpu formats to use for vertex buffers and textures holding each packed format. The names
+// follow wgpu. Only rgb9e5 has a texture format the gpu decodes, everything else is read as
+// unsigned integers and decoded with the shader functions.
+
+#[cfg(any(feature = "alloc", test))]
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use crate::shaders::Format;
+#[cfg(any(feature = "alloc", test))]
+use crate::shaders::ShaderLanguage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Uint32,
+    Uint32x2,
+}
+
+impl VertexFormat {
+    pub fn size(self) -> u32 {
+        match self {
+            VertexFormat::Uint32 => 4,
+            VertexFormat::Uint32x2 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    Rgb9e5Ufloat,
+    R32Uint,
+    Rg32Uint,
+}
+
+impl TextureFormat {
+    pub fn texel_size(self) -> u32 {
+        match self {
+            TextureFormat::Rgb9e5Ufloat | TextureFormat::R32Uint => 4,
+            TextureFormat::Rg32Uint => 8,
+        }
+    }
+
+    // VK_FORMAT_E5B9G9R9_UFLOAT_PACK32, VK_FORMAT_R32_UINT and VK_FORMAT_R32G32_UINT.
+    pub fn vk_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgb9e5Ufloat => 123,
+            TextureFormat::R32Uint => 98,
+            TextureFormat::Rg32Uint => 101,
+        }
+    }
+
+    // DXGI_FORMAT_R9G9B9E5_SHAREDEXP, DXGI_FORMAT_R32_UINT and DXGI_FORMAT_R32G32_UINT.
+    pub fn dxgi_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgb9e5Ufloat => 67,
+            TextureFormat::R32Uint => 42,
+            TextureFormat::Rg32Uint => 17,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuFormat {
+    pub vertex_format: VertexFormat,
+    pub texture_format: TextureFormat,
+    // Bytes per value in a vertex buffer or texture. The 48 bit formats are padded to 8 bytes, as
+    // vertex attributes and texels can't be 6 bytes. packed48 stores them tightly in storage
+    // buffers instead.
+    pub stride: u32,
+    // Alignment of the value in buffers, as a u32 or vec2<u32>.
+    pub alignment: u32,
+    // Whether the gpu decodes the texture format when sampling. Vertex attributes always need the
+    // decode function.
+    pub hardware_decode: bool,
+    // The shader function that decodes the value read as vertex_format or texture_format. In
+    // wgsl it has a trailing underscore, see `decode_function`.
+    pub decode_function: &'static str,
+}
+
+impl GpuFormat {
+    #[cfg(any(feature = "alloc", test))]
+    pub fn decode_function(&self, language: ShaderLanguage) -> String {
+        match language {
+            ShaderLanguage::Wgsl => format!("{}_", self.decode_function),
+            _ => self.decode_function.to_string(),
+        }
+    }
+}
+
+impl Format {
+    pub fn gpu_format(self) -> GpuFormat {
+        let (vertex_format, texture_format) = match self {
+            Format::Rgb9e5 => (VertexFormat::Uint32, TextureFormat::Rgb9e5Ufloat),
+            Format::Xyz8e5 | Format::Xyz9e2 => (VertexFormat::Uint32, TextureFormat::R32Uint),
+            Format::Xyz13e6 | Format::Xyz14e3 | Format::Xyz18e7 => {
+                (VertexFormat::Uint32x2, TextureFormat::Rg32Uint)
+            }
+        };
+        GpuFormat {
+            vertex_format,
+            texture_format,
+            stride: vertex_format.size(),
+            alignment: vertex_format.size(),
+            hardware_decode: texture_format == TextureFormat::Rgb9e5Ufloat,
+            decode_function: match self {
+                Format::Rgb9e5 => "rgb9e5_to_vec3",
+                Format::Xyz8e5 => "xyz8e5_to_vec3",
+                Format::Xyz9e2 => "xyz9e2_to_vec3",
+                Format::Xyz13e6 => "xyz13e6_to_vec3",
+                Format::Xyz14e3 => "xyz14e3_to_vec3",
+                Format::Xyz18e7 => "xyz18e7_to_vec3",
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use crate::shader_emulation;
+    use crate::shader_emulation::tests::{bits3, test_inputs};
+    use crate::shaders::tests::{function_count, LANGUAGES};
+    use crate::{rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+    use super::*;
+
+    #[test]
+    fn test_gpu_formats() {
+        let bytes = [
+            rgb9e5::BYTES,
+            xyz8e5::BYTES,
+            xyz9e2::BYTES,
+            xyz13e6::BYTES,
+            xyz14e3::BYTES,
+            xyz18e7::BYTES,
+        ];
+        for (format, bytes) in Format::ALL.into_iter().zip(bytes) {
+            let gpu = format.gpu_format();
+            assert!(gpu.stride >= bytes as u32 && gpu.stride < bytes as u32 + 4);
+            assert_eq!(gpu.stride, gpu.texture_format.texel_size());
+            assert_eq!(gpu.stride % gpu.alignment, 0);
+            assert_eq!(gpu.hardware_decode, format == Format::Rgb9e5);
+            for language in LANGUAGES {
+                let name = gpu.decode_function(language);
+                assert_eq!(function_count(format.source(language), &name), 1, "{name}");
+            }
+        }
+    }
+
+    // The 48 bit formats read the padding as the upper half of the second word, which the
+    // decoders ignore.
+    #[test]
+    fn test_padded_48_bit() {
+        for xyz in test_inputs() {
+            let (a, b) = xyz13e6::vec3_to_xyz13e6(xyz);
+            let padded = [a, b as u32 | 0xABCD0000];
+            assert_eq!(
+                bits3(shader_emulation::xyz13e6_to_vec3(padded)),
+                bits3(xyz13e6::xyz13e6_to_vec3((a, b)))
+            );
+            let (a, b) = xyz14e3::vec3_to_xyz14e3(xyz);
+            let padded = [a, b as u32 | 0xABCD0000];
+            assert_eq!(
+                bits3(shader_emulation::xyz14e3_to_vec3(padded)),
+                bits3(xyz14e3::xyz14e3_to_vec3((a, b)))
+            );
+        }
+    }
+}
@@ -0,0 +1,467 @@
+// Radiance .hdr files: a text header, a resolution line and RGBE or XYZE pixels, with scanlines
+// either flat or in the new-style run length encoding. Old-style RLE, where a pixel of [1, 1, 1, n]
+// repeats the previous one, is read as flat pixels, the same as rgbe.c does.
+// https://radsite.lbl.gov/radiance/refer/filefmts.pdf
+
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::rgbe8::{rgbe8_to_vec3, vec3_to_rgbe8};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrFormat {
+    // 32-bit_rle_rgbe, the default when the header has no FORMAT line.
+    Rgbe,
+    // 32-bit_rle_xyze, CIE XYZ stored the same way.
+    Xyze,
+}
+
+impl HdrFormat {
+    pub const fn name(self) -> &'static str {
+        match self {
+            HdrFormat::Rgbe => "32-bit_rle_rgbe",
+            HdrFormat::Xyze => "32-bit_rle_xyze",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: HdrFormat,
+    // Rows from top to bottom, as `vec3_to_rgbe8` bytes.
+    pub pixels: Vec<[u8; 4]>,
+}
+
+#[derive(Debug)]
+pub enum HdrError {
+    Io(io::Error),
+    // The file doesn't start with "#?".
+    Signature,
+    Format(String),
+    // Only "-Y height +X width" and "+Y height +X width" are supported.
+    Resolution(String),
+    Scanline { row: u32 },
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HdrError::Io(error) => write!(f, "{error}"),
+            HdrError::Signature => write!(f, "not a radiance file, missing #? signature"),
+            HdrError::Format(format) => write!(f, "unsupported pixel format {format}"),
+            HdrError::Resolution(line) => write!(f, "unsupported resolution line {line}"),
+            HdrError::Scanline { row } => write!(f, "invalid run length encoding in row {row}"),
+        }
+    }
+}
+
+impl std::error::Error for HdrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HdrError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HdrError {
+    fn from(error: io::Error) -> Self {
+        HdrError::Io(error)
+    }
+}
+
+impl HdrImage {
+    pub fn from_vec3(
+        width: u32,
+        height: u32,
+        format: HdrFormat,
+        pixels: &[[f32; 3]],
+    ) -> io::Result<Self> {
+        check_size(width, height, pixels.len())?;
+        Ok(HdrImage {
+            width,
+            height,
+            format,
+            pixels: pixels.iter().map(|&rgb| vec3_to_rgbe8(rgb)).collect(),
+        })
+    }
+
+    pub fn to_vec3(&self) -> Vec<[f32; 3]> {
+        self.pixels.iter().map(|&v| rgbe8_to_vec3(v)).collect()
+    }
+}
+
+fn check_size(width: u32, height: u32, pixels: usize) -> io::Result<()> {
+    if Some(pixels) != (width as usize).checked_mul(height as usize) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "pixels don't match the size",
+        ));
+    }
+    Ok(())
+}
+
+// New-style RLE is only used for these widths, as the scanline marker stores the width in 15 bits.
+const fn is_rle_width(width: u32) -> bool {
+    width >= 8 && width < 0x8000
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String, HdrError> {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+fn parse_resolution(line: &str) -> Option<(u32, u32, bool)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let [y, height, "+X", width] = words[..] else {
+        return None;
+    };
+    let flipped = match y {
+        "-Y" => false,
+        "+Y" => true,
+        _ => return None,
+    };
+    Some((width.parse().ok()?, height.parse().ok()?, flipped))
+}
+
+fn read_byte(reader: &mut impl BufRead) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_scanline(
+    reader: &mut impl BufRead,
+    row: u32,
+    scanline: &mut [[u8; 4]],
+) -> Result<(), HdrError> {
+    let width = scanline.len();
+    let mut first = [0; 4];
+    reader.read_exact(&mut first)?;
+    if !(is_rle_width(width as u32) && first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0) {
+        scanline[0] = first;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+    if (first[2] as usize) << 8 | first[3] as usize != width {
+        return Err(HdrError::Scanline { row });
+    }
+
+    // The four channels one after the other, each as runs and literal spans. A count above 128
+    // repeats the next byte count - 128 times, otherwise count bytes follow.
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = read_byte(reader)?;
+            if count > 128 {
+                let n = count as usize - 128;
+                if x + n > width {
+                    return Err(HdrError::Scanline { row });
+                }
+                let value = read_byte(reader)?;
+                for pixel in &mut scanline[x..x + n] {
+                    pixel[channel] = value;
+                }
+                x += n;
+            } else {
+                let n = count as usize;
+                if n == 0 || x + n > width {
+                    return Err(HdrError::Scanline { row });
+                }
+                for pixel in &mut scanline[x..x + n] {
+                    pixel[channel] = read_byte(reader)?;
+                }
+                x += n;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn read_hdr(mut reader: impl BufRead) -> Result<HdrImage, HdrError> {
+    if !read_line(&mut reader)?.starts_with("#?") {
+        return Err(HdrError::Signature);
+    }
+    // Variables like EXPOSURE and comments are skipped, the header ends at an empty line.
+    let mut format = HdrFormat::Rgbe;
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(name) = line.strip_prefix("FORMAT=") {
+            format = match name.trim() {
+                "32-bit_rle_rgbe" => HdrFormat::Rgbe,
+                "32-bit_rle_xyze" => HdrFormat::Xyze,
+                other => return Err(HdrError::Format(other.to_string())),
+            };
+        }
+    }
+
+    let line = read_line(&mut reader)?;
+    let (width, height, flipped) =
+        parse_resolution(&line).ok_or_else(|| HdrError::Resolution(line.clone()))?;
+    let bytes = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .ok_or_else(|| HdrError::Resolution(line.clone()))?;
+    // Scanlines are added as they're read, so a huge size in the header ends at the end of the
+    // file instead of allocating. Widths without RLE are always flat pixels.
+    let mut pixels = Vec::new();
+    if is_rle_width(width) {
+        let mut scanline = vec![[0; 4]; width as usize];
+        for row in 0..height {
+            read_scanline(&mut reader, row, &mut scanline)?;
+            pixels.extend_from_slice(&scanline);
+        }
+    } else {
+        let mut data = Vec::new();
+        (&mut reader).take(bytes as u64).read_to_end(&mut data)?;
+        if data.len() != bytes {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        pixels = data
+            .chunks_exact(4)
+            .map(|pixel| pixel.try_into().unwrap())
+            .collect();
+    }
+    if flipped {
+        pixels = pixels
+            .chunks(width.max(1) as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        format,
+        pixels,
+    })
+}
+
+// Runs of 3 or more equal bytes, up to 127, are stored as runs, the rest as literal spans of up
+// to 128 bytes.
+fn write_rle_channel(out: &mut Vec<u8>, data: &[u8]) {
+    let flush = |out: &mut Vec<u8>, literal: &[u8]| {
+        for span in literal.chunks(128) {
+            out.push(span.len() as u8);
+            out.extend_from_slice(span);
+        }
+    };
+
+    let mut start = 0;
+    let mut x = 0;
+    while x < data.len() {
+        let run = data[x..]
+            .iter()
+            .take(127)
+            .take_while(|&&byte| byte == data[x])
+            .count();
+        if run >= 3 {
+            flush(out, &data[start..x]);
+            out.push(128 + run as u8);
+            out.push(data[x]);
+            x += run;
+            start = x;
+        } else {
+            x += 1;
+        }
+    }
+    flush(out, &data[start..]);
+}
+
+// Writes a top to bottom image, with RLE scanlines when the width allows them.
+pub fn write_hdr(mut writer: impl Write, image: &HdrImage) -> io::Result<()> {
+    check_size(image.width, image.height, image.pixels.len())?;
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT={}\n\n-Y {} +X {}\n",
+        image.format.name(),
+        image.height,
+        image.width
+    )?;
+    if image.width == 0 {
+        return Ok(());
+    }
+
+    let mut out = Vec::new();
+    for scanline in image.pixels.chunks(image.width as usize) {
+        out.clear();
+        if is_rle_width(image.width) {
+            out.extend_from_slice(&[2, 2, (image.width >> 8) as u8, image.width as u8]);
+            for channel in 0..4 {
+                let data: Vec<u8> = scanline.iter().map(|pixel| pixel[channel]).collect();
+                write_rle_channel(&mut out, &data);
+            }
+        } else {
+            out.extend(scanline.iter().flatten());
+        }
+        writer.write_all(&out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+
+    fn header(format: &str, resolution: &str) -> Vec<u8> {
+        format!("#?RADIANCE\n# made by hand\nFORMAT={format}\nEXPOSURE=1.0\n\n{resolution}\n")
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_read_rle() {
+        let mut file = header("32-bit_rle_rgbe", "-Y 1 +X 8");
+        file.extend_from_slice(&[2, 2, 0, 8]);
+        // A run of 8.
+        file.extend_from_slice(&[136, 5]);
+        // 8 literal bytes.
+        file.extend_from_slice(&[8, 1, 2, 3, 4, 5, 6, 7, 8]);
+        // A run of 2 and 6 literal bytes.
+        file.extend_from_slice(&[130, 9, 6, 1, 2, 3, 4, 5, 6]);
+        // Two runs.
+        file.extend_from_slice(&[131, 129, 133, 130]);
+
+        let image = read_hdr(&file[..]).unwrap();
+        assert_eq!((image.width, image.height), (8, 1));
+        assert_eq!(image.format, HdrFormat::Rgbe);
+        let expected: Vec<[u8; 4]> = vec![
+            [5, 1, 9, 129],
+            [5, 2, 9, 129],
+            [5, 3, 1, 129],
+            [5, 4, 2, 130],
+            [5, 5, 3, 130],
+            [5, 6, 4, 130],
+            [5, 7, 5, 130],
+            [5, 8, 6, 130],
+        ];
+        assert_eq!(image.pixels, expected);
+        assert_eq!(image.to_vec3()[0], rgbe8_to_vec3([5, 1, 9, 129]));
+    }
+
+    #[test]
+    fn test_read_flat() {
+        // Too narrow for RLE, stored bottom to top.
+        let mut file = header("32-bit_rle_xyze", "+Y 2 +X 2");
+        file.extend_from_slice(&[1, 2, 3, 128, 4, 5, 6, 128]);
+        file.extend_from_slice(&[7, 8, 9, 129, 2, 2, 0, 2]);
+
+        let image = read_hdr(&file[..]).unwrap();
+        assert_eq!(image.format, HdrFormat::Xyze);
+        assert_eq!(
+            image.pixels,
+            [[7, 8, 9, 129], [2, 2, 0, 2], [1, 2, 3, 128], [4, 5, 6, 128]]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let read = |file: &[u8]| read_hdr(file).unwrap_err();
+        assert!(matches!(read(b"P6\n"), HdrError::Signature));
+        assert!(matches!(
+            read(&header("32-bit_rle_rgba", "-Y 1 +X 1")),
+            HdrError::Format(format) if format == "32-bit_rle_rgba"
+        ));
+        assert!(matches!(
+            read(&header("32-bit_rle_rgbe", "-X 1 +Y 1")),
+            HdrError::Resolution(_)
+        ));
+
+        let mut file = header("32-bit_rle_rgbe", "-Y 2 +X 8");
+        file.extend_from_slice(&[2, 2, 0, 8, 136, 0, 136, 0, 136, 0, 136, 0]);
+        // The width in the marker is wrong.
+        let mut wrong_width = file.clone();
+        wrong_width.extend_from_slice(&[2, 2, 0, 9]);
+        assert!(matches!(read(&wrong_width), HdrError::Scanline { row: 1 }));
+        // A run past the end of the row.
+        let mut overrun = file.clone();
+        overrun.extend_from_slice(&[2, 2, 0, 8, 137, 0]);
+        assert!(matches!(read(&overrun), HdrError::Scanline { row: 1 }));
+        let mut empty_span = file.clone();
+        empty_span.extend_from_slice(&[2, 2, 0, 8, 0]);
+        assert!(matches!(read(&empty_span), HdrError::Scanline { row: 1 }));
+        assert!(
+            matches!(read(&file), HdrError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof)
+        );
+
+        // Sizes whose bytes overflow, and sizes far larger than the file, with and without RLE.
+        assert!(matches!(
+            read(&header("32-bit_rle_rgbe", "-Y 4294967295 +X 4294967295")),
+            HdrError::Resolution(_)
+        ));
+        for resolution in ["-Y 100000 +X 100000", "-Y 4294967295 +X 8"] {
+            assert!(matches!(
+                read(&header("32-bit_rle_rgbe", resolution)),
+                HdrError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+            ));
+        }
+
+        let error = HdrImage::from_vec3(2, 2, HdrFormat::Rgbe, &[[0.0; 3]; 3]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let image = HdrImage {
+            width: 2,
+            height: 2,
+            format: HdrFormat::Rgbe,
+            pixels: vec![[0; 4]; 5],
+        };
+        let error = write_hdr(Vec::new(), &image).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = rand::thread_rng();
+        for width in [0, 1, 7, 8, 9, 100, 300, 0x7FFF, 0x8000] {
+            let height = rng.gen_range(1..4);
+            // Mixes runs of every length with random bytes.
+            let pixels = (0..width * height)
+                .map(|_| {
+                    if rng.gen_bool(0.5) {
+                        [rng.gen(), 7, 7, 130]
+                    } else {
+                        [rng.gen_range(0..3), rng.gen(), 7, rng.gen()]
+                    }
+                })
+                .collect();
+            let image = HdrImage {
+                width,
+                height,
+                format: HdrFormat::Rgbe,
+                pixels,
+            };
+            let mut file = Vec::new();
+            write_hdr(&mut file, &image).unwrap();
+            assert_eq!(read_hdr(&file[..]).unwrap(), image);
+            if is_rle_width(width) && width > 100 {
+                // The constant channel compresses, so it's smaller than flat pixels.
+                assert!(file.len() < (width * height * 4) as usize);
+            }
+        }
+
+        let rgb: Vec<[f32; 3]> = (0..64).map(|i| [i as f32, 1.0, 1e-3]).collect();
+        let image = HdrImage::from_vec3(16, 4, HdrFormat::Rgbe, &rgb).unwrap();
+        let mut file = Vec::new();
+        write_hdr(&mut file, &image).unwrap();
+        let decoded = read_hdr(&file[..]).unwrap().to_vec3();
+        // 1e-3 is below the truncated mantissa step of 1.0.
+        assert_eq!(decoded[1], [1.0, 1.0, 0.0]);
+        for (decoded, rgb) in decoded.iter().zip(&rgb) {
+            assert_eq!(*decoded, rgbe8_to_vec3(vec3_to_rgbe8(*rgb)));
+        }
+    }
+}
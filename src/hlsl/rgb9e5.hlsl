@@ -0,0 +1,118 @@
+static const uint RGB9E5_EXPONENT_BITS        = 5u;
+static const int  RGB9E5_MANTISSA_BITS        = 9;
+static const uint RGB9E5_MANTISSA_BITSU       = 9u;
+static const int  RGB9E5_EXP_BIAS             = 15;
+static const uint RGB9E5_MAX_VALID_BIASED_EXP = 31u;
+
+static const uint  MAX_RGB9E5_EXP             = 16u;
+static const int   RGB9E5_MANTISSA_VALUES     = 512;
+static const int   MAX_RGB9E5_MANTISSA        = 511;
+static const uint  MAX_RGB9E5_MANTISSAU       = 511u;
+static const float MAX_RGB9E5_                = 65408.0;
+static const float EPSILON_RGB9E5_            = 0.000000059604645;
+
+int floor_log2(float x) {
+    uint f = asuint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+
+// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 nan_to_zero(float3 v) {
+    uint3 b = asuint(v);
+    return asfloat(b * uint3((b & 0x7FFFFFFFu) <= 0x7F800000u));
+}
+
+// https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint vec3_to_rgb9e5(float3 rgb_in) {
+    float3 rgb = clamp(nan_to_zero(rgb_in), 0.0, MAX_RGB9E5_);
+
+    float maxrgb = max(rgb.r, max(rgb.g, rgb.b));
+    int exp_shared = max(-RGB9E5_EXP_BIAS - 1, floor_log2(maxrgb)) + 1 + RGB9E5_EXP_BIAS;
+    float denom = exp2(float(exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS));
+
+    int maxm = int(floor(maxrgb / denom + 0.5));
+    if (maxm == RGB9E5_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uint3 n = uint3(floor(rgb / denom + 0.5));
+
+    return (uint(exp_shared) << 27u) | (n.b << 18u) | (n.g << 9u) | (n.r << 0u);
+}
+
+// HLSL has no intrinsic for extracting a bitfield.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+
+float3 rgb9e5_to_vec3(uint v) {
+    int exponent = int(bitfield_extract(v, 27u, RGB9E5_EXPONENT_BITS)) - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS;
+    float scale = exp2(float(exponent));
+
+    return float3(
+        float((v >> 0u) & ((1u << RGB9E5_MANTISSA_BITSU) - 1u)),
+        float((v >> 9u) & ((1u << RGB9E5_MANTISSA_BITSU) - 1u)),
+        float((v >> 18u) & ((1u << RGB9E5_MANTISSA_BITSU) - 1u))
+    ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uint3 mantissas;
+    uint3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uint3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uint3 over = uint3(m > (max_mantissa >> d));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_rgb9e5(uint v) {
+    uint3 m = uint3(bitfield_extract(v, 0u, RGB9E5_MANTISSA_BITSU), bitfield_extract(v, 9u, RGB9E5_MANTISSA_BITSU), bitfield_extract(v, 18u, RGB9E5_MANTISSA_BITSU));
+    SharedExponentParts parts = { bitfield_extract(v, 27u, RGB9E5_EXPONENT_BITS), m, (uint3)0u };
+    return parts;
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_rgb9e5 checks them instead.
+uint compose_rgb9e5(SharedExponentParts parts) {
+    uint3 m = parts.mantissas & MAX_RGB9E5_MANTISSAU;
+    return ((parts.exponent & RGB9E5_MAX_VALID_BIASED_EXP) << 27u) | (m.z << 18u) | (m.y << 9u) | (m.x << 0u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_rgb9e5 of the scaled value.
+uint ldexp_rgb9e5(uint v, int k) {
+    return compose_rgb9e5(ldexp_parts(decompose_rgb9e5(v), k, RGB9E5_EXPONENT_BITS, RGB9E5_MANTISSA_BITSU));
+}
@@ -0,0 +1,156 @@
+static const uint XYZ13E6_EXPONENT_BITS        = 6u;
+static const int  XYZ13E6_MANTISSA_BITS        = 13;
+static const uint XYZ13E6_MANTISSA_BITSU       = 13u;
+static const int  XYZ13E6_EXP_BIAS             = 31;
+static const uint XYZ13E6_MAX_VALID_BIASED_EXP = 63u;
+
+static const uint  MAX_XYZ13E6_EXP             = 32u;
+static const int   XYZ13E6_MANTISSA_VALUES     = 8192;
+static const int   MAX_XYZ13E6_MANTISSA        = 8191;
+static const uint  MAX_XYZ13E6_MANTISSAU       = 8191u;
+static const float MAX_XYZ13E6_                = 4294443000.0;
+static const float EPSILON_XYZ13E6_            = 0.00000000000005684342;
+
+int floor_log2(float x) {
+    uint f = asuint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+
+uint is_sign_negative(float v) {
+    return (asuint(v) >> 31u) & 1u;
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = asuint(v) & 0x7FFFFFFFu;
+    return asfloat(b * uint3(b <= 0x7F800000u));
+}
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint2 vec3_to_xyz13e6(float3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x);
+    uint ysign = is_sign_negative(xyz_in.y);
+    uint zsign = is_sign_negative(xyz_in.z);
+
+    float3 xyz = min(abs_nan_to_zero(xyz_in), MAX_XYZ13E6_);
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ13E6_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ13E6_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ13E6_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uint3 s = uint3(floor(xyz / denom + 0.5));
+
+    uint out_a = (uint(exp_shared) << 26u) | (s.y << 13u) | (s.x << 0u);
+    uint out_b = (zsign << 15u) | (ysign << 14u) | (xsign << 13u) | (s.z << 0u);
+
+    return uint2(out_a, out_b);
+}
+
+// HLSL has no intrinsic for extracting a bitfield.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+
+float3 xyz13e6_to_vec3(uint2 v) {
+    int exponent = int(bitfield_extract(v.x, 26u, XYZ13E6_EXPONENT_BITS)) - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS;
+    float scale = exp2(float(exponent));
+
+    // bitfield_extract(v.y, 13u, 1u) << 31u is extracting the sign bit and
+    // shifts it over to the corresponding IEEE 754 sign location
+    return float3(
+        asfloat(asuint(float(bitfield_extract(v.x,  0u, XYZ13E6_MANTISSA_BITSU))) | bitfield_extract(v.y, 13u, 1u) << 31u),
+        asfloat(asuint(float(bitfield_extract(v.x, 13u, XYZ13E6_MANTISSA_BITSU))) | bitfield_extract(v.y, 14u, 1u) << 31u),
+        asfloat(asuint(float(bitfield_extract(v.y,  0u, XYZ13E6_MANTISSA_BITSU))) | bitfield_extract(v.y, 15u, 1u) << 31u)
+    ) * scale;
+}
+
+#ifdef __HLSL_ENABLE_16_BIT
+// With 16-bit types (SM 6.2 and later, -enable-16bit-types) the 48 bits can be stored as three
+// 16-bit words, so that values are tightly packed at 6 bytes each in a ByteAddressBuffer.
+uint16_t3 vec3_to_xyz13e6_u16(float3 xyz_in) {
+    uint2 v = vec3_to_xyz13e6(xyz_in);
+    return uint16_t3(v.x & 0xFFFFu, v.x >> 16u, v.y);
+}
+
+float3 xyz13e6_u16_to_vec3(uint16_t3 v) {
+    return xyz13e6_to_vec3(uint2(uint(v.x) | (uint(v.y) << 16u), uint(v.z)));
+}
+#endif
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uint3 mantissas;
+    uint3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uint3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uint3 over = uint3(m > (max_mantissa >> d));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz13e6(uint2 v) {
+    uint3 m = uint3(bitfield_extract(v.x, 0u, XYZ13E6_MANTISSA_BITSU), bitfield_extract(v.x, 13u, XYZ13E6_MANTISSA_BITSU), bitfield_extract(v.y, 0u, XYZ13E6_MANTISSA_BITSU));
+    uint3 s = uint3(bitfield_extract(v.y, 13u, 1u), bitfield_extract(v.y, 14u, 1u), bitfield_extract(v.y, 15u, 1u));
+    SharedExponentParts parts = { bitfield_extract(v.x, 26u, XYZ13E6_EXPONENT_BITS), m, s };
+    return parts;
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz13e6 checks them instead.
+uint2 compose_xyz13e6(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ13E6_MAX_VALID_BIASED_EXP;
+    uint3 m = parts.mantissas & MAX_XYZ13E6_MANTISSAU;
+    uint3 s = parts.signs & 1u;
+    return uint2((e << 26u) | (m.y << 13u) | (m.x << 0u), (s.z << 15u) | (s.y << 14u) | (s.x << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uint2 negate_xyz13e6(uint2 v) {
+    return uint2(v.x, v.y ^ 0x0000E000u);
+}
+
+uint2 abs_xyz13e6(uint2 v) {
+    return uint2(v.x, v.y & ~0x0000E000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz13e6 of the scaled value.
+uint2 ldexp_xyz13e6(uint2 v, int k) {
+    return compose_xyz13e6(ldexp_parts(decompose_xyz13e6(v), k, XYZ13E6_EXPONENT_BITS, XYZ13E6_MANTISSA_BITSU));
+}
@@ -0,0 +1,146 @@
+static const uint XYZ18E7_EXPONENT_BITS        = 7u;
+static const int  XYZ18E7_MANTISSA_BITS        = 18;
+static const uint XYZ18E7_MANTISSA_BITSU       = 18u;
+static const int  XYZ18E7_EXP_BIAS             = 63;
+static const uint XYZ18E7_MAX_VALID_BIASED_EXP = 127u;
+
+static const uint  MAX_XYZ18E7_EXP             = 64u;
+static const int   XYZ18E7_MANTISSA_VALUES     = 262144;
+static const int   MAX_XYZ18E7_MANTISSA        = 262143;
+static const uint  MAX_XYZ18E7_MANTISSAU       = 262143u;
+static const float MAX_XYZ18E7_                = 1.8446674e+19;
+static const float EPSILON_XYZ18E7_            = 4.135903e-25;
+
+int floor_log2(float x) {
+    uint f = asuint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+
+uint is_sign_negative(float v) {
+    return (asuint(v) >> 31u) & 1u;
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = asuint(v) & 0x7FFFFFFFu;
+    return asfloat(b * uint3(b <= 0x7F800000u));
+}
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint2 vec3_to_xyz18e7(float3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x);
+    uint ysign = is_sign_negative(xyz_in.y);
+    uint zsign = is_sign_negative(xyz_in.z);
+
+    float3 xyz = min(abs_nan_to_zero(xyz_in), MAX_XYZ18E7_);
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ18E7_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ18E7_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ18E7_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uint3 s = uint3(floor(xyz / denom + 0.5));
+
+    uint out_a = (s.y << 18u) | (s.x << 0u);
+    uint out_b = (uint(exp_shared) << 25u) | (zsign << 24u) | (ysign << 23u) | (xsign << 22u) | (s.z << 4u) | (s.y >> 14u);
+
+    return uint2(out_a, out_b);
+}
+
+// HLSL has no intrinsic for extracting a bitfield.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+
+float3 xyz18e7_to_vec3(uint2 v) {
+    int exponent = int(bitfield_extract(v.y, 25u, XYZ18E7_EXPONENT_BITS)) - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS;
+    float scale = exp2(float(exponent));
+
+    uint xb = bitfield_extract(v.x, 0u, XYZ18E7_MANTISSA_BITSU);
+    uint yb = bitfield_extract(v.x, 18u, XYZ18E7_MANTISSA_BITSU) | bitfield_extract(v.y, 0u, 4u) << 14u;
+    uint zb = bitfield_extract(v.y, 4u, XYZ18E7_MANTISSA_BITSU);
+
+    // Extract the sign bits, then bitfield_extract(v.y, 22u, 1u) << 31u shifts it over to the corresponding IEEE 754 sign location.
+    return float3(
+        asfloat(asuint(float(xb)) | bitfield_extract(v.y, 22u, 1u) << 31u),
+        asfloat(asuint(float(yb)) | bitfield_extract(v.y, 23u, 1u) << 31u),
+        asfloat(asuint(float(zb)) | bitfield_extract(v.y, 24u, 1u) << 31u)
+    ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uint3 mantissas;
+    uint3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uint3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uint3 over = uint3(m > (max_mantissa >> d));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz18e7(uint2 v) {
+    uint3 m = uint3(bitfield_extract(v.x, 0u, XYZ18E7_MANTISSA_BITSU), bitfield_extract(v.x, 18u, 14u) | bitfield_extract(v.y, 0u, 4u) << 14u, bitfield_extract(v.y, 4u, XYZ18E7_MANTISSA_BITSU));
+    uint3 s = uint3(bitfield_extract(v.y, 22u, 1u), bitfield_extract(v.y, 23u, 1u), bitfield_extract(v.y, 24u, 1u));
+    SharedExponentParts parts = { bitfield_extract(v.y, 25u, XYZ18E7_EXPONENT_BITS), m, s };
+    return parts;
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz18e7 checks them instead.
+uint2 compose_xyz18e7(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ18E7_MAX_VALID_BIASED_EXP;
+    uint3 m = parts.mantissas & MAX_XYZ18E7_MANTISSAU;
+    uint3 s = parts.signs & 1u;
+    return uint2((m.y << 18u) | (m.x << 0u), (e << 25u) | (s.z << 24u) | (s.y << 23u) | (s.x << 22u) | (m.z << 4u) | (m.y >> 14u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uint2 negate_xyz18e7(uint2 v) {
+    return uint2(v.x, v.y ^ 0x01C00000u);
+}
+
+uint2 abs_xyz18e7(uint2 v) {
+    return uint2(v.x, v.y & ~0x01C00000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz18e7 of the scaled value.
+uint2 ldexp_xyz18e7(uint2 v, int k) {
+    return compose_xyz18e7(ldexp_parts(decompose_xyz18e7(v), k, XYZ18E7_EXPONENT_BITS, XYZ18E7_MANTISSA_BITSU));
+}
@@ -0,0 +1,628 @@
+// KTX2 files for Vulkan and wgpu, uncompressed and without supercompression. rgb9e5 is stored as
+// VK_FORMAT_E5B9G9R9_UFLOAT_PACK32, which the gpu samples directly. The other formats are stored
+// as the R32_UINT or R32G32_UINT textures `Format::gpu_format` lists, with a key/value entry
+// naming the format so they can be read back. 2D textures, cubemaps and arrays of either are
+// supported, with mip chains.
+// https://registry.khronos.org/KTX/specs/2.0/ktxspec.v2.html
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::dds::{max_mip_levels, mip_size};
+use crate::gpu_format::TextureFormat;
+use crate::shaders::Format;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+// The identifier, the 9 header fields and the index up to the level index.
+const HEADER_SIZE: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 3 * 8;
+
+// The key of the entry naming the crate format, with the name of `Format::name` as its value.
+pub const FORMAT_KEY: &str = "shared_exponent_formats.format";
+
+// Data Format Descriptor fields.
+const KHR_DF_VERSIONNUMBER_1_3: u32 = 2;
+const KHR_DF_MODEL_RGBSDA: u32 = 1;
+const KHR_DF_PRIMARIES_BT709: u32 = 1;
+const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+const KHR_DF_SAMPLE_DATATYPE_EXPONENT: u32 = 0x20;
+const KHR_DF_CHANNEL_RED: u32 = 0;
+const KHR_DF_CHANNEL_GREEN: u32 = 1;
+const KHR_DF_CHANNEL_BLUE: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ktx2Texture {
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    // 0 for a texture that isn't an array, like KTX2's layerCount.
+    pub layers: u32,
+    pub cubemap: bool,
+    pub mip_levels: u32,
+    // Every mip level from the largest, each holding the images of every layer and, within a
+    // layer, every face, with rows from top to bottom. The texels are the words of the texture
+    // format: one for rgb9e5 and the 32 bit formats, and two for the others, with the u16 of
+    // xyz13e6 and xyz14e3 in the low half of the second word. Cubemap faces are in the order +X,
+    // -X, +Y, -Y, +Z, -Z.
+    pub levels: Vec<Vec<u32>>,
+}
+
+#[derive(Debug)]
+pub enum Ktx2Error {
+    Io(io::Error),
+    Identifier,
+    Header(&'static str),
+    // The vkFormat, when it isn't one of the texture formats of `Format::gpu_format`.
+    Format(u32),
+    // The value of the format key, when it doesn't name a format stored as the vkFormat.
+    FormatName(String),
+}
+
+impl fmt::Display for Ktx2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ktx2Error::Io(error) => write!(f, "{error}"),
+            Ktx2Error::Identifier => write!(f, "not a ktx2 file"),
+            Ktx2Error::Header(problem) => write!(f, "unsupported ktx2 file, {problem}"),
+            Ktx2Error::Format(format) => write!(f, "unsupported vk format {format}"),
+            Ktx2Error::FormatName(name) => write!(f, "unknown {FORMAT_KEY} {name}"),
+        }
+    }
+}
+
+impl std::error::Error for Ktx2Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Ktx2Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Ktx2Error {
+    fn from(error: io::Error) -> Self {
+        Ktx2Error::Io(error)
+    }
+}
+
+impl Ktx2Texture {
+    // A 2D texture with one level.
+    pub fn new(format: Format, width: u32, height: u32, texels: Vec<u32>) -> Self {
+        Ktx2Texture {
+            format,
+            width,
+            height,
+            layers: 0,
+            cubemap: false,
+            mip_levels: 1,
+            levels: vec![texels],
+        }
+    }
+
+    pub fn faces(&self) -> u32 {
+        if self.cubemap {
+            6
+        } else {
+            1
+        }
+    }
+
+    // The words of one texel.
+    pub fn texel_words(&self) -> usize {
+        self.format.gpu_format().texture_format.texel_size() as usize / 4
+    }
+
+    // The texels of one image of a level.
+    pub fn image(&self, level: u32, layer: u32, face: u32) -> &[u32] {
+        let size = self.image_words(level);
+        let start = (layer * self.faces() + face) as usize * size;
+        &self.levels[level as usize][start..start + size]
+    }
+
+    fn image_words(&self, level: u32) -> usize {
+        let width = mip_size(self.width, level) as usize;
+        let height = mip_size(self.height, level) as usize;
+        width * height * self.texel_words()
+    }
+
+    // The words of all images of a level, None when the bytes don't fit in memory.
+    fn level_words(&self, level: u32) -> Option<usize> {
+        let images = self.layers.max(1) as usize * self.faces() as usize;
+        (mip_size(self.width, level) as usize)
+            .checked_mul(mip_size(self.height, level) as usize)?
+            .checked_mul(self.texel_words() * images)
+            .filter(|words| words.checked_mul(4).is_some())
+    }
+
+    fn check(&self) -> io::Result<()> {
+        let invalid = |problem| Err(io::Error::new(io::ErrorKind::InvalidInput, problem));
+        if self.width == 0 || self.height == 0 {
+            return invalid("empty texture");
+        }
+        if self.cubemap && self.width != self.height {
+            return invalid("cubemap faces aren't square");
+        }
+        if self.mip_levels == 0 || self.mip_levels > max_mip_levels(self.width, self.height) {
+            return invalid("invalid mip count");
+        }
+        if self.levels.len() != self.mip_levels as usize {
+            return invalid("wrong number of levels");
+        }
+        for (level, texels) in self.levels.iter().enumerate() {
+            if Some(texels.len()) != self.level_words(level as u32) {
+                return invalid("level doesn't match its size");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn vk_format(format: Format) -> u32 {
+    format.gpu_format().texture_format.vk_format()
+}
+
+// The basic descriptor block. rgb9e5 has a mantissa and an exponent sample per channel, with the
+// values of the example in the Khronos Data Format Specification. The others are unsigned
+// integer channels, whose upper value is 1 as for any channel that isn't normalized.
+fn data_format_descriptor(format: Format) -> Vec<u32> {
+    let texture_format = format.gpu_format().texture_format;
+    let mut samples = Vec::new();
+    match texture_format {
+        TextureFormat::Rgb9e5Ufloat => {
+            let channels = [
+                KHR_DF_CHANNEL_RED,
+                KHR_DF_CHANNEL_GREEN,
+                KHR_DF_CHANNEL_BLUE,
+            ];
+            for (i, channel) in channels.into_iter().enumerate() {
+                samples.extend([(i as u32 * 9) | 8 << 16 | channel << 24, 0, 0, 8448]);
+                let exponent = channel | KHR_DF_SAMPLE_DATATYPE_EXPONENT;
+                samples.extend([27 | 4 << 16 | exponent << 24, 0, 15, 31]);
+            }
+        }
+        TextureFormat::R32Uint => samples.extend([31 << 16 | KHR_DF_CHANNEL_RED << 24, 0, 0, 1]),
+        TextureFormat::Rg32Uint => {
+            samples.extend([31 << 16 | KHR_DF_CHANNEL_RED << 24, 0, 0, 1]);
+            samples.extend([32 | 31 << 16 | KHR_DF_CHANNEL_GREEN << 24, 0, 0, 1]);
+        }
+    }
+
+    let block_size = 24 + samples.len() as u32 * 4;
+    let mut dfd = vec![
+        4 + block_size,
+        0,
+        KHR_DF_VERSIONNUMBER_1_3 | block_size << 16,
+        KHR_DF_MODEL_RGBSDA | KHR_DF_PRIMARIES_BT709 << 8 | KHR_DF_TRANSFER_LINEAR << 16,
+        0,
+        texture_format.texel_size(),
+        0,
+    ];
+    dfd.extend(samples);
+    dfd
+}
+
+fn key_value_data(format: Format) -> Vec<u8> {
+    let mut entry = Vec::new();
+    entry.extend_from_slice(FORMAT_KEY.as_bytes());
+    entry.push(0);
+    entry.extend_from_slice(format.name().as_bytes());
+    entry.push(0);
+
+    let mut data = (entry.len() as u32).to_le_bytes().to_vec();
+    data.extend(entry);
+    data.resize(data.len().next_multiple_of(4), 0);
+    data
+}
+
+pub fn write_ktx2(mut writer: impl Write, texture: &Ktx2Texture) -> io::Result<()> {
+    texture.check()?;
+    let texel_size = texture.format.gpu_format().texture_format.texel_size();
+
+    let dfd: Vec<u8> = data_format_descriptor(texture.format)
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let kvd = key_value_data(texture.format);
+    let dfd_offset = HEADER_SIZE + LEVEL_INDEX_ENTRY_SIZE * texture.mip_levels as usize;
+    let kvd_offset = dfd_offset + dfd.len();
+
+    // Levels are stored from the smallest, each aligned to the texel size, which is a multiple of
+    // 4 for these formats.
+    let mut offset = kvd_offset + kvd.len();
+    let mut level_index = vec![[0u64; 3]; texture.mip_levels as usize];
+    for (level, texels) in texture.levels.iter().enumerate().rev() {
+        offset = offset.next_multiple_of(texel_size as usize);
+        let length = (texels.len() * 4) as u64;
+        level_index[level] = [offset as u64, length, length];
+        offset += texels.len() * 4;
+    }
+
+    let mut bytes = IDENTIFIER.to_vec();
+    let header = [
+        vk_format(texture.format),
+        4,
+        texture.width,
+        texture.height,
+        0,
+        texture.layers,
+        texture.faces(),
+        texture.mip_levels,
+        0,
+        dfd_offset as u32,
+        dfd.len() as u32,
+        kvd_offset as u32,
+        kvd.len() as u32,
+    ];
+    bytes.extend(header.iter().flat_map(|word| word.to_le_bytes()));
+    // No supercompression global data.
+    bytes.extend([0; 16]);
+    bytes.extend(level_index.iter().flatten().flat_map(|x| x.to_le_bytes()));
+    bytes.extend(dfd);
+    bytes.extend(kvd);
+    for (level, texels) in texture.levels.iter().enumerate().rev() {
+        bytes.resize(level_index[level][0] as usize, 0);
+        bytes.extend(texels.iter().flat_map(|word| word.to_le_bytes()));
+    }
+    writer.write_all(&bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+// The bytes at offset..offset + length, or an error if the file is too short.
+fn range(bytes: &[u8], offset: u64, length: u64) -> Result<&[u8], Ktx2Error> {
+    let end = offset
+        .checked_add(length)
+        .filter(|&end| end <= bytes.len() as u64);
+    match end {
+        Some(end) => Ok(&bytes[offset as usize..end as usize]),
+        None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+    }
+}
+
+// The value of `key`, without the terminating NUL.
+fn find_key<'a>(mut kvd: &'a [u8], key: &str) -> Result<Option<&'a [u8]>, Ktx2Error> {
+    while kvd.len() >= 4 {
+        let length = read_u32(kvd, 0) as u64;
+        let entry = range(kvd, 4, length)?;
+        if let Some(value) = entry.strip_prefix(key.as_bytes()) {
+            if let Some(value) = value.strip_prefix(&[0]) {
+                return Ok(Some(value.strip_suffix(&[0]).unwrap_or(value)));
+            }
+        }
+        let next = (4 + entry.len()).next_multiple_of(4).min(kvd.len());
+        kvd = &kvd[next..];
+    }
+    Ok(None)
+}
+
+pub fn read_ktx2(mut reader: impl Read) -> Result<Ktx2Texture, Ktx2Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let header = range(&bytes, 0, HEADER_SIZE as u64)?;
+    if header[..12] != IDENTIFIER {
+        return Err(Ktx2Error::Identifier);
+    }
+    let field = |i: usize| read_u32(header, 12 + i * 4);
+    let [vk, type_size, width, height, depth, layers, faces, mip_count, supercompression] =
+        [0, 1, 2, 3, 4, 5, 6, 7, 8].map(field);
+    let [kvd_offset, kvd_length] = [11, 12].map(field);
+
+    if supercompression != 0 {
+        return Err(Ktx2Error::Header("supercompression isn't supported"));
+    }
+    if type_size != 4 {
+        return Err(Ktx2Error::Header("wrong type size"));
+    }
+    if depth != 0 || height == 0 || width == 0 {
+        return Err(Ktx2Error::Header("not a 2D texture"));
+    }
+    let cubemap = match faces {
+        1 => false,
+        6 if width == height => true,
+        _ => return Err(Ktx2Error::Header("invalid face count")),
+    };
+    // 0 asks the loader to generate the mips, the file still holds one level.
+    let mip_levels = mip_count.max(1);
+    if mip_levels > max_mip_levels(width, height) {
+        return Err(Ktx2Error::Header("too many mip levels"));
+    }
+
+    let texture_formats = [
+        TextureFormat::Rgb9e5Ufloat,
+        TextureFormat::R32Uint,
+        TextureFormat::Rg32Uint,
+    ];
+    if !texture_formats
+        .iter()
+        .any(|format| format.vk_format() == vk)
+    {
+        return Err(Ktx2Error::Format(vk));
+    }
+    // Files from other tools have no format key, which is fine for rgb9e5.
+    let kvd = range(&bytes, kvd_offset as u64, kvd_length as u64)?;
+    let format = match find_key(kvd, FORMAT_KEY)? {
+        Some(name) => Format::ALL
+            .into_iter()
+            .find(|format| format.name().as_bytes() == name && vk_format(*format) == vk)
+            .ok_or_else(|| Ktx2Error::FormatName(String::from_utf8_lossy(name).into_owned()))?,
+        None if vk == TextureFormat::Rgb9e5Ufloat.vk_format() => Format::Rgb9e5,
+        None => return Err(Ktx2Error::Header("no format key for an integer texture")),
+    };
+
+    let mut texture = Ktx2Texture {
+        format,
+        width,
+        height,
+        layers,
+        cubemap,
+        mip_levels,
+        levels: Vec::new(),
+    };
+    let level_index = range(
+        &bytes,
+        HEADER_SIZE as u64,
+        (LEVEL_INDEX_ENTRY_SIZE * mip_levels as usize) as u64,
+    )?;
+    for level in 0..mip_levels {
+        let entry = level as usize * LEVEL_INDEX_ENTRY_SIZE;
+        let [offset, length] = [0, 8].map(|i| read_u64(level_index, entry + i));
+        let words = texture
+            .level_words(level)
+            .ok_or(Ktx2Error::Header("texture too large"))?;
+        if length != words as u64 * 4 {
+            return Err(Ktx2Error::Header("wrong level size"));
+        }
+        let data = range(&bytes, offset, length)?;
+        texture.levels.push(
+            data.chunks_exact(4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+                .collect(),
+        );
+    }
+    Ok(texture)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+    use crate::rgb9e5::vec3_to_rgb9e5;
+    use crate::xyz13e6::vec3_to_xyz13e6;
+
+    fn random_vec3() -> [f32; 3] {
+        let mut rng = rand::thread_rng();
+        [
+            rng.gen(),
+            rng.gen_range(-1.0..1.0),
+            rng.gen::<f32>() * 100.0,
+        ]
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u32> {
+        bytes.chunks(4).map(|word| read_u32(word, 0)).collect()
+    }
+
+    #[test]
+    fn test_rgb9e5() {
+        // 3x2 and 1x1.
+        let levels = vec![
+            (0..6).map(|_| vec3_to_rgb9e5(random_vec3())).collect(),
+            vec![vec3_to_rgb9e5(random_vec3())],
+        ];
+        let texture = Ktx2Texture {
+            format: Format::Rgb9e5,
+            width: 3,
+            height: 2,
+            layers: 0,
+            cubemap: false,
+            mip_levels: 2,
+            levels: levels.clone(),
+        };
+        let mut file = Vec::new();
+        write_ktx2(&mut file, &texture).unwrap();
+
+        let mut expected = IDENTIFIER.to_vec();
+        // vkFormat, typeSize, size, layers, faces, levels and supercompression.
+        let header: [u32; 9] = [123, 4, 3, 2, 0, 0, 1, 2, 0];
+        // The dfd after the header and the level index, the key/value data after it, and no
+        // supercompression data.
+        let index: [u32; 8] = [128, 124, 252, 44, 0, 0, 0, 0];
+        // The smallest level first.
+        let level_index: [u32; 12] = [300, 0, 24, 0, 24, 0, 296, 0, 4, 0, 4, 0];
+        let dfd: [u32; 31] = [
+            124,
+            0,
+            2 | 120 << 16,
+            1 | 1 << 8 | 1 << 16,
+            0,
+            4,
+            0,
+            // Mantissa and exponent of red, green and blue.
+            0x00080000,
+            0,
+            0,
+            8448,
+            0x2004001B,
+            0,
+            15,
+            31,
+            0x01080009,
+            0,
+            0,
+            8448,
+            0x2104001B,
+            0,
+            15,
+            31,
+            0x02080012,
+            0,
+            0,
+            8448,
+            0x2204001B,
+            0,
+            15,
+            31,
+        ];
+        for word in header.iter().chain(&index).chain(&level_index).chain(&dfd) {
+            expected.extend(word.to_le_bytes());
+        }
+        expected.extend(38u32.to_le_bytes());
+        expected.extend(b"shared_exponent_formats.format\0rgb9e5\0\0\0");
+        for word in levels[1].iter().chain(&levels[0]) {
+            expected.extend(word.to_le_bytes());
+        }
+        assert_eq!(words(&file), words(&expected));
+        assert_eq!(read_ktx2(&file[..]).unwrap(), texture);
+
+        // Without the format key, as other tools write it.
+        let kvd_length = 44;
+        let mut other = file.clone();
+        other[252..252 + kvd_length].fill(0);
+        other[252..256].copy_from_slice(&10u32.to_le_bytes());
+        other[256..266].copy_from_slice(b"KTXwriter\0");
+        assert_eq!(read_ktx2(&other[..]).unwrap(), texture);
+    }
+
+    #[test]
+    fn test_array_cubemap() {
+        // Two cubes with 4x4, 2x2 and 1x1 faces.
+        let levels: Vec<Vec<u32>> = [16, 4, 1]
+            .into_iter()
+            .map(|size| {
+                (0..size * 12)
+                    .flat_map(|_| {
+                        let (a, b) = vec3_to_xyz13e6(random_vec3());
+                        [a, b as u32]
+                    })
+                    .collect()
+            })
+            .collect();
+        let texture = Ktx2Texture {
+            format: Format::Xyz13e6,
+            width: 4,
+            height: 4,
+            layers: 2,
+            cubemap: true,
+            mip_levels: 3,
+            levels: levels.clone(),
+        };
+        let mut file = Vec::new();
+        write_ktx2(&mut file, &texture).unwrap();
+
+        let header = words(&file[12..HEADER_SIZE]);
+        // R32G32_UINT with 2 layers of 6 faces.
+        assert_eq!(header[..9], [101, 4, 4, 4, 0, 2, 6, 3, 0]);
+        for level in 0..3 {
+            let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY_SIZE;
+            let offset = read_u64(&file, entry) as usize;
+            assert_eq!(offset % 8, 0);
+            assert_eq!(read_u64(&file, entry + 8), levels[level].len() as u64 * 4);
+            assert_eq!(
+                words(&file[offset..offset + levels[level].len() * 4]),
+                levels[level]
+            );
+        }
+        let dfd_offset = header[9] as usize;
+        // Two 32 bit channels in an 8 byte texel.
+        assert_eq!(
+            words(&file[dfd_offset..dfd_offset + 60]),
+            [
+                60,
+                0,
+                2 | 56 << 16,
+                0x10101,
+                0,
+                8,
+                0,
+                0x001F0000,
+                0,
+                0,
+                1,
+                0x011F0020,
+                0,
+                0,
+                1
+            ]
+        );
+
+        let read = read_ktx2(&file[..]).unwrap();
+        assert_eq!(read, texture);
+        // The second cube's -X face of the 2x2 level.
+        assert_eq!(read.image(1, 1, 1), &levels[1][7 * 8..8 * 8]);
+        assert_eq!(read.texel_words(), 2);
+    }
+
+    #[test]
+    fn test_formats() {
+        for format in Format::ALL {
+            let texel_words = format.gpu_format().texture_format.texel_size() as usize / 4;
+            let mut rng = rand::thread_rng();
+            let texels = (0..10 * texel_words).map(|_| rng.gen()).collect();
+            let texture = Ktx2Texture::new(format, 5, 2, texels);
+            let mut file = Vec::new();
+            write_ktx2(&mut file, &texture).unwrap();
+            assert_eq!(read_ktx2(&file[..]).unwrap(), texture);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let texture = Ktx2Texture::new(Format::Xyz8e5, 1, 1, vec![0]);
+        let mut file = Vec::new();
+        write_ktx2(&mut file, &texture).unwrap();
+        let read = |file: &[u8]| read_ktx2(file).unwrap_err();
+
+        let mut wrong = file.clone();
+        wrong[0] = 0;
+        assert!(matches!(read(&wrong), Ktx2Error::Identifier));
+        // VK_FORMAT_R8G8B8A8_UNORM.
+        let mut wrong = file.clone();
+        wrong[12] = 37;
+        assert!(matches!(read(&wrong), Ktx2Error::Format(37)));
+        // Zstandard.
+        let mut wrong = file.clone();
+        wrong[44] = 2;
+        assert!(matches!(read(&wrong), Ktx2Error::Header(_)));
+        // An xyz8e5 texture named xyz9e2 is fine, a name stored as another texture format isn't.
+        let kvd_offset = read_u32(&file, 12 + 11 * 4) as usize;
+        let name = kvd_offset + 4 + FORMAT_KEY.len() + 1;
+        let mut other = file.clone();
+        other[name..name + 6].copy_from_slice(b"xyz9e2");
+        assert_eq!(read_ktx2(&other[..]).unwrap().format, Format::Xyz9e2);
+        other[name..name + 7].copy_from_slice(b"xyz13e6");
+        assert!(matches!(read(&other), Ktx2Error::FormatName(name) if name == "xyz13e6"));
+        // An integer texture without the key.
+        let mut wrong = file.clone();
+        wrong[kvd_offset + 4] = b'x';
+        assert!(matches!(read(&wrong), Ktx2Error::Header(_)));
+        assert!(matches!(
+            read(&file[..file.len() - 1]),
+            Ktx2Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        // Sizes and layer counts whose bytes overflow.
+        for field in [2, 5] {
+            let mut wrong = file.clone();
+            wrong[12 + field * 4..16 + field * 4].fill(0xFF);
+            wrong[20..28].fill(0xFF);
+            assert!(matches!(read(&wrong), Ktx2Error::Header(_)));
+        }
+
+        let mut texture = Ktx2Texture::new(Format::Xyz8e5, 2, 2, vec![0; 4]);
+        texture.layers = 2;
+        let error = write_ktx2(Vec::new(), &texture).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let texture = Ktx2Texture::new(Format::Xyz8e5, 0, 0, Vec::new());
+        let error = write_ktx2(Vec::new(), &texture).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,6 +1,39 @@
+// no_std without the std feature. The tests always have std, and run with either math backend.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("without the std feature, the libm feature is needed for the float math");
+
+#[cfg(any(feature = "alloc", test))]
+extern crate alloc;
+
 pub mod custom_shared_format;
+#[cfg(feature = "std")]
+pub mod dds;
 pub mod evaluate;
+#[cfg(feature = "std")]
+pub mod exhaustive;
+pub mod float16;
+pub mod gpu_format;
+#[cfg(feature = "std")]
+pub mod hdr;
+#[cfg(feature = "std")]
+pub mod ktx2;
+pub mod math;
+pub mod packed48;
+pub mod parts;
+#[cfg(feature = "std")]
+pub mod pfm;
 pub mod rgb9e5;
+pub mod rgbe8;
+#[cfg(test)]
+mod shader_consistency;
+#[cfg(any(feature = "alloc", test))]
+pub mod shader_emulation;
+#[cfg(any(feature = "alloc", test))]
+pub mod shader_gen;
+pub mod shaders;
+pub mod transcode;
 pub mod xyz13e6;
 pub mod xyz14e3;
 pub mod xyz18e7;
@@ -8,7 +41,7 @@ pub mod xyz18e7_f64test;
 pub mod xyz8e5;
 pub mod xyz9e2;
 
-pub fn nan_to_zero(value: f32) -> f32 {
+pub const fn nan_to_zero(value: f32) -> f32 {
     if value.is_nan() {
         0.0
     } else {
@@ -16,10 +49,30 @@ pub fn nan_to_zero(value: f32) -> f32 {
     }
 }
 
-pub fn nan_to_zero64(value: f64) -> f64 {
+pub const fn nan_to_zero64(value: f64) -> f64 {
     if value.is_nan() {
         0.0
     } else {
         value
     }
 }
+
+// The smallest exponent a packed value can use, and how far its mantissas are shifted up to
+// keep the same value. The encoders pick the smallest exponent the largest mantissa fits in, so
+// after the shift the top mantissa bit is set, or the exponent is 0. `mantissas` is all mantissas
+// or'ed together. Zero is stored with exponent 0.
+pub(crate) const fn canonical_exponent(
+    exponent: u32,
+    mantissas: u32,
+    mantissa_bits: u32,
+) -> (u32, u32) {
+    if mantissas == 0 {
+        return (0, 0);
+    }
+    let shift = mantissas.leading_zeros() - (32 - mantissa_bits);
+    if shift < exponent {
+        (exponent - shift, shift)
+    } else {
+        (0, exponent)
+    }
+}
@@ -0,0 +1,174 @@
+// The float functions core doesn't have. With the std feature these are the std methods,
+// otherwise they come from libm. The f32 versions have the libm names.
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn floorf(x: f32) -> f32 {
+    x.floor()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn floorf(x: f32) -> f32 {
+    libm::floorf(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn log2f(x: f32) -> f32 {
+    x.log2()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn log2f(x: f32) -> f32 {
+    libm::log2f(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn exp2f(x: f32) -> f32 {
+    x.exp2()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn exp2f(x: f32) -> f32 {
+    libm::exp2f(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn floor(x: f64) -> f64 {
+    x.floor()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn floor(x: f64) -> f64 {
+    libm::floor(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn log2(x: f64) -> f64 {
+    x.log2()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn log2(x: f64) -> f64 {
+    libm::log2(x)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn exp2(x: f64) -> f64 {
+    x.exp2()
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+pub fn exp2(x: f64) -> f64 {
+    libm::exp2(x)
+}
+
+// Exact floor(log2(x)) from the bits, usable in const fn. x has to be positive and finite. Zero
+// gives i32::MIN, like `log2(0.0).floor() as i32`.
+#[inline]
+pub const fn floor_log2f(x: f32) -> i32 {
+    let bits = x.to_bits() & 0x7FFFFFFF;
+    if bits == 0 {
+        i32::MIN
+    } else if bits < 0x00800000 {
+        // Subnormal, the leading one of the mantissa gives the exponent.
+        (31 - bits.leading_zeros() as i32) - 149
+    } else {
+        (bits >> 23) as i32 - 127
+    }
+}
+
+#[inline]
+pub const fn floor_log2(x: f64) -> i32 {
+    let bits = x.to_bits() & 0x7FFFFFFFFFFFFFFF;
+    if bits == 0 {
+        i32::MIN
+    } else if bits < 0x0010000000000000 {
+        (63 - bits.leading_zeros() as i32) - 1074
+    } else {
+        (bits >> 52) as i32 - 1023
+    }
+}
+
+// 2^exponent built from the bits, the same as `exp2f(exponent as f32)` but usable in const fn.
+#[inline]
+pub const fn pow2f(exponent: i32) -> f32 {
+    if exponent > 127 {
+        f32::INFINITY
+    } else if exponent >= -126 {
+        f32::from_bits(((exponent + 127) as u32) << 23)
+    } else if exponent >= -149 {
+        f32::from_bits(1 << (exponent + 149))
+    } else {
+        0.0
+    }
+}
+
+#[inline]
+pub const fn pow2(exponent: i32) -> f64 {
+    if exponent > 1023 {
+        f64::INFINITY
+    } else if exponent >= -1022 {
+        f64::from_bits(((exponent + 1023) as u64) << 52)
+    } else if exponent >= -1074 {
+        f64::from_bits(1 << (exponent + 1074))
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_floor_log2() {
+        let mut rng = rand::thread_rng();
+        let edges = [
+            f32::MIN_POSITIVE,
+            f32::MIN_POSITIVE / 2.0,
+            f32::from_bits(1),
+            f32::MAX,
+            1.0,
+            1.0 - f32::EPSILON / 2.0,
+            4.0 - f32::EPSILON,
+            65408.0,
+        ];
+        let random = (0..100000).map(|_| f32::from_bits(rng.gen_range(1..0x7F800000)));
+        for x in edges.into_iter().chain(random) {
+            // log2 rounds up to the next integer just below powers of two, so check the
+            // definition instead.
+            let e = floor_log2f(x);
+            assert!(pow2f(e) <= x && (e == 127 || x < pow2f(e + 1)), "{x:e}");
+            let e = floor_log2(x as f64);
+            assert!(pow2(e) <= x as f64 && (x as f64) < pow2(e + 1), "{x:e}");
+        }
+        assert_eq!(floor_log2f(0.0), i32::MIN);
+        assert_eq!(floor_log2(0.0), i32::MIN);
+        assert_eq!(floor_log2(f64::from_bits(1)), -1074);
+        assert_eq!(floor_log2(f64::MAX), 1023);
+    }
+
+    #[test]
+    fn test_pow2() {
+        for e in -160..140 {
+            assert_eq!(pow2f(e), exp2f(e as f32), "{e}");
+        }
+        for e in -1100..1030 {
+            assert_eq!(pow2(e), exp2(e as f64), "{e}");
+        }
+    }
+}
@@ -0,0 +1,140 @@
+#include <metal_stdlib>
+using namespace metal;
+
+constant uint XYZ13E6_EXPONENT_BITS        = 6u;
+constant int  XYZ13E6_MANTISSA_BITS        = 13;
+constant uint XYZ13E6_MANTISSA_BITSU       = 13u;
+constant int  XYZ13E6_EXP_BIAS             = 31;
+constant uint XYZ13E6_MAX_VALID_BIASED_EXP = 63u;
+
+constant uint  MAX_XYZ13E6_EXP             = 32u;
+constant int   XYZ13E6_MANTISSA_VALUES     = 8192;
+constant int   MAX_XYZ13E6_MANTISSA        = 8191;
+constant uint  MAX_XYZ13E6_MANTISSAU       = 8191u;
+constant float MAX_XYZ13E6_                = 4294443000.0;
+constant float EPSILON_XYZ13E6_            = 0.00000000000005684342;
+
+int floor_log2(float x) {
+    uint f = as_type<uint>(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+
+uint is_sign_negative(float v) {
+    return (as_type<uint>(v) >> 31u) & 1u;
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = as_type<uint3>(v) & 0x7FFFFFFFu;
+    return as_type<float3>(select(uint3(0u), b, b <= 0x7F800000u));
+}
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint2 vec3_to_xyz13e6(float3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x);
+    uint ysign = is_sign_negative(xyz_in.y);
+    uint zsign = is_sign_negative(xyz_in.z);
+
+    float3 xyz = min(abs_nan_to_zero(xyz_in), float3(MAX_XYZ13E6_));
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ13E6_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ13E6_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ13E6_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uint3 s = uint3(floor(xyz / denom + 0.5));
+
+    uint out_a = (uint(exp_shared) << 26u) | (s.y << 13u) | (s.x << 0u);
+    uint out_b = (zsign << 15u) | (ysign << 14u) | (xsign << 13u) | (s.z << 0u);
+
+    return uint2(out_a, out_b);
+}
+
+float3 xyz13e6_to_vec3(uint2 v) {
+    int exponent = int(extract_bits(v.x, 26u, XYZ13E6_EXPONENT_BITS)) - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS;
+    float scale = exp2(float(exponent));
+
+    // extract_bits(v.y, 13u, 1u) << 31u is extracting the sign bit and
+    // shifts it over to the corresponding IEEE 754 sign location
+    return float3(
+        as_type<float>(as_type<uint>(float(extract_bits(v.x,  0u, XYZ13E6_MANTISSA_BITSU))) | extract_bits(v.y, 13u, 1u) << 31u),
+        as_type<float>(as_type<uint>(float(extract_bits(v.x, 13u, XYZ13E6_MANTISSA_BITSU))) | extract_bits(v.y, 14u, 1u) << 31u),
+        as_type<float>(as_type<uint>(float(extract_bits(v.y,  0u, XYZ13E6_MANTISSA_BITSU))) | extract_bits(v.y, 15u, 1u) << 31u)
+    ) * scale;
+}
+
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uint3 mantissas;
+    uint3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uint3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uint3 over = select(uint3(0u), uint3(1u), m > (max_mantissa >> d));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz13e6(uint2 v) {
+    uint3 m = uint3(extract_bits(v.x, 0u, XYZ13E6_MANTISSA_BITSU), extract_bits(v.x, 13u, XYZ13E6_MANTISSA_BITSU), extract_bits(v.y, 0u, XYZ13E6_MANTISSA_BITSU));
+    uint3 s = uint3(extract_bits(v.y, 13u, 1u), extract_bits(v.y, 14u, 1u), extract_bits(v.y, 15u, 1u));
+    return SharedExponentParts{extract_bits(v.x, 26u, XYZ13E6_EXPONENT_BITS), m, s};
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz13e6 checks them instead.
+uint2 compose_xyz13e6(SharedExponentParts parts) {
+    uint e = parts.exponent & XYZ13E6_MAX_VALID_BIASED_EXP;
+    uint3 m = parts.mantissas & MAX_XYZ13E6_MANTISSAU;
+    uint3 s = parts.signs & 1u;
+    return uint2((e << 26u) | (m.y << 13u) | (m.x << 0u), (s.z << 15u) | (s.y << 14u) | (s.x << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uint2 negate_xyz13e6(uint2 v) {
+    return uint2(v.x, v.y ^ 0x0000E000u);
+}
+
+uint2 abs_xyz13e6(uint2 v) {
+    return uint2(v.x, v.y & ~0x0000E000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz13e6 of the scaled value.
+uint2 ldexp_xyz13e6(uint2 v, int k) {
+    return compose_xyz13e6(ldexp_parts(decompose_xyz13e6(v), k, XYZ13E6_EXPONENT_BITS, XYZ13E6_MANTISSA_BITSU));
+}
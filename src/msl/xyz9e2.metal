@@ -0,0 +1,143 @@
+#include <metal_stdlib>
+using namespace metal;
+
+constant uint XYZ9E2_EXPONENT_BITS        = 2u;
+constant int  XYZ9E2_MANTISSA_BITS        = 9;
+constant uint XYZ9E2_MANTISSA_BITSU       = 9u;
+constant int  XYZ9E2_EXP_BIAS             = 3;
+constant uint XYZ9E2_MAX_VALID_BIASED_EXP = 3u;
+
+constant uint  MAX_XYZ9E2_EXP             = 0u;
+constant int   XYZ9E2_MANTISSA_VALUES     = 512;
+constant int   MAX_XYZ9E2_MANTISSA        = 511;
+constant uint  MAX_XYZ9E2_MANTISSAU       = 511u;
+constant float MAX_XYZ9E2_                = 1.0;
+constant float EPSILON_XYZ9E2_            = 0.00024414063;
+
+// MAX_XYZ9E2 would be 0.9980469, this is used to scale to exactly 1.0
+constant float XYZ9E2_NORM_MULT           = 1.0019569;
+
+int floor_log2(float x) {
+    uint f = as_type<uint>(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+
+uint is_sign_negative(float v) {
+    return (as_type<uint>(v) >> 31u) & 1u;
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = as_type<uint3>(v) & 0x7FFFFFFFu;
+    return as_type<float3>(select(uint3(0u), b, b <= 0x7F800000u));
+}
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+uint vec3_to_xyz9e2(float3 xyz_in) {
+    uint xsign = is_sign_negative(xyz_in.x) << 9u;
+    uint ysign = is_sign_negative(xyz_in.y) << 9u;
+    uint zsign = is_sign_negative(xyz_in.z) << 9u;
+
+    float3 xyz = min(abs_nan_to_zero(xyz_in), float3(MAX_XYZ9E2_)) / XYZ9E2_NORM_MULT;
+
+    float maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    int exp_shared = max(-XYZ9E2_EXP_BIAS - 1, floor_log2(maxxyz)) + 1 + XYZ9E2_EXP_BIAS;
+    float denom = exp2(float(exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS));
+
+    int maxm = int(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ9E2_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    uint3 s = uint3(floor(xyz / denom + 0.5));
+
+    return (uint(exp_shared) << 30u) | ((s.z | zsign) << 20u) | ((s.y | ysign) << 10u) | ((s.x | xsign) << 0u);
+}
+
+float3 xyz9e2_to_vec3(uint v) {
+    int exponent = int(extract_bits(v, 30u, XYZ9E2_EXPONENT_BITS)) - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS;
+    float scale = exp2(float(exponent)) * XYZ9E2_NORM_MULT;
+
+    // Extract both the mantissa and sign at the same time.
+    uint xb = extract_bits(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u);
+    uint yb = extract_bits(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u);
+    uint zb = extract_bits(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u);
+
+    // xb & 0x1FFu masks out for just the mantissa
+    // xb & 0x200u << 22u masks out just the sign bit and shifts it over
+    // to the corresponding IEEE 754 sign location
+    return float3(
+        as_type<float>(as_type<uint>(float(xb & 0x1FFu)) | (xb & 0x200u) << 22u),
+        as_type<float>(as_type<uint>(float(yb & 0x1FFu)) | (yb & 0x200u) << 22u),
+        as_type<float>(as_type<uint>(float(zb & 0x1FFu)) | (zb & 0x200u) << 22u)
+    ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    uint exponent;
+    uint3 mantissas;
+    uint3 signs;
+};
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+SharedExponentParts ldexp_parts(SharedExponentParts parts, int k, uint exponent_bits, uint mantissa_bits) {
+    uint max_exponent = (1u << exponent_bits) - 1u;
+    uint max_mantissa = (1u << mantissa_bits) - 1u;
+    uint3 m = parts.mantissas;
+    int e = int(parts.exponent) + clamp(k, -256, 256);
+    if (e > int(max_exponent)) {
+        uint d = min(uint(e) - max_exponent, mantissa_bits);
+        uint3 over = select(uint3(0u), uint3(1u), m > (max_mantissa >> d));
+        m = (m << d) * (1u - over) + max_mantissa * over;
+        e = int(max_exponent);
+    } else if (e < 0) {
+        uint s = uint(min(-e, 31));
+        m = (m + ((1u << s) >> 1u)) >> s;
+        e = 0;
+    }
+
+    uint any_mantissa = m.x | m.y | m.z;
+    uint shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(uint(e), mantissa_bits - 1u - uint(floor_log2(float(any_mantissa))));
+    }
+    parts.exponent = uint(e) - shift;
+    parts.mantissas = m << shift;
+    return parts;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+SharedExponentParts decompose_xyz9e2(uint v) {
+    // Extract both the mantissa and sign at the same time.
+    uint3 b = uint3(extract_bits(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u), extract_bits(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u), extract_bits(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u));
+    return SharedExponentParts{extract_bits(v, 30u, XYZ9E2_EXPONENT_BITS), b & MAX_XYZ9E2_MANTISSAU, b >> XYZ9E2_MANTISSA_BITSU};
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz9e2 checks them instead.
+uint compose_xyz9e2(SharedExponentParts parts) {
+    uint3 b = (parts.mantissas & MAX_XYZ9E2_MANTISSAU) | ((parts.signs & 1u) << XYZ9E2_MANTISSA_BITSU);
+    return ((parts.exponent & XYZ9E2_MAX_VALID_BIASED_EXP) << 30u) | (b.z << 20u) | (b.y << 10u) | (b.x << 0u);
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+uint negate_xyz9e2(uint v) {
+    return v ^ 0x20080200u;
+}
+
+uint abs_xyz9e2(uint v) {
+    return v & ~0x20080200u;
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz9e2 of the scaled value.
+uint ldexp_xyz9e2(uint v, int k) {
+    return compose_xyz9e2(ldexp_parts(decompose_xyz9e2(v), k, XYZ9E2_EXPONENT_BITS, XYZ9E2_MANTISSA_BITSU));
+}
@@ -0,0 +1,104 @@
+// Arrays of 48 bit values like xyz13e6 and xyz14e3, stored tightly with two values in every three
+// u32. This is the same as the 6 byte values one after the other in little endian order, and is
+// what packed48_load and packed48_store in the glsl and wgsl sources read and write.
+
+#[cfg(any(feature = "alloc", test))]
+use alloc::vec::Vec;
+
+// The number of u32 needed for `len` values. An odd length is padded to a whole pair.
+pub fn packed48_len(len: usize) -> usize {
+    len.div_ceil(2) * 3
+}
+
+#[inline]
+pub fn pack_pair(a: (u32, u16), b: (u32, u16)) -> [u32; 3] {
+    [
+        a.0,
+        (a.1 as u32) | (b.0 << 16),
+        (b.0 >> 16) | ((b.1 as u32) << 16),
+    ]
+}
+
+#[inline]
+pub fn unpack_pair(words: [u32; 3]) -> [(u32, u16); 2] {
+    [
+        (words[0], words[1] as u16),
+        ((words[1] >> 16) | (words[2] << 16), (words[2] >> 16) as u16),
+    ]
+}
+
+#[cfg(any(feature = "alloc", test))]
+pub fn pack(values: &[(u32, u16)]) -> Vec<u32> {
+    values
+        .chunks(2)
+        .flat_map(|pair| pack_pair(pair[0], pair.get(1).copied().unwrap_or((0, 0))))
+        .collect()
+}
+
+#[cfg(any(feature = "alloc", test))]
+pub fn unpack(words: &[u32], len: usize) -> Vec<(u32, u16)> {
+    (0..len).map(|index| load(words, index)).collect()
+}
+
+#[inline]
+pub fn load(words: &[u32], index: usize) -> (u32, u16) {
+    let pair = index / 2 * 3;
+    unpack_pair([words[pair], words[pair + 1], words[pair + 2]])[index % 2]
+}
+
+#[inline]
+pub fn store(words: &mut [u32], index: usize, value: (u32, u16)) {
+    let pair = index / 2 * 3;
+    let mut values = unpack_pair([words[pair], words[pair + 1], words[pair + 2]]);
+    values[index % 2] = value;
+    words[pair..pair + 3].copy_from_slice(&pack_pair(values[0], values[1]));
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use crate::xyz13e6::{vec3_to_xyz13e6, xyz13e6_to_vec3};
+
+    use super::*;
+
+    pub fn random_values(n: usize) -> Vec<(u32, u16)> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| (rng.gen(), rng.gen())).collect()
+    }
+
+    #[test]
+    fn test_matches_bytes() {
+        for n in [0, 1, 2, 3, 10, 11] {
+            let values = random_values(n);
+            let words = pack(&values);
+            assert_eq!(words.len(), packed48_len(n));
+
+            let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+            let expected: Vec<u8> = values
+                .iter()
+                .flat_map(|v| v.0.to_le_bytes().into_iter().chain(v.1.to_le_bytes()))
+                .collect();
+            assert_eq!(bytes[..n * 6], expected[..]);
+            assert!(bytes[n * 6..].iter().all(|b| *b == 0));
+            assert_eq!(unpack(&words, n), values);
+        }
+    }
+
+    #[test]
+    fn test_store() {
+        let values = random_values(101);
+        let mut words = vec![0; packed48_len(values.len())];
+        for (i, v) in values.iter().enumerate().rev() {
+            store(&mut words, i, *v);
+        }
+        assert_eq!(words, pack(&values));
+
+        let xyz = vec3_to_xyz13e6([1.0, -2.0, 3.0]);
+        store(&mut words, 7, xyz);
+        assert_eq!(xyz13e6_to_vec3(load(&words, 7)), [1.0, -2.0, 3.0]);
+        assert_eq!(load(&words, 6), values[6]);
+        assert_eq!(load(&words, 8), values[8]);
+    }
+}
@@ -0,0 +1,178 @@
+// The fields of a packed value, for working on values without decoding them. `decompose_xyz8e5`
+// (and the same for every format) splits a packed value into its parts, and `compose_xyz8e5`
+// packs them again after checking that every field fits.
+
+use core::fmt;
+
+use crate::canonical_exponent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Parts {
+    // The biased exponent as stored.
+    pub exponent: u32,
+    pub mantissas: [u32; 3],
+    // Always false for rgb9e5, which has no sign bits.
+    pub signs: [bool; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartsError {
+    Exponent { exponent: u32 },
+    Mantissa { component: usize, mantissa: u32 },
+    // A sign is set for a format without sign bits.
+    Sign { component: usize },
+}
+
+impl fmt::Display for PartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartsError::Exponent { exponent } => {
+                write!(f, "exponent {exponent} doesn't fit in the exponent bits")
+            }
+            PartsError::Mantissa {
+                component,
+                mantissa,
+            } => write!(
+                f,
+                "mantissa {mantissa} of component {component} doesn't fit in the mantissa bits"
+            ),
+            PartsError::Sign { component } => {
+                write!(f, "component {component} is negative in an unsigned format")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PartsError {}
+
+impl Parts {
+    pub(crate) const fn check(
+        self,
+        exponent_bits: u32,
+        mantissa_bits: u32,
+        signed: bool,
+    ) -> Result<Parts, PartsError> {
+        if self.exponent >> exponent_bits != 0 {
+            return Err(PartsError::Exponent {
+                exponent: self.exponent,
+            });
+        }
+        let mut i = 0;
+        while i < 3 {
+            if self.mantissas[i] >> mantissa_bits != 0 {
+                return Err(PartsError::Mantissa {
+                    component: i,
+                    mantissa: self.mantissas[i],
+                });
+            }
+            if !signed && self.signs[i] {
+                return Err(PartsError::Sign { component: i });
+            }
+            i += 1;
+        }
+        Ok(self)
+    }
+
+    // The value times 2^k, by changing the exponent. Past the largest exponent the mantissas are
+    // shifted up instead, and saturate at the largest mantissa like the encoders clamp. Below
+    // exponent 0 they are shifted down and rounded like the encoders round. The result is moved
+    // to the smallest exponent, so it's the code the encoder gives for the scaled value.
+    pub(crate) const fn ldexp(self, k: i32, exponent_bits: u32, mantissa_bits: u32) -> Parts {
+        let max_exponent = (1u32 << exponent_bits) - 1;
+        let max_mantissa = (1u32 << mantissa_bits) - 1;
+        // Anything past this saturates or rounds to zero anyway, and it can't overflow.
+        let k = if k > 256 {
+            256
+        } else if k < -256 {
+            -256
+        } else {
+            k
+        };
+
+        let mut m = self.mantissas;
+        let mut e = self.exponent as i32 + k;
+        if e > max_exponent as i32 {
+            let d = e as u32 - max_exponent;
+            let d = if d < mantissa_bits { d } else { mantissa_bits };
+            let mut i = 0;
+            while i < 3 {
+                m[i] = if m[i] > max_mantissa >> d {
+                    max_mantissa
+                } else {
+                    m[i] << d
+                };
+                i += 1;
+            }
+            e = max_exponent as i32;
+        } else if e < 0 {
+            let s = if -e < 31 { -e as u32 } else { 31 };
+            let mut i = 0;
+            while i < 3 {
+                m[i] = (m[i] + ((1 << s) >> 1)) >> s;
+                i += 1;
+            }
+            e = 0;
+        }
+
+        let (exponent, shift) = canonical_exponent(e as u32, m[0] | m[1] | m[2], mantissa_bits);
+        Parts {
+            exponent,
+            mantissas: [m[0] << shift, m[1] << shift, m[2] << shift],
+            signs: self.signs,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let parts = Parts {
+            exponent: 31,
+            mantissas: [255, 0, 1],
+            signs: [true, false, false],
+        };
+        assert_eq!(parts.check(5, 8, true), Ok(parts));
+        assert_eq!(
+            parts.check(4, 8, true),
+            Err(PartsError::Exponent { exponent: 31 })
+        );
+        assert_eq!(
+            parts.check(5, 7, true),
+            Err(PartsError::Mantissa {
+                component: 0,
+                mantissa: 255
+            })
+        );
+        assert_eq!(
+            parts.check(5, 8, false),
+            Err(PartsError::Sign { component: 0 })
+        );
+    }
+
+    #[test]
+    fn test_ldexp() {
+        let parts = Parts {
+            exponent: 10,
+            mantissas: [255, 64, 1],
+            signs: [false, true, false],
+        };
+        let scaled = |k| parts.ldexp(k, 5, 8);
+        assert_eq!(scaled(0), parts);
+        assert_eq!(scaled(-10).exponent, 0);
+        assert_eq!(scaled(21).exponent, 31);
+        // Saturates the first mantissa and shifts the others up.
+        assert_eq!(scaled(22).mantissas, [255, 128, 2]);
+        assert_eq!(scaled(1000).mantissas, [255, 255, 255]);
+        // Rounds to the nearest, with ties up.
+        assert_eq!(scaled(-11).mantissas, [128, 32, 1]);
+        assert_eq!(scaled(-1000).mantissas, [0; 3]);
+        assert_eq!(scaled(-1000).signs, parts.signs);
+        assert_eq!(scaled(i32::MIN).exponent, 0);
+        assert_eq!(scaled(i32::MAX).exponent, 31);
+    }
+}
@@ -0,0 +1,301 @@
+// Portable float maps, the simplest float image format: a "PF" (color) or "Pf" (gray) line, the
+// width and height, and a scale whose sign gives the byte order, negative for little endian.
+// Then the f32 pixels follow without compression, with rows from bottom to top. Images here are
+// top to bottom like the other formats, so rows are flipped when reading and writing.
+// https://www.pauldebevec.com/Research/HDR/PFM/
+
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PfmChannels {
+    Color,
+    // One channel, which is read into all three components.
+    Gray,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PfmImage {
+    pub width: u32,
+    pub height: u32,
+    // Rows from top to bottom.
+    pub pixels: Vec<[f32; 3]>,
+}
+
+#[derive(Debug)]
+pub enum PfmError {
+    Io(io::Error),
+    Header(&'static str),
+}
+
+impl fmt::Display for PfmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PfmError::Io(error) => write!(f, "{error}"),
+            PfmError::Header(problem) => write!(f, "invalid pfm header, {problem}"),
+        }
+    }
+}
+
+impl std::error::Error for PfmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PfmError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PfmError {
+    fn from(error: io::Error) -> Self {
+        PfmError::Io(error)
+    }
+}
+
+fn read_byte(reader: &mut impl BufRead) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+// A header field, skipping whitespace before it. The single whitespace byte after it is consumed,
+// so after the scale the pixels start.
+fn read_token(reader: &mut impl BufRead) -> Result<String, PfmError> {
+    let mut byte = read_byte(reader)?;
+    while byte.is_ascii_whitespace() {
+        byte = read_byte(reader)?;
+    }
+    let mut token = Vec::new();
+    while !byte.is_ascii_whitespace() {
+        if token.len() > 64 {
+            return Err(PfmError::Header("field too long"));
+        }
+        token.push(byte);
+        byte = read_byte(reader)?;
+    }
+    String::from_utf8(token).map_err(|_| PfmError::Header("field isn't ascii"))
+}
+
+pub fn read_pfm(mut reader: impl BufRead) -> Result<PfmImage, PfmError> {
+    let channels = match &read_token(&mut reader)?[..] {
+        "PF" => PfmChannels::Color,
+        "Pf" => PfmChannels::Gray,
+        _ => return Err(PfmError::Header("not a pfm file")),
+    };
+    let mut size = [0; 2];
+    for size in &mut size {
+        *size = read_token(&mut reader)?
+            .parse()
+            .map_err(|_| PfmError::Header("invalid size"))?;
+    }
+    let [width, height] = size;
+    let scale: f32 = read_token(&mut reader)?
+        .parse()
+        .map_err(|_| PfmError::Header("invalid scale"))?;
+    // The magnitude is a brightness hint that readers ignore, zero has no byte order.
+    let endian = if scale < 0.0 {
+        Endian::Little
+    } else if scale > 0.0 {
+        Endian::Big
+    } else {
+        return Err(PfmError::Header("scale is 0"));
+    };
+
+    let components = match channels {
+        PfmChannels::Color => 3,
+        PfmChannels::Gray => 1,
+    };
+    let row_bytes = (width as usize)
+        .checked_mul(components * 4)
+        .filter(|_| (width as usize).checked_mul(height as usize).is_some())
+        .ok_or(PfmError::Header("image too large"))?;
+    // Read by rows through `take`, so memory only grows with the pixels that are in the file
+    // rather than with the size in the header.
+    let mut bytes = Vec::new();
+    let mut rows = Vec::new();
+    // Images without columns have no pixels, however many rows.
+    for _ in 0..if width == 0 { 0 } else { height } {
+        bytes.clear();
+        (&mut reader)
+            .take(row_bytes as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() != row_bytes {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let values: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|bytes| {
+                let bytes = bytes.try_into().unwrap();
+                match endian {
+                    Endian::Little => f32::from_le_bytes(bytes),
+                    Endian::Big => f32::from_be_bytes(bytes),
+                }
+            })
+            .collect();
+        let row: Vec<[f32; 3]> = match channels {
+            PfmChannels::Color => values
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect(),
+            PfmChannels::Gray => values.iter().map(|&value| [value; 3]).collect(),
+        };
+        rows.push(row);
+    }
+    let pixels = rows.into_iter().rev().flatten().collect();
+
+    Ok(PfmImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// Gray files store the first component of every pixel, which is all of them for images read from
+// gray files.
+pub fn write_pfm(
+    mut writer: impl Write,
+    image: &PfmImage,
+    channels: PfmChannels,
+    endian: Endian,
+) -> io::Result<()> {
+    if Some(image.pixels.len()) != (image.width as usize).checked_mul(image.height as usize) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "pixels don't match the size",
+        ));
+    }
+    let magic = match channels {
+        PfmChannels::Color => "PF",
+        PfmChannels::Gray => "Pf",
+    };
+    let scale = match endian {
+        Endian::Little => "-1.0",
+        Endian::Big => "1.0",
+    };
+    let mut bytes = format!("{magic}\n{} {}\n{scale}\n", image.width, image.height).into_bytes();
+
+    let rows = image.pixels.chunks(image.width.max(1) as usize).rev();
+    for &pixel in rows.flatten() {
+        let values = match channels {
+            PfmChannels::Color => &pixel[..],
+            PfmChannels::Gray => &pixel[..1],
+        };
+        for value in values {
+            bytes.extend(match endian {
+                Endian::Little => value.to_le_bytes(),
+                Endian::Big => value.to_be_bytes(),
+            });
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        // 2x2 color, little endian, with the bottom row first.
+        let mut file = b"PF\n2 2\n-1.000000\n".to_vec();
+        for value in [
+            3.0f32, 3.5, 4.0, 5.0, 6.0, 7.0, 0.0, 0.5, 1.0, 1.5, 2.0, -1.0,
+        ] {
+            file.extend(value.to_le_bytes());
+        }
+        let image = read_pfm(&file[..]).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(
+            image.pixels,
+            [
+                [0.0, 0.5, 1.0],
+                [1.5, 2.0, -1.0],
+                [3.0, 3.5, 4.0],
+                [5.0, 6.0, 7.0]
+            ]
+        );
+
+        // 3x1 gray, big endian, with any whitespace between the fields.
+        let mut file = b"Pf 3\t1\r\n 0.5\n".to_vec();
+        for value in [1.0f32, f32::INFINITY, 1e-3] {
+            file.extend(value.to_be_bytes());
+        }
+        let image = read_pfm(&file[..]).unwrap();
+        assert_eq!(image.pixels, [[1.0; 3], [f32::INFINITY; 3], [1e-3; 3]]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = rand::thread_rng();
+        for (width, height) in [(0, 0), (1, 1), (7, 3), (64, 17)] {
+            let pixels = (0..width * height)
+                .map(|_| [(); 3].map(|_| f32::from_bits(rng.gen())))
+                .collect();
+            let image = PfmImage {
+                width,
+                height,
+                pixels,
+            };
+            for endian in [Endian::Little, Endian::Big] {
+                let mut file = Vec::new();
+                write_pfm(&mut file, &image, PfmChannels::Color, endian).unwrap();
+                let read = read_pfm(&file[..]).unwrap();
+                assert_eq!((read.width, read.height), (width, height));
+                // Compared as bits for NaN.
+                let bits = |image: &PfmImage| -> Vec<[u32; 3]> {
+                    image.pixels.iter().map(|p| p.map(f32::to_bits)).collect()
+                };
+                assert_eq!(bits(&read), bits(&image));
+
+                let mut file = Vec::new();
+                write_pfm(&mut file, &image, PfmChannels::Gray, endian).unwrap();
+                let gray = read_pfm(&file[..]).unwrap();
+                let expected: Vec<[u32; 3]> =
+                    image.pixels.iter().map(|p| [p[0].to_bits(); 3]).collect();
+                assert_eq!(bits(&gray), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let read = |file: &[u8]| read_pfm(file).unwrap_err();
+        assert!(matches!(read(b"P6\n1 1\n255\n"), PfmError::Header(_)));
+        assert!(matches!(read(b"PF\n1 -1\n-1.0\n"), PfmError::Header(_)));
+        assert!(matches!(read(b"PF\n1 1\n0.0\n"), PfmError::Header(_)));
+        // Sizes far larger than the file, which overflow the byte count on 32 bit targets.
+        for file in [
+            &b"PF\n2147483648 2147483648\n-1.0\n"[..],
+            b"PF\n4294967295 1\n-1.0\n",
+            b"Pf\n100000 100000\n-1.0\n\0\0\0\0",
+        ] {
+            match read_pfm(file).unwrap_err() {
+                PfmError::Io(error) => assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof),
+                PfmError::Header(_) => assert_eq!(usize::BITS, 32),
+            }
+        }
+        let empty = read_pfm(&b"PF\n0 4294967295\n-1.0\n"[..]).unwrap();
+        assert!(empty.pixels.is_empty());
+        assert!(matches!(
+            read(b"PF\n1 1\n-1.0\n\0\0\0\0"),
+            PfmError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        let image = PfmImage {
+            width: 2,
+            height: 2,
+            pixels: vec![[0.0; 3]; 3],
+        };
+        let error = write_pfm(Vec::new(), &image, PfmChannels::Color, Endian::Little).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "rgb9e5";
 pub const BYTES: u8 = 4;
@@ -9,14 +11,6 @@ pub const RGB9E5_MANTISSA_BITS: i32 = 9;
 pub const RGB9E5_EXP_BIAS: i32 = 15;
 pub const RGB9E5_MAX_VALID_BIASED_EXP: i32 = 31;
 
-/*
-pub const MAX_RGB9E5_EXP: i32 = 16;
-pub const RGB9E5_MANTISSA_VALUES: i32 = 512;
-pub const MAX_RGB9E5_MANTISSA: i32 = 511;
-pub const MAX_RGB9E5: f32 = 65408.0;
-pub const EPSILON_RGB9E5: f32 = 5.9604645e-8;
- */
-
 pub const MAX_RGB9E5_EXP: i32 = RGB9E5_MAX_VALID_BIASED_EXP - RGB9E5_EXP_BIAS;
 pub const RGB9E5_MANTISSA_VALUES: i32 = 1 << RGB9E5_MANTISSA_BITS;
 pub const MAX_RGB9E5_MANTISSA: i32 = RGB9E5_MANTISSA_VALUES - 1;
@@ -27,23 +21,29 @@ pub const MAX_RGB9E5: f32 =
 pub const EPSILON_RGB9E5: f32 =
     (1.0 / RGB9E5_MANTISSA_VALUES as f32) / (1 << RGB9E5_EXP_BIAS) as f32;
 
+const _: () = assert!(MAX_RGB9E5_EXP == 16);
+const _: () = assert!(RGB9E5_MANTISSA_VALUES == 512);
+const _: () = assert!(MAX_RGB9E5_MANTISSA == 511);
+const _: () = assert!(MAX_RGB9E5 == 65408.0);
+const _: () = assert!(EPSILON_RGB9E5 == 5.9604645e-8);
+
 // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_rgb9e5(rgb: [f32; 3]) -> u32 {
+pub const fn vec3_to_rgb9e5(rgb: [f32; 3]) -> u32 {
     let rc = nan_to_zero(rgb[0]).clamp(0.0, MAX_RGB9E5);
     let gc = nan_to_zero(rgb[1]).clamp(0.0, MAX_RGB9E5);
     let bc = nan_to_zero(rgb[2]).clamp(0.0, MAX_RGB9E5);
 
     let maxrgb = rc.max(gc).max(bc);
-    let mut exp_shared =
-        (-RGB9E5_EXP_BIAS - 1).max(maxrgb.log2().floor() as i32) + 1 + RGB9E5_EXP_BIAS;
+    let mut exp_shared = floor_log2f(maxrgb.max(pow2f(-RGB9E5_EXP_BIAS - 1))) + 1 + RGB9E5_EXP_BIAS;
 
     debug_assert!(exp_shared <= RGB9E5_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS);
 
-    let maxm = (maxrgb / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxrgb / denom + 0.5) as i32;
     if maxm == MAX_RGB9E5_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -52,9 +52,9 @@ pub fn vec3_to_rgb9e5(rgb: [f32; 3]) -> u32 {
         debug_assert!(maxm <= MAX_RGB9E5_MANTISSA);
     }
 
-    let rm = (rc / denom + 0.5).floor() as i32;
-    let gm = (gc / denom + 0.5).floor() as i32;
-    let bm = (bc / denom + 0.5).floor() as i32;
+    let rm = (rc / denom + 0.5) as i32;
+    let gm = (gc / denom + 0.5) as i32;
+    let bm = (bc / denom + 0.5) as i32;
 
     debug_assert!(rm <= MAX_RGB9E5_MANTISSA);
     debug_assert!(gm <= MAX_RGB9E5_MANTISSA);
@@ -75,17 +75,17 @@ pub fn vec3_to_rgb9e5(rgb: [f32; 3]) -> u32 {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn rgb9e5_to_vec3(v: u32) -> [f32; 3] {
+pub const fn rgb9e5_to_vec3(v: u32) -> [f32; 3] {
     let exponent = bitfield_extract(v, 27, RGB9E5_EXPONENT_BITS as u32) as i32
         - RGB9E5_EXP_BIAS
         - RGB9E5_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2();
+    let scale = pow2f(exponent);
 
     [
         bitfield_extract(v, 0, RGB9E5_MANTISSA_BITS as u32) as f32 * scale,
@@ -94,10 +94,84 @@ pub fn rgb9e5_to_vec3(v: u32) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_rgb9e5` gives for the value `v` decodes to. The same value can be stored with
+// a larger exponent and smaller mantissas, these are moved to the smallest exponent.
+#[inline]
+pub const fn canonicalize_rgb9e5(v: u32) -> u32 {
+    let bits = RGB9E5_MANTISSA_BITS as u32;
+    let exp = bitfield_extract(v, 27, RGB9E5_EXPONENT_BITS as u32);
+    let rm = bitfield_extract(v, 0, bits);
+    let gm = bitfield_extract(v, 9, bits);
+    let bm = bitfield_extract(v, 18, bits);
+
+    let (exp, shift) = canonical_exponent(exp, rm | gm | bm, bits);
+
+    #[allow(clippy::identity_op)]
+    let ret = (exp << 27) | (bm << shift << 18) | (gm << shift << 9) | (rm << shift << 0);
+
+    ret
+}
+
+#[inline]
+pub const fn is_canonical_rgb9e5(v: u32) -> bool {
+    canonicalize_rgb9e5(v) == v
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_rgb9e5(v: u32) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v, 27, RGB9E5_EXPONENT_BITS as u32),
+        mantissas: [
+            bitfield_extract(v, 0, RGB9E5_MANTISSA_BITS as u32),
+            bitfield_extract(v, 9, RGB9E5_MANTISSA_BITS as u32),
+            bitfield_extract(v, 18, RGB9E5_MANTISSA_BITS as u32),
+        ],
+        signs: [false; 3],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_rgb9e5(parts: Parts) -> Result<u32, PartsError> {
+    match parts.check(
+        RGB9E5_EXPONENT_BITS as u32,
+        RGB9E5_MANTISSA_BITS as u32,
+        SIGNED,
+    ) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> u32 {
+    let [rm, gm, bm] = parts.mantissas;
+
+    #[allow(clippy::identity_op)]
+    let ret = (parts.exponent << 27) | (bm << 18) | (gm << 9) | (rm << 0);
+
+    ret
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_rgb9e5` on the scaled
+// decoded value. Components past MAX_RGB9E5 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_rgb9e5(v: u32, k: i32) -> u32 {
+    pack_parts(decompose_rgb9e5(v).ldexp(
+        k,
+        RGB9E5_EXPONENT_BITS as u32,
+        RGB9E5_MANTISSA_BITS as u32,
+    ))
+}
+
 #[cfg(test)]
 pub mod tests {
 
+    use std::hint::black_box;
+
     use glam::Vec3;
+    use rand::Rng;
 
     use crate::evaluate::test_util::{Report, DEFUALT_ITERATIONS};
 
@@ -120,6 +194,36 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_packed_layout() {
+        // Red in the lowest bits and the exponent in the highest, like GL_RGB9_E5,
+        // DXGI_FORMAT_R9G9B9E5_SHAREDEXP and MTLPixelFormatRGB9E5Float. 1.0 is 256 * 2^(16 - 15 - 9).
+        assert_eq!(vec3_to_rgb9e5([1.0, 0.0, 0.0]), 16 << 27 | 256);
+        assert_eq!(vec3_to_rgb9e5([0.0, 1.0, 0.0]), 16 << 27 | 256 << 9);
+        assert_eq!(vec3_to_rgb9e5([0.0, 0.0, 1.0]), 16 << 27 | 256 << 18);
+        assert_eq!(vec3_to_rgb9e5([MAX_RGB9E5; 3]), 0xFFFFFFFF);
+        assert_eq!(rgb9e5_to_vec3(16 << 27 | 256 << 9), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_const() {
+        const WHITE: u32 = vec3_to_rgb9e5([1.0; 3]);
+        const COLORS: [u32; 3] = [
+            vec3_to_rgb9e5([0.5, 0.25, 100.0]),
+            vec3_to_rgb9e5([f32::NAN, f32::INFINITY, -1.0]),
+            vec3_to_rgb9e5([1e-9, 3.9999998, 0.0]),
+        ];
+        const DECODED: [f32; 3] = rgb9e5_to_vec3(WHITE);
+        assert_eq!(WHITE, 16 << 27 | 256 << 18 | 256 << 9 | 256);
+        assert_eq!(DECODED, [1.0; 3]);
+        assert_eq!(COLORS[0], vec3_to_rgb9e5(black_box([0.5, 0.25, 100.0])));
+        assert_eq!(
+            COLORS[1],
+            vec3_to_rgb9e5(black_box([f32::NAN, f32::INFINITY, -1.0]))
+        );
+        assert_eq!(COLORS[2], vec3_to_rgb9e5(black_box([1e-9, 3.9999998, 0.0])));
+    }
+
     #[test]
     fn test_edge_cases() {
         debug_assert_eq!(
@@ -151,4 +255,82 @@ pub mod tests {
             rgb9e5_to_vec3(vec3_to_rgb9e5((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            let c = canonicalize_rgb9e5(v);
+            assert!(is_canonical_rgb9e5(c));
+            assert_eq!(is_canonical_rgb9e5(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = rgb9e5_to_vec3(v);
+            assert_eq!(
+                rgb9e5_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_rgb9e5(decoded));
+        }
+
+        // 1.0 as 128 * 2^(17 - 15 - 9) instead of 256 * 2^(16 - 15 - 9).
+        assert_eq!(canonicalize_rgb9e5(17 << 27 | 128), 16 << 27 | 256);
+        assert_eq!(
+            canonicalize_rgb9e5(17 << 27 | 128),
+            vec3_to_rgb9e5([1.0, 0.0, 0.0])
+        );
+        assert_eq!(canonicalize_rgb9e5(31 << 27), 0);
+        assert!(is_canonical_rgb9e5(vec3_to_rgb9e5([MAX_RGB9E5; 3])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            assert_eq!(compose_rgb9e5(decompose_rgb9e5(v)), Ok(v));
+
+            let xyz = rgb9e5_to_vec3(v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_rgb9e5(v, k), vec3_to_rgb9e5(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_rgb9e5(vec3_to_rgb9e5([1.0, 0.0, 0.0]));
+        assert_eq!(one.mantissas, [1 << 8, 0, 0]);
+        assert_eq!(
+            compose_rgb9e5(Parts {
+                exponent: 32,
+                ..one
+            }),
+            Err(PartsError::Exponent { exponent: 32 })
+        );
+        assert_eq!(
+            compose_rgb9e5(Parts {
+                mantissas: [0, 0, 1 << 9],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 9
+            })
+        );
+        let negative = Parts {
+            signs: [false, true, false],
+            ..one
+        };
+        assert_eq!(
+            compose_rgb9e5(negative),
+            Err(PartsError::Sign { component: 1 })
+        );
+        assert_eq!(
+            ldexp_rgb9e5(vec3_to_rgb9e5([MAX_RGB9E5; 3]), 1),
+            vec3_to_rgb9e5([MAX_RGB9E5; 3])
+        );
+        assert_eq!(
+            ldexp_rgb9e5(vec3_to_rgb9e5([1.0; 3]), -1000),
+            vec3_to_rgb9e5([0.0; 3])
+        );
+    }
 }
@@ -0,0 +1,157 @@
+// Radiance RGBE, the pixel format of .hdr files: three 8 bit mantissas and an 8 bit exponent with
+// bias 128, stored as the bytes [r, g, b, e]. XYZE files use the same encoding for CIE XYZ.
+// Unlike the other formats the mantissas are truncated instead of rounded, as Radiance and
+// rgbe.c do, and there is no implicit scale: the largest component is m / 256 * 2^(e - 128) with
+// m >= 128. An exponent byte of 0 is zero.
+//
+// The exponent doesn't fit in `SharedExponentFormat`, which has at most 7 exponent bits.
+
+use crate::math::{floor_log2f, pow2f};
+use crate::nan_to_zero;
+
+pub const NAME: &str = "rgbe8";
+pub const BYTES: u8 = 4;
+pub const SIGNED: bool = false;
+
+pub const RGBE8_EXPONENT_BITS: i32 = 8;
+pub const RGBE8_MANTISSA_BITS: i32 = 8;
+pub const RGBE8_EXP_BIAS: i32 = 128;
+pub const RGBE8_MAX_VALID_BIASED_EXP: i32 = 255;
+
+pub const MAX_RGBE8_EXP: i32 = RGBE8_MAX_VALID_BIASED_EXP - RGBE8_EXP_BIAS;
+pub const RGBE8_MANTISSA_VALUES: i32 = 1 << RGBE8_MANTISSA_BITS;
+pub const MAX_RGBE8_MANTISSA: i32 = RGBE8_MANTISSA_VALUES - 1;
+pub const MAX_RGBE8: f32 = MAX_RGBE8_MANTISSA as f32 * pow2f(MAX_RGBE8_EXP - RGBE8_MANTISSA_BITS);
+// Biased exponent 1 is the smallest, as 0 is zero.
+pub const EPSILON_RGBE8: f32 = pow2f(1 - RGBE8_EXP_BIAS - RGBE8_MANTISSA_BITS);
+
+const _: () = assert!(MAX_RGBE8_EXP == 127);
+const _: () = assert!(MAX_RGBE8 == 1.6947657e38);
+const _: () = assert!(EPSILON_RGBE8 == 2.2959e-41);
+
+// The same as Radiance's setcolr and rgbe.c's float2rgbe, with frexp done on the bits: the
+// exponent e puts the largest component in [0.5, 1) * 2^e, and the mantissas are the components
+// times 2^(8 - e), truncated. Negative components and NaN are 0 and values past MAX_RGBE8 are
+// clamped to it. Like both of them, vectors whose largest component is below 1e-32 are zero,
+// although exponents down to 2^-127 could store them.
+#[inline]
+pub const fn vec3_to_rgbe8(rgb: [f32; 3]) -> [u8; 4] {
+    let rc = nan_to_zero(rgb[0]).clamp(0.0, MAX_RGBE8);
+    let gc = nan_to_zero(rgb[1]).clamp(0.0, MAX_RGBE8);
+    let bc = nan_to_zero(rgb[2]).clamp(0.0, MAX_RGBE8);
+
+    let maxrgb = rc.max(gc).max(bc);
+    if (maxrgb as f64) < 1e-32 {
+        return [0; 4];
+    }
+    let exp = floor_log2f(maxrgb) + 1;
+    debug_assert!(exp + RGBE8_EXP_BIAS <= RGBE8_MAX_VALID_BIASED_EXP);
+
+    // 2^-exp is at most 2^127, the 2^8 is applied after so it can't overflow.
+    let scale = pow2f(-exp);
+
+    [
+        truncate_mantissa(rc, scale),
+        truncate_mantissa(gc, scale),
+        truncate_mantissa(bc, scale),
+        (exp + RGBE8_EXP_BIAS) as u8,
+    ]
+}
+
+// Everything is positive and below 256, so truncating with `as` is the same as floor.
+#[inline]
+const fn truncate_mantissa(x: f32, scale: f32) -> u8 {
+    (x * scale * RGBE8_MANTISSA_VALUES as f32) as u8
+}
+
+// rgbe.c's rgbe2float, which stb_image and most loaders use. Radiance's colr_color adds 0.5 to
+// the mantissas, this doesn't so that zero stays zero.
+#[inline]
+pub const fn rgbe8_to_vec3(v: [u8; 4]) -> [f32; 3] {
+    if v[3] == 0 {
+        return [0.0; 3];
+    }
+    let scale = pow2f(v[3] as i32 - RGBE8_EXP_BIAS - RGBE8_MANTISSA_BITS);
+
+    [
+        v[0] as f32 * scale,
+        v[1] as f32 * scale,
+        v[2] as f32 * scale,
+    ]
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+
+    // rgbe.c's float2rgbe in f64, with frexp from std.
+    fn float2rgbe(rgb: [f32; 3]) -> [u8; 4] {
+        let v = rgb[0].max(rgb[1]).max(rgb[2]) as f64;
+        if v < 1e-32 {
+            return [0; 4];
+        }
+        let e = v.log2().floor() as i32 + 1;
+        let scale = 256.0 / 2.0f64.powi(e);
+        [
+            (rgb[0] as f64 * scale) as u8,
+            (rgb[1] as f64 * scale) as u8,
+            (rgb[2] as f64 * scale) as u8,
+            (e + 128) as u8,
+        ]
+    }
+
+    #[test]
+    fn test_rgbe8() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let exp = rng.gen_range(-130..128);
+            let rgb = [(); 3].map(|_| rng.gen::<f32>() * pow2f(exp));
+            let v = vec3_to_rgbe8(rgb);
+            assert_eq!(v, float2rgbe(rgb), "{rgb:?}");
+
+            // Truncating loses less than one step of the largest component.
+            let decoded = rgbe8_to_vec3(v);
+            let step = if v[3] == 0 {
+                1e-32
+            } else {
+                pow2f(v[3] as i32 - 136)
+            };
+            for i in 0..3 {
+                assert!(
+                    decoded[i] <= rgb[i] && rgb[i] - decoded[i] < step,
+                    "{rgb:?}"
+                );
+            }
+
+            // Decoded values encode to themselves, except for the unused small mantissas and the
+            // values below 1e-32.
+            let v: [u8; 4] = rng.gen();
+            let decoded = rgbe8_to_vec3(v);
+            if v[3] != 0
+                && v[0].max(v[1]).max(v[2]) >= 128
+                && decoded[0].max(decoded[1]).max(decoded[2]) >= 1e-32
+            {
+                assert_eq!(vec3_to_rgbe8(decoded), v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert_eq!(vec3_to_rgbe8([1.0, 0.5, 0.0]), [128, 64, 0, 129]);
+        assert_eq!(rgbe8_to_vec3([128, 64, 0, 129]), [1.0, 0.5, 0.0]);
+        assert_eq!(vec3_to_rgbe8([f32::INFINITY; 3]), [255, 255, 255, 255]);
+        assert_eq!(rgbe8_to_vec3([255, 255, 255, 255]), [MAX_RGBE8; 3]);
+        assert_eq!(vec3_to_rgbe8([f32::MAX; 3]), [255, 255, 255, 255]);
+        assert_eq!(vec3_to_rgbe8([-1.0, f32::NAN, 0.0]), [0; 4]);
+        assert_eq!(vec3_to_rgbe8([0.0; 3]), [0; 4]);
+        assert_eq!(rgbe8_to_vec3([1, 0, 0, 1]), [EPSILON_RGBE8, 0.0, 0.0]);
+        assert_eq!(rgbe8_to_vec3([255, 255, 255, 0]), [0.0; 3]);
+        // 2^-106 is the smallest power of two that isn't zero.
+        assert_eq!(vec3_to_rgbe8([pow2f(-106), 0.0, 0.0]), [128, 0, 0, 23]);
+        assert_eq!(vec3_to_rgbe8([pow2f(-107), 1e-33, 0.0]), [0; 4]);
+    }
+}
@@ -0,0 +1,550 @@
+// Checks the constants and bit offsets written out in the shader sources against the rust
+// modules. The emulation in shader_emulation is written by hand as well, so this reads the
+// sources themselves: constants are compared by name, the offsets of the bitfield extracts in the
+// decoders are compared with the layouts, and the packing statements of the encoders are
+// evaluated with integer field values. The layouts are checked against rust in shader_gen.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::shader_gen::tests::parse_constants;
+use crate::shader_gen::{pack, BitRange, FormatDesc};
+use crate::shaders::tests::LANGUAGES;
+use crate::shaders::{Format, ShaderLanguage, GLSL_RGB9E5_UNORM8};
+use crate::{rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+// The name and value of each rust constant of a format.
+macro_rules! constants {
+    ($module:ident: $($name:ident),* $(,)?) => {
+        vec![$((stringify!($name), $module::$name as f64)),*]
+    };
+}
+
+// The rust constants duplicated by the shaders. The shaders add a trailing underscore to MAX_ and
+// EPSILON_, and rgb9e5 only has the unsigned copies like MANTISSA_BITSU in the shaders.
+fn rust_constants(format: Format) -> Vec<(&'static str, f64)> {
+    match format {
+        Format::Rgb9e5 => {
+            let mut constants = constants!(rgb9e5:
+                RGB9E5_EXPONENT_BITS, RGB9E5_MANTISSA_BITS, RGB9E5_EXP_BIAS,
+                RGB9E5_MAX_VALID_BIASED_EXP, MAX_RGB9E5_EXP, RGB9E5_MANTISSA_VALUES,
+                MAX_RGB9E5_MANTISSA, MAX_RGB9E5, EPSILON_RGB9E5,
+            );
+            constants.push(("RGB9E5_MANTISSA_BITSU", rgb9e5::RGB9E5_MANTISSA_BITS as f64));
+            constants.push(("MAX_RGB9E5_MANTISSAU", rgb9e5::MAX_RGB9E5_MANTISSA as f64));
+            constants
+        }
+        Format::Xyz8e5 => constants!(xyz8e5:
+            XYZ8E5_EXPONENT_BITS, XYZ8E5_MANTISSA_BITS, XYZ8E5_MANTISSA_BITSU, XYZ8E5_EXP_BIAS,
+            XYZ8E5_MAX_VALID_BIASED_EXP, MAX_XYZ8E5_EXP, XYZ8E5_MANTISSA_VALUES,
+            MAX_XYZ8E5_MANTISSA, MAX_XYZ8E5_MANTISSAU, MAX_XYZ8E5, EPSILON_XYZ8E5,
+        ),
+        Format::Xyz9e2 => {
+            let mut constants = constants!(xyz9e2:
+                XYZ9E2_EXPONENT_BITS, XYZ9E2_MANTISSA_BITS, XYZ9E2_MANTISSA_BITSU, XYZ9E2_EXP_BIAS,
+                XYZ9E2_MAX_VALID_BIASED_EXP, MAX_XYZ9E2_EXP, XYZ9E2_MANTISSA_VALUES,
+                MAX_XYZ9E2_MANTISSA, MAX_XYZ9E2_MANTISSAU, MAX_XYZ9E2, EPSILON_XYZ9E2,
+            );
+            constants.push(("XYZ9E2_NORM_MULT", xyz9e2::NORM_MULT as f64));
+            constants
+        }
+        Format::Xyz13e6 => constants!(xyz13e6:
+            XYZ13E6_EXPONENT_BITS, XYZ13E6_MANTISSA_BITS, XYZ13E6_MANTISSA_BITSU, XYZ13E6_EXP_BIAS,
+            XYZ13E6_MAX_VALID_BIASED_EXP, MAX_XYZ13E6_EXP, XYZ13E6_MANTISSA_VALUES,
+            MAX_XYZ13E6_MANTISSA, MAX_XYZ13E6_MANTISSAU, MAX_XYZ13E6, EPSILON_XYZ13E6,
+        ),
+        Format::Xyz14e3 => {
+            let mut constants = constants!(xyz14e3:
+                XYZ14E3_EXPONENT_BITS, XYZ14E3_MANTISSA_BITS, XYZ14E3_MANTISSA_BITSU,
+                XYZ14E3_EXP_BIAS, XYZ14E3_MAX_VALID_BIASED_EXP, MAX_XYZ14E3_EXP,
+                XYZ14E3_MANTISSA_VALUES, MAX_XYZ14E3_MANTISSA, MAX_XYZ14E3_MANTISSAU, MAX_XYZ14E3,
+                EPSILON_XYZ14E3,
+            );
+            constants.push(("XYZ14E3_NORM_MULT", xyz14e3::NORM_MULT as f64));
+            constants
+        }
+        Format::Xyz18e7 => constants!(xyz18e7:
+            XYZ18E7_EXPONENT_BITS, XYZ18E7_MANTISSA_BITS, XYZ18E7_MANTISSA_BITSU, XYZ18E7_EXP_BIAS,
+            XYZ18E7_MAX_VALID_BIASED_EXP, MAX_XYZ18E7_EXP, XYZ18E7_MANTISSA_VALUES,
+            MAX_XYZ18E7_MANTISSA, MAX_XYZ18E7_MANTISSAU, MAX_XYZ18E7, EPSILON_XYZ18E7,
+        ),
+    }
+}
+
+fn rust_value(rust: &[(&str, f64)], shader_name: &str) -> Option<f64> {
+    let name = shader_name.strip_suffix('_').unwrap_or(shader_name);
+    rust.iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| *value)
+}
+
+// Every shipped source of a format, with a label for failures.
+fn sources(format: Format) -> Vec<(String, ShaderLanguage, &'static str)> {
+    let mut sources: Vec<_> = LANGUAGES
+        .into_iter()
+        .map(|language| {
+            let label = format!("{language:?} {}", format.name());
+            (label, language, format.source(language))
+        })
+        .collect();
+    sources.push((
+        format!("wgsl module {}", format.name()),
+        ShaderLanguage::Wgsl,
+        format.wgsl_module(),
+    ));
+    sources.push((
+        format!("guarded glsl {}", format.name()),
+        ShaderLanguage::Glsl,
+        format.glsl_guarded(),
+    ));
+    sources
+}
+
+fn function_name(language: ShaderLanguage, name: &str) -> String {
+    match language {
+        ShaderLanguage::Wgsl => format!("{name}_"),
+        _ => name.to_string(),
+    }
+}
+
+// The lines between the signature of a top level function and its closing brace.
+fn function_body<'a>(source: &'a str, name: &str) -> Vec<&'a str> {
+    let signature = format!(" {name}(");
+    let mut lines = source
+        .lines()
+        .skip_while(|line| line.starts_with(' ') || !line.contains(&signature));
+    assert!(lines.next().is_some(), "missing function {name}");
+    lines.take_while(|line| *line != "}").collect()
+}
+
+// The arguments of a call, split at top level commas, and the rest of the text after it.
+fn call_arguments(text: &str) -> (Vec<&str>, &str) {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut arguments = Vec::new();
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(text[start..i].trim());
+                start = i + 1;
+            }
+            ')' => {
+                arguments.push(text[start..i].trim());
+                return (arguments, &text[i + 1..]);
+            }
+            _ => {}
+        }
+    }
+    panic!("unclosed call in {text}");
+}
+
+fn parse_uint(text: &str) -> Option<u32> {
+    let text = text.trim().trim_end_matches('u');
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// A sum of constants and literals, like `XYZ8E5_MANTISSA_BITSU + 1u`.
+fn constant_sum(text: &str, constants: &[(String, f64)]) -> u32 {
+    text.split('+')
+        .map(|term| {
+            let term = term.trim();
+            parse_uint(term).unwrap_or_else(|| {
+                let (_, value) = constants
+                    .iter()
+                    .find(|(name, _)| name == term)
+                    .unwrap_or_else(|| panic!("unknown constant {term}"));
+                *value as u32
+            })
+        })
+        .sum()
+}
+
+fn word_index(text: &str) -> u32 {
+    match text {
+        "v" | "v.x" | "v[0]" => 0,
+        "v.y" | "v[1]" => 1,
+        _ => panic!("unknown word {text}"),
+    }
+}
+
+// The bit ranges read by a decoder, clipped to the word. Bitfields are read with
+// extractBits/bitfield_extract/extract_bits, or in rgb9e5 with `(v >> 9u) & ((1u << B) - 1u)`.
+fn decoder_extracts(body: &[&str], constants: &[(String, f64)]) -> Vec<BitRange> {
+    let mut extracts = Vec::new();
+    for line in body {
+        for function in ["extractBits(", "bitfield_extract(", "extract_bits("] {
+            for (i, _) in line.match_indices(function) {
+                let (arguments, _) = call_arguments(&line[i + function.len()..]);
+                let [word, offset, bits] = arguments[..] else {
+                    panic!("bitfield extract with {} arguments", arguments.len());
+                };
+                extracts.push((word, offset, constant_sum(bits, constants)));
+            }
+        }
+        for (i, _) in line.match_indices("(v >> ") {
+            let (offset, rest) = line[i + 6..].split_once(')').unwrap();
+            let rest = rest.strip_prefix(" & ((1u << ").unwrap();
+            let (bits, _) = rest.split_once(')').unwrap();
+            extracts.push(("v", offset, constant_sum(bits, constants)));
+        }
+    }
+    extracts
+        .into_iter()
+        .map(|(word, offset, bits)| {
+            let offset = parse_uint(offset).unwrap();
+            BitRange::new(word_index(word), offset, bits.min(32 - offset))
+        })
+        .collect()
+}
+
+// The masks a function applies to each word with `op`, like `v.y ^ 0x01C00000u`.
+fn word_masks(body: &[&str], op: &str, words: u32) -> Vec<u32> {
+    let mut masks = vec![0; words as usize];
+    for line in body {
+        for (i, _) in line.match_indices(op) {
+            let word = line[..i].rsplit(['(', ' ']).next().unwrap();
+            let mask = line[i + op.len()..].split([',', ')', ';']).next().unwrap();
+            masks[word_index(word) as usize] |= parse_uint(mask).unwrap();
+        }
+    }
+    masks
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Number(u32),
+    Ident(String),
+    Open,
+    Close,
+    Comma,
+    Op(&'static str),
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        i += 1;
+        match c {
+            ' ' => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '|' => tokens.push(Token::Op("|")),
+            '&' => tokens.push(Token::Op("&")),
+            '<' | '>' if chars.get(i) == Some(&c) => {
+                i += 1;
+                tokens.push(Token::Op(if c == '<' { "<<" } else { ">>" }));
+            }
+            _ if c.is_ascii_alphanumeric() || c == '_' => {
+                // Member accesses like `s.x` and `v[1]` are part of the name.
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || "_.[]".contains(chars[i]))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if c.is_ascii_digit() {
+                    tokens.push(Token::Number(parse_uint(&word)?));
+                } else {
+                    tokens.push(Token::Ident(word));
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+// Evaluates the integer expressions that pack the fields, with | & << >> and parentheses, casts,
+// is_sign_negative and the vector constructor of the result. Anything else, like float math,
+// gives None.
+struct Evaluator<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    variables: &'a HashMap<String, u32>,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, token: Token) -> Option<()> {
+        (self.peek() == Some(&token)).then(|| self.position += 1)
+    }
+
+    fn binary(&mut self, level: usize) -> Option<u32> {
+        const LEVELS: [&[&str]; 3] = [&["|"], &["&"], &["<<", ">>"]];
+        if level == LEVELS.len() {
+            return self.primary();
+        }
+        let mut value = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            if !LEVELS[level].contains(&op) {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.binary(level + 1)?;
+            value = match op {
+                "|" => value | rhs,
+                "&" => value & rhs,
+                "<<" => value.checked_shl(rhs)?,
+                _ => value.checked_shr(rhs)?,
+            };
+        }
+        Some(value)
+    }
+
+    fn primary(&mut self) -> Option<u32> {
+        match self.tokens.get(self.position)? {
+            Token::Number(n) => {
+                self.position += 1;
+                Some(*n)
+            }
+            Token::Open => {
+                self.position += 1;
+                let value = self.binary(0)?;
+                self.expect(Token::Close)?;
+                Some(value)
+            }
+            Token::Ident(name) if self.tokens.get(self.position + 1) == Some(&Token::Open) => {
+                if !["uint", "u32", "is_sign_negative"].contains(&name.as_str()) {
+                    return None;
+                }
+                self.position += 2;
+                let value = self.binary(0)?;
+                self.expect(Token::Close)?;
+                Some(value)
+            }
+            Token::Ident(name) => {
+                self.position += 1;
+                self.variables.get(name).copied()
+            }
+            _ => None,
+        }
+    }
+
+    // A single value, or the words of a vector constructor.
+    fn words(&mut self) -> Option<Vec<u32>> {
+        let constructor = match self.peek()? {
+            Token::Ident(name) => ["vec2", "uvec2", "uint2"].contains(&name.as_str()),
+            _ => false,
+        };
+        let words = if constructor {
+            self.position += 1;
+            self.expect(Token::Open)?;
+            let mut words = vec![self.binary(0)?];
+            while self.expect(Token::Comma).is_some() {
+                words.push(self.binary(0)?);
+            }
+            self.expect(Token::Close)?;
+            words
+        } else {
+            vec![self.binary(0)?]
+        };
+        (self.position == self.tokens.len()).then_some(words)
+    }
+}
+
+fn evaluate(text: &str, variables: &HashMap<String, u32>) -> Option<Vec<u32>> {
+    let tokens = tokenize(text)?;
+    Evaluator {
+        tokens: &tokens,
+        position: 0,
+        variables,
+    }
+    .words()
+}
+
+// Runs the integer statements of an encoder with the fields already computed, and returns the
+// packed words. Statements that can't be evaluated, like the float math computing the fields,
+// are skipped, so the field variables keep the given values.
+fn run_encoder(body: &[&str], exponent: u32, mantissas: [u32; 3], signs: [u32; 3]) -> Vec<u32> {
+    let mut variables = HashMap::new();
+    variables.insert("exp_shared".to_string(), exponent);
+    for i in 0..3 {
+        for name in ["s", "n"] {
+            let member = ["xyz", "rgb"][(name == "n") as usize].as_bytes()[i] as char;
+            variables.insert(format!("{name}.{member}"), mantissas[i]);
+        }
+        for input in ["xyz", "xyz_in"] {
+            let member = ["x", "y", "z"][i];
+            variables.insert(format!("{input}.{member}"), signs[i]);
+        }
+    }
+
+    for line in body {
+        let line = line.trim();
+        if let Some(expression) = line.strip_prefix("return ") {
+            let expression = expression.trim_end_matches(';');
+            return evaluate(expression, &variables)
+                .unwrap_or_else(|| panic!("can't evaluate {line}"));
+        }
+        let Some((declaration, expression)) = line.split_once(" = ") else {
+            continue;
+        };
+        let Some(name) = declaration
+            .strip_prefix("let ")
+            .or_else(|| declaration.strip_prefix("uint "))
+        else {
+            continue;
+        };
+        if let Some([value]) = evaluate(expression.trim_end_matches(';'), &variables).as_deref() {
+            variables.insert(name.trim().to_string(), *value);
+        }
+    }
+    panic!("encoder without return");
+}
+
+#[test]
+fn test_constants_match_rust() {
+    for format in Format::ALL {
+        let rust = rust_constants(format);
+        for (label, _, source) in sources(format) {
+            let shader = parse_constants(source);
+            assert_eq!(shader.len(), rust.len(), "{label}");
+            for (name, value) in shader {
+                let Some(expected) = rust_value(&rust, &name) else {
+                    panic!("{label}: {name} is not a rust constant");
+                };
+                // The shaders have f32 constants.
+                assert_eq!(value as f32, expected as f32, "{label}: {name}");
+            }
+        }
+    }
+
+    let rust = rust_constants(Format::Rgb9e5);
+    for (name, value) in parse_constants(GLSL_RGB9E5_UNORM8) {
+        let Some(expected) = rust_value(&rust, &name) else {
+            panic!("rgb9e5_unorm8: {name} is not a rust constant");
+        };
+        assert_eq!(value as f32, expected as f32, "rgb9e5_unorm8: {name}");
+    }
+}
+
+#[test]
+fn test_decoder_offsets_match_layout() {
+    for format in Format::ALL {
+        let layout = FormatDesc::from_format(format).layout;
+        let mut fields: Vec<BitRange> = layout.exponent.clone();
+        fields.extend(layout.mantissas.iter().flatten());
+        fields.extend(layout.signs.iter().flatten());
+
+        // decompose_* reads the same bitfields as the decoder.
+        let decoders = [
+            format!("{}_to_vec3", format.name()),
+            format!("decompose_{}", format.name()),
+        ];
+        for (label, language, source) in sources(format) {
+            for decoder in &decoders {
+                let label = format!("{label} {decoder}");
+                let name = function_name(language, decoder);
+                let extracts =
+                    decoder_extracts(&function_body(source, &name), &parse_constants(source));
+
+                for e in &extracts {
+                    // xyz8e5 and xyz9e2 read each mantissa together with the sign above it.
+                    let with_sign = (0..3).any(|i| {
+                        let sign = layout.signs.map(|s| s[i]);
+                        layout.mantissas[i][..] == [BitRange::new(e.word, e.offset, e.bits - 1)]
+                            && sign == Some(BitRange::new(e.word, e.offset + e.bits - 1, 1))
+                    });
+                    assert!(fields.contains(e) || with_sign, "{label}: extract {e:?}");
+                }
+                for f in &fields {
+                    let covered = extracts.iter().any(|e| {
+                        e.word == f.word
+                            && e.offset <= f.offset
+                            && f.offset + f.bits <= e.offset + e.bits
+                    });
+                    assert!(covered, "{label}: {f:?} is not read");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_encoder_offsets_match_layout() {
+    let mut rng = rand::thread_rng();
+    for format in Format::ALL {
+        let desc = FormatDesc::from_format(format);
+        let layout = &desc.layout;
+        for (label, language, source) in sources(format) {
+            let name = function_name(language, &format!("vec3_to_{}", format.name()));
+            let body = function_body(source, &name);
+            for _ in 0..100 {
+                let exponent = rng.gen_range(0..1 << desc.exponent_bits);
+                let mantissas: [u32; 3] =
+                    std::array::from_fn(|_| rng.gen_range(0..1 << desc.mantissa_bits));
+                let signs: [u32; 3] = match layout.signs {
+                    Some(_) => std::array::from_fn(|_| rng.gen_range(0..2)),
+                    None => [0; 3],
+                };
+
+                let mut expected = vec![0; layout.words as usize];
+                pack(&mut expected, &layout.exponent, exponent);
+                for i in 0..3 {
+                    pack(&mut expected, &layout.mantissas[i], mantissas[i]);
+                    if let Some(s) = layout.signs {
+                        pack(&mut expected, &[s[i]], signs[i]);
+                    }
+                }
+
+                let packed = run_encoder(&body, exponent, mantissas, signs);
+                assert_eq!(
+                    packed, expected,
+                    "{label}: exponent {exponent} mantissas {mantissas:?} signs {signs:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parts_functions_match_layout() {
+    for format in Format::ALL {
+        let layout = FormatDesc::from_format(format).layout;
+        let mut sign_masks = vec![0; layout.words as usize];
+        if let Some(signs) = layout.signs {
+            for sign in signs {
+                pack(&mut sign_masks, &[sign], 1);
+            }
+        }
+
+        for (label, language, source) in sources(format) {
+            for function in ["compose", "ldexp"] {
+                let name = function_name(language, &format!("{function}_{}", format.name()));
+                function_body(source, &name);
+            }
+            // Shared by every format, so even wgsl leaves ldexp_parts unsuffixed. The wgsl
+            // modules import it from the common module.
+            if !label.starts_with("wgsl module") {
+                function_body(source, "ldexp_parts");
+            }
+
+            let negate = function_name(language, &format!("negate_{}", format.name()));
+            let abs = function_name(language, &format!("abs_{}", format.name()));
+            if layout.signs.is_none() {
+                assert!(!source.contains(&format!(" {negate}(")), "{label}");
+                assert!(!source.contains(&format!(" {abs}(")), "{label}");
+                continue;
+            }
+            let negate = word_masks(&function_body(source, &negate), " ^ ", layout.words);
+            assert_eq!(negate, sign_masks, "{label}: negate");
+            let abs = word_masks(&function_body(source, &abs), " & ~", layout.words);
+            assert_eq!(abs, sign_masks, "{label}: abs");
+        }
+    }
+}
@@ -0,0 +1,1458 @@
+// Rust mirrors of the functions in `glsl/*.glsl` and `wgsl/*.wgsl`, used to check that data
+// encoded on the cpu decodes identically to data written by shaders, and the other way around.
+// The GLSL and WGSL versions of each format are the same algorithm, so one function covers both.
+//
+// The shader code differs from the native rust functions in a few ways that are mirrored here:
+// - NaN is replaced with 0.0 by `nan_to_zero`/`abs_nan_to_zero`, which work on the bits so that
+//   fast-math compilers can't remove them. `min`, `max` and `clamp` are undefined for NaN, so
+//   these debug assert that they never see one.
+// - The exponent is found with the `floor_log2` bit trick, which unlike `math::floor_log2f`
+//   doesn't handle subnormals. The encoders never pass it one.
+// - `exp2` is f32 and is only ever called with integer arguments, where it is exact.
+// - rgb9e5 clamps with `clamp`, the xyz formats with `min(abs(xyz), MAX)`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::math::floorf;
+use crate::shader_gen::{pack, unpack, FormatDesc};
+
+pub const RGB9E5_EXPONENT_BITS: u32 = 5;
+pub const RGB9E5_MANTISSA_BITS: i32 = 9;
+pub const RGB9E5_MANTISSA_BITSU: u32 = 9;
+pub const RGB9E5_EXP_BIAS: i32 = 15;
+pub const RGB9E5_MAX_VALID_BIASED_EXP: u32 = 31;
+
+pub const MAX_RGB9E5_EXP: u32 = 16;
+pub const RGB9E5_MANTISSA_VALUES: i32 = 512;
+pub const MAX_RGB9E5_MANTISSA: i32 = 511;
+pub const MAX_RGB9E5_MANTISSAU: u32 = 511;
+pub const MAX_RGB9E5_: f32 = 65408.0;
+pub const EPSILON_RGB9E5_: f32 = 0.000000059604645;
+
+pub const XYZ8E5_EXPONENT_BITS: u32 = 5;
+pub const XYZ8E5_MANTISSA_BITS: i32 = 8;
+pub const XYZ8E5_MANTISSA_BITSU: u32 = 8;
+pub const XYZ8E5_EXP_BIAS: i32 = 15;
+pub const XYZ8E5_MAX_VALID_BIASED_EXP: u32 = 31;
+
+pub const MAX_XYZ8E5_EXP: u32 = 16;
+pub const XYZ8E5_MANTISSA_VALUES: i32 = 256;
+pub const MAX_XYZ8E5_MANTISSA: i32 = 255;
+pub const MAX_XYZ8E5_MANTISSAU: u32 = 255;
+pub const MAX_XYZ8E5_: f32 = 65280.0;
+pub const EPSILON_XYZ8E5_: f32 = 0.00000011920929;
+
+pub const XYZ9E2_EXPONENT_BITS: u32 = 2;
+pub const XYZ9E2_MANTISSA_BITS: i32 = 9;
+pub const XYZ9E2_MANTISSA_BITSU: u32 = 9;
+pub const XYZ9E2_EXP_BIAS: i32 = 3;
+pub const XYZ9E2_MAX_VALID_BIASED_EXP: u32 = 3;
+
+pub const MAX_XYZ9E2_EXP: u32 = 0;
+pub const XYZ9E2_MANTISSA_VALUES: i32 = 512;
+pub const MAX_XYZ9E2_MANTISSA: i32 = 511;
+pub const MAX_XYZ9E2_MANTISSAU: u32 = 511;
+pub const MAX_XYZ9E2_: f32 = 1.0;
+pub const EPSILON_XYZ9E2_: f32 = 0.00024414063;
+pub const XYZ9E2_NORM_MULT: f32 = 1.0019569;
+
+pub const XYZ13E6_EXPONENT_BITS: u32 = 6;
+pub const XYZ13E6_MANTISSA_BITS: i32 = 13;
+pub const XYZ13E6_MANTISSA_BITSU: u32 = 13;
+pub const XYZ13E6_EXP_BIAS: i32 = 31;
+pub const XYZ13E6_MAX_VALID_BIASED_EXP: u32 = 63;
+
+pub const MAX_XYZ13E6_EXP: u32 = 32;
+pub const XYZ13E6_MANTISSA_VALUES: i32 = 8192;
+pub const MAX_XYZ13E6_MANTISSA: i32 = 8191;
+pub const MAX_XYZ13E6_MANTISSAU: u32 = 8191;
+pub const MAX_XYZ13E6_: f32 = 4294443000.0;
+pub const EPSILON_XYZ13E6_: f32 = 0.00000000000005684342;
+
+pub const XYZ14E3_EXPONENT_BITS: u32 = 3;
+pub const XYZ14E3_MANTISSA_BITS: i32 = 14;
+pub const XYZ14E3_MANTISSA_BITSU: u32 = 14;
+pub const XYZ14E3_EXP_BIAS: i32 = 3;
+pub const XYZ14E3_MAX_VALID_BIASED_EXP: u32 = 7;
+
+pub const MAX_XYZ14E3_EXP: u32 = 4;
+pub const XYZ14E3_MANTISSA_VALUES: i32 = 16384;
+pub const MAX_XYZ14E3_MANTISSA: i32 = 16383;
+pub const MAX_XYZ14E3_MANTISSAU: u32 = 16383;
+pub const MAX_XYZ14E3_: f32 = 16.0;
+pub const EPSILON_XYZ14E3_: f32 = 0.0000076293945;
+pub const XYZ14E3_NORM_MULT: f32 = 1.000061;
+
+pub const XYZ18E7_EXPONENT_BITS: u32 = 7;
+pub const XYZ18E7_MANTISSA_BITS: i32 = 18;
+pub const XYZ18E7_MANTISSA_BITSU: u32 = 18;
+pub const XYZ18E7_EXP_BIAS: i32 = 63;
+pub const XYZ18E7_MAX_VALID_BIASED_EXP: u32 = 127;
+
+pub const MAX_XYZ18E7_EXP: u32 = 64;
+pub const XYZ18E7_MANTISSA_VALUES: i32 = 262144;
+pub const MAX_XYZ18E7_MANTISSA: i32 = 262143;
+pub const MAX_XYZ18E7_MANTISSAU: u32 = 262143;
+pub const MAX_XYZ18E7_: f32 = 1.8446674e+19;
+pub const EPSILON_XYZ18E7_: f32 = 4.135903e-25;
+
+#[inline]
+pub fn floor_log2(x: f32) -> i32 {
+    let f = x.to_bits();
+    let biasedexponent = (f & 0x7F800000u32) >> 23u32;
+    biasedexponent as i32 - 127
+}
+
+#[inline]
+pub fn is_sign_negative(v: f32) -> u32 {
+    (v.to_bits() >> 31u32) & 1u32
+}
+
+// Same as both the GLSL compatibility function and WGSL's extractBits for bits < 32.
+#[inline]
+pub fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+    let mask = (1u32 << bits) - 1u32;
+    (value >> offset) & mask
+}
+
+// The shaders only call exp2 with integer arguments in the normal f32 range.
+#[inline]
+pub fn exp2(x: f32) -> f32 {
+    debug_assert_eq!(x, floorf(x));
+    debug_assert!((-126.0..=127.0).contains(&x));
+    f32::from_bits(((x as i32 + 127) as u32) << 23u32)
+}
+
+#[inline]
+pub fn min(a: f32, b: f32) -> f32 {
+    debug_assert!(!a.is_nan() && !b.is_nan());
+    a.min(b)
+}
+
+#[inline]
+pub fn max(a: f32, b: f32) -> f32 {
+    debug_assert!(!a.is_nan() && !b.is_nan());
+    a.max(b)
+}
+
+#[inline]
+pub fn clamp(x: f32, lo: f32, hi: f32) -> f32 {
+    min(max(x, lo), hi)
+}
+
+#[inline]
+pub fn nan_to_zero(v: f32) -> f32 {
+    let b = v.to_bits();
+    f32::from_bits(b * ((b & 0x7FFFFFFFu32) <= 0x7F800000u32) as u32)
+}
+
+#[inline]
+pub fn abs_nan_to_zero(v: f32) -> f32 {
+    let b = v.to_bits() & 0x7FFFFFFFu32;
+    f32::from_bits(b * (b <= 0x7F800000u32) as u32)
+}
+
+#[inline]
+pub fn vec3_to_rgb9e5(rgb_in: [f32; 3]) -> u32 {
+    let rgb = rgb_in.map(|c| clamp(nan_to_zero(c), 0.0, MAX_RGB9E5_));
+
+    let maxrgb = max(rgb[0], max(rgb[1], rgb[2]));
+    let mut exp_shared = (-RGB9E5_EXP_BIAS - 1).max(floor_log2(maxrgb)) + 1 + RGB9E5_EXP_BIAS;
+    let mut denom = exp2((exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxrgb / denom + 0.5) as i32;
+    if maxm == RGB9E5_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let n = rgb.map(|c| floorf(c / denom + 0.5) as u32);
+
+    #[allow(clippy::identity_op)]
+    let ret = ((exp_shared as u32) << 27u32) | (n[2] << 18u32) | (n[1] << 9u32) | (n[0] << 0u32);
+
+    ret
+}
+
+#[inline]
+#[allow(clippy::identity_op)]
+pub fn rgb9e5_to_vec3(v: u32) -> [f32; 3] {
+    let exponent = bitfield_extract(v, 27, RGB9E5_EXPONENT_BITS) as i32
+        - RGB9E5_EXP_BIAS
+        - RGB9E5_MANTISSA_BITS;
+    let scale = exp2(exponent as f32);
+
+    [
+        ((v >> 0u32) & ((1u32 << RGB9E5_MANTISSA_BITSU) - 1u32)) as f32 * scale,
+        ((v >> 9u32) & ((1u32 << RGB9E5_MANTISSA_BITSU) - 1u32)) as f32 * scale,
+        ((v >> 18u32) & ((1u32 << RGB9E5_MANTISSA_BITSU) - 1u32)) as f32 * scale,
+    ]
+}
+
+// GLSL mod for floats.
+#[inline]
+pub fn glsl_mod(x: f32, y: f32) -> f32 {
+    x - y * floorf(x / y)
+}
+
+#[inline]
+pub fn unorm8_to_bytes(c: [f32; 4]) -> [f32; 4] {
+    c.map(|c| floorf(c * 255.0 + 0.5))
+}
+
+// Mirrors `glsl/rgb9e5_unorm8.glsl`, which only uses float math.
+#[inline]
+pub fn pow2_unorm8(e: f32) -> f32 {
+    let mut p = 1.0;
+    for i in 0..32 {
+        if (i as f32) < e {
+            p *= 2.0;
+        } else if (i as f32) < -e {
+            p *= 0.5;
+        }
+    }
+    p
+}
+
+#[inline]
+pub fn rgb9e5_unorm8_to_vec3(c: [f32; 4]) -> [f32; 3] {
+    let b = unorm8_to_bytes(c);
+
+    let m = [
+        b[0] + glsl_mod(b[1], 2.0) * 256.0,
+        floorf(b[1] / 2.0) + glsl_mod(b[2], 4.0) * 128.0,
+        floorf(b[2] / 4.0) + glsl_mod(b[3], 8.0) * 64.0,
+    ];
+    let exponent = floorf(b[3] / 8.0) - RGB9E5_EXP_BIAS as f32 - RGB9E5_MANTISSA_BITS as f32;
+
+    m.map(|m| m * pow2_unorm8(exponent))
+}
+
+#[inline]
+pub fn floor_log2_unorm8(x: f32) -> f32 {
+    let mut e = 0.0;
+    let mut p = 1.0;
+    for _ in 0..16 {
+        if p > x {
+            p *= 0.5;
+            e -= 1.0;
+        } else if p * 2.0 <= x {
+            p *= 2.0;
+            e += 1.0;
+        }
+    }
+    e
+}
+
+#[inline]
+pub fn vec3_to_rgb9e5_unorm8(rgb_in: [f32; 3]) -> [f32; 4] {
+    let rgb = rgb_in.map(|c| clamp(c, 0.0, MAX_RGB9E5_));
+    let bias = RGB9E5_EXP_BIAS as f32;
+
+    let maxrgb = max(rgb[0], max(rgb[1], rgb[2]));
+    let mut exp_shared = max(-bias - 1.0, floor_log2_unorm8(maxrgb)) + 1.0 + bias;
+    let mut denom = pow2_unorm8(exp_shared - bias - RGB9E5_MANTISSA_BITS as f32);
+
+    let maxm = floorf(maxrgb / denom + 0.5);
+    if maxm == RGB9E5_MANTISSA_VALUES as f32 {
+        denom *= 2.0;
+        exp_shared += 1.0;
+    }
+
+    let n = rgb.map(|c| floorf(c / denom + 0.5));
+
+    let b = [
+        glsl_mod(n[0], 256.0),
+        floorf(n[0] / 256.0) + glsl_mod(n[1], 128.0) * 2.0,
+        floorf(n[1] / 128.0) + glsl_mod(n[2], 64.0) * 4.0,
+        floorf(n[2] / 64.0) + exp_shared * 8.0,
+    ];
+
+    b.map(|b| b / 255.0)
+}
+
+#[inline]
+pub fn vec3_to_xyz8e5(xyz_in: [f32; 3]) -> u32 {
+    let xsign = is_sign_negative(xyz_in[0]) << 8u32;
+    let ysign = is_sign_negative(xyz_in[1]) << 8u32;
+    let zsign = is_sign_negative(xyz_in[2]) << 8u32;
+
+    let xyz = xyz_in.map(|c| min(abs_nan_to_zero(c), MAX_XYZ8E5_));
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-XYZ8E5_EXP_BIAS - 1).max(floor_log2(maxxyz)) + 1 + XYZ8E5_EXP_BIAS;
+    let mut denom = exp2((exp_shared - XYZ8E5_EXP_BIAS - XYZ8E5_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == XYZ8E5_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+
+    #[allow(clippy::identity_op)]
+    let ret = ((exp_shared as u32) << 27u32)
+        | ((s[2] | zsign) << 18u32)
+        | ((s[1] | ysign) << 9u32)
+        | ((s[0] | xsign) << 0u32);
+
+    ret
+}
+
+#[inline]
+pub fn xyz8e5_to_vec3(v: u32) -> [f32; 3] {
+    let exponent = bitfield_extract(v, 27, XYZ8E5_EXPONENT_BITS) as i32
+        - XYZ8E5_EXP_BIAS
+        - XYZ8E5_MANTISSA_BITS;
+    let scale = exp2(exponent as f32);
+
+    let xb = bitfield_extract(v, 0, XYZ8E5_MANTISSA_BITSU + 1);
+    let yb = bitfield_extract(v, 9, XYZ8E5_MANTISSA_BITSU + 1);
+    let zb = bitfield_extract(v, 18, XYZ8E5_MANTISSA_BITSU + 1);
+
+    [
+        f32::from_bits(((xb & 0xFFu32) as f32).to_bits() | (xb & 0x100u32) << 23u32) * scale,
+        f32::from_bits(((yb & 0xFFu32) as f32).to_bits() | (yb & 0x100u32) << 23u32) * scale,
+        f32::from_bits(((zb & 0xFFu32) as f32).to_bits() | (zb & 0x100u32) << 23u32) * scale,
+    ]
+}
+
+#[inline]
+pub fn vec3_to_xyz9e2(xyz_in: [f32; 3]) -> u32 {
+    let xsign = is_sign_negative(xyz_in[0]) << 9u32;
+    let ysign = is_sign_negative(xyz_in[1]) << 9u32;
+    let zsign = is_sign_negative(xyz_in[2]) << 9u32;
+
+    let xyz = xyz_in.map(|c| min(abs_nan_to_zero(c), MAX_XYZ9E2_) / XYZ9E2_NORM_MULT);
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-XYZ9E2_EXP_BIAS - 1).max(floor_log2(maxxyz)) + 1 + XYZ9E2_EXP_BIAS;
+    let mut denom = exp2((exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == XYZ9E2_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+
+    #[allow(clippy::identity_op)]
+    let ret = ((exp_shared as u32) << 30u32)
+        | ((s[2] | zsign) << 20u32)
+        | ((s[1] | ysign) << 10u32)
+        | ((s[0] | xsign) << 0u32);
+
+    ret
+}
+
+#[inline]
+pub fn xyz9e2_to_vec3(v: u32) -> [f32; 3] {
+    let exponent = bitfield_extract(v, 30, XYZ9E2_EXPONENT_BITS) as i32
+        - XYZ9E2_EXP_BIAS
+        - XYZ9E2_MANTISSA_BITS;
+    let scale = exp2(exponent as f32) * XYZ9E2_NORM_MULT;
+
+    let xb = bitfield_extract(v, 0, XYZ9E2_MANTISSA_BITSU + 1);
+    let yb = bitfield_extract(v, 10, XYZ9E2_MANTISSA_BITSU + 1);
+    let zb = bitfield_extract(v, 20, XYZ9E2_MANTISSA_BITSU + 1);
+
+    [
+        f32::from_bits(((xb & 0x1FFu32) as f32).to_bits() | (xb & 0x200u32) << 22u32) * scale,
+        f32::from_bits(((yb & 0x1FFu32) as f32).to_bits() | (yb & 0x200u32) << 22u32) * scale,
+        f32::from_bits(((zb & 0x1FFu32) as f32).to_bits() | (zb & 0x200u32) << 22u32) * scale,
+    ]
+}
+
+#[inline]
+pub fn vec3_to_xyz13e6(xyz_in: [f32; 3]) -> [u32; 2] {
+    let xsign = is_sign_negative(xyz_in[0]);
+    let ysign = is_sign_negative(xyz_in[1]);
+    let zsign = is_sign_negative(xyz_in[2]);
+
+    let xyz = xyz_in.map(|c| min(abs_nan_to_zero(c), MAX_XYZ13E6_));
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-XYZ13E6_EXP_BIAS - 1).max(floor_log2(maxxyz)) + 1 + XYZ13E6_EXP_BIAS;
+    let mut denom = exp2((exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == XYZ13E6_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+
+    #[allow(clippy::identity_op)]
+    let out_a = ((exp_shared as u32) << 26u32) | (s[1] << 13u32) | (s[0] << 0u32);
+    #[allow(clippy::identity_op)]
+    let out_b = (zsign << 15u32) | (ysign << 14u32) | (xsign << 13u32) | (s[2] << 0u32);
+
+    [out_a, out_b]
+}
+
+#[inline]
+pub fn xyz13e6_to_vec3(v: [u32; 2]) -> [f32; 3] {
+    let exponent = bitfield_extract(v[0], 26, XYZ13E6_EXPONENT_BITS) as i32
+        - XYZ13E6_EXP_BIAS
+        - XYZ13E6_MANTISSA_BITS;
+    let scale = exp2(exponent as f32);
+
+    let xb = bitfield_extract(v[0], 0, XYZ13E6_MANTISSA_BITSU);
+    let yb = bitfield_extract(v[0], 13, XYZ13E6_MANTISSA_BITSU);
+    let zb = bitfield_extract(v[1], 0, XYZ13E6_MANTISSA_BITSU);
+
+    [
+        f32::from_bits((xb as f32).to_bits() | bitfield_extract(v[1], 13, 1) << 31u32) * scale,
+        f32::from_bits((yb as f32).to_bits() | bitfield_extract(v[1], 14, 1) << 31u32) * scale,
+        f32::from_bits((zb as f32).to_bits() | bitfield_extract(v[1], 15, 1) << 31u32) * scale,
+    ]
+}
+
+#[inline]
+pub fn vec3_to_xyz14e3(xyz_in: [f32; 3]) -> [u32; 2] {
+    let xsign = is_sign_negative(xyz_in[0]);
+    let ysign = is_sign_negative(xyz_in[1]);
+    let zsign = is_sign_negative(xyz_in[2]);
+
+    let xyz = xyz_in.map(|c| min(abs_nan_to_zero(c), MAX_XYZ14E3_) / XYZ14E3_NORM_MULT);
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-XYZ14E3_EXP_BIAS - 1).max(floor_log2(maxxyz)) + 1 + XYZ14E3_EXP_BIAS;
+    let mut denom = exp2((exp_shared - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == XYZ14E3_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+    let e = exp_shared as u32;
+
+    #[allow(clippy::identity_op)]
+    let out_a = ((e & 1u32) << 31u32)
+        | (zsign << 30u32)
+        | (ysign << 29u32)
+        | (xsign << 28u32)
+        | (s[1] << 14u32)
+        | (s[0] << 0u32);
+    #[allow(clippy::identity_op)]
+    let out_b = ((e & 6u32) << 13u32) | (s[2] << 0u32);
+
+    [out_a, out_b]
+}
+
+#[inline]
+pub fn xyz14e3_to_vec3(v: [u32; 2]) -> [f32; 3] {
+    let biased_exp = bitfield_extract(v[0], 31, 1) | bitfield_extract(v[1], 14, 2) << 1u32;
+    let exponent = biased_exp as i32 - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS;
+    let scale = exp2(exponent as f32) * XYZ14E3_NORM_MULT;
+
+    let xb = bitfield_extract(v[0], 0, XYZ14E3_MANTISSA_BITSU);
+    let yb = bitfield_extract(v[0], 14, XYZ14E3_MANTISSA_BITSU);
+    let zb = bitfield_extract(v[1], 0, XYZ14E3_MANTISSA_BITSU);
+
+    [
+        f32::from_bits((xb as f32).to_bits() | bitfield_extract(v[0], 28, 1) << 31u32) * scale,
+        f32::from_bits((yb as f32).to_bits() | bitfield_extract(v[0], 29, 1) << 31u32) * scale,
+        f32::from_bits((zb as f32).to_bits() | bitfield_extract(v[0], 30, 1) << 31u32) * scale,
+    ]
+}
+
+#[inline]
+pub fn packed48_word(index: u32) -> u32 {
+    (index * 3) >> 1
+}
+
+#[inline]
+pub fn packed48_load(lo: u32, hi: u32, index: u32) -> [u32; 2] {
+    if index & 1 == 0 {
+        return [lo, hi & 0xFFFF];
+    }
+    [(lo >> 16) | (hi << 16), hi >> 16]
+}
+
+#[inline]
+pub fn packed48_store(lo: u32, hi: u32, index: u32, v: [u32; 2]) -> [u32; 2] {
+    if index & 1 == 0 {
+        return [v[0], (hi & 0xFFFF0000) | (v[1] & 0xFFFF)];
+    }
+    [(lo & 0xFFFF) | (v[0] << 16), (v[0] >> 16) | (v[1] << 16)]
+}
+
+#[inline]
+pub fn load_xyz13e6(lo: u32, hi: u32, index: u32) -> [f32; 3] {
+    xyz13e6_to_vec3(packed48_load(lo, hi, index))
+}
+
+#[inline]
+pub fn store_xyz13e6(lo: u32, hi: u32, index: u32, xyz: [f32; 3]) -> [u32; 2] {
+    packed48_store(lo, hi, index, vec3_to_xyz13e6(xyz))
+}
+
+#[inline]
+pub fn load_xyz14e3(lo: u32, hi: u32, index: u32) -> [f32; 3] {
+    xyz14e3_to_vec3(packed48_load(lo, hi, index))
+}
+
+#[inline]
+pub fn store_xyz14e3(lo: u32, hi: u32, index: u32, xyz: [f32; 3]) -> [u32; 2] {
+    packed48_store(lo, hi, index, vec3_to_xyz14e3(xyz))
+}
+
+#[inline]
+pub fn vec3_to_xyz18e7(xyz_in: [f32; 3]) -> [u32; 2] {
+    let xsign = is_sign_negative(xyz_in[0]);
+    let ysign = is_sign_negative(xyz_in[1]);
+    let zsign = is_sign_negative(xyz_in[2]);
+
+    let xyz = xyz_in.map(|c| min(abs_nan_to_zero(c), MAX_XYZ18E7_));
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-XYZ18E7_EXP_BIAS - 1).max(floor_log2(maxxyz)) + 1 + XYZ18E7_EXP_BIAS;
+    let mut denom = exp2((exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == XYZ18E7_MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+
+    #[allow(clippy::identity_op)]
+    let out_a = (s[1] << 18u32) | (s[0] << 0u32);
+    let out_b = ((exp_shared as u32) << 25u32)
+        | (zsign << 24u32)
+        | (ysign << 23u32)
+        | (xsign << 22u32)
+        | (s[2] << 4u32)
+        | (s[1] >> 14u32);
+
+    [out_a, out_b]
+}
+
+#[inline]
+pub fn xyz18e7_to_vec3(v: [u32; 2]) -> [f32; 3] {
+    let exponent = bitfield_extract(v[1], 25, XYZ18E7_EXPONENT_BITS) as i32
+        - XYZ18E7_EXP_BIAS
+        - XYZ18E7_MANTISSA_BITS;
+    let scale = exp2(exponent as f32);
+
+    let xb = bitfield_extract(v[0], 0, XYZ18E7_MANTISSA_BITSU);
+    let yb =
+        bitfield_extract(v[0], 18, XYZ18E7_MANTISSA_BITSU) | bitfield_extract(v[1], 0, 4) << 14u32;
+    let zb = bitfield_extract(v[1], 4, XYZ18E7_MANTISSA_BITSU);
+
+    [
+        f32::from_bits((xb as f32).to_bits() | bitfield_extract(v[1], 22, 1) << 31u32) * scale,
+        f32::from_bits((yb as f32).to_bits() | bitfield_extract(v[1], 23, 1) << 31u32) * scale,
+        f32::from_bits((zb as f32).to_bits() | bitfield_extract(v[1], 24, 1) << 31u32) * scale,
+    ]
+}
+
+// The shader struct, with 0 or 1 for each sign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedExponentParts {
+    pub exponent: u32,
+    pub mantissas: [u32; 3],
+    pub signs: [u32; 3],
+}
+
+#[inline]
+pub fn ldexp_parts(
+    parts: SharedExponentParts,
+    k: i32,
+    exponent_bits: u32,
+    mantissa_bits: u32,
+) -> SharedExponentParts {
+    let max_exponent = (1u32 << exponent_bits) - 1;
+    let max_mantissa = (1u32 << mantissa_bits) - 1;
+    let mut m = parts.mantissas;
+    let mut e = parts.exponent as i32 + k.clamp(-256, 256);
+    if e > max_exponent as i32 {
+        let d = (e as u32 - max_exponent).min(mantissa_bits);
+        let over = m.map(|m| (m > max_mantissa >> d) as u32);
+        m = [0, 1, 2].map(|i| (m[i] << d) * (1 - over[i]) + max_mantissa * over[i]);
+        e = max_exponent as i32;
+    } else if e < 0 {
+        let s = (-e).min(31) as u32;
+        m = m.map(|m| (m + ((1u32 << s) >> 1)) >> s);
+        e = 0;
+    }
+
+    let any_mantissa = m[0] | m[1] | m[2];
+    let mut shift = 0;
+    if any_mantissa == 0 {
+        e = 0;
+    } else {
+        shift = (e as u32).min(mantissa_bits - 1 - floor_log2(any_mantissa as f32) as u32);
+    }
+    SharedExponentParts {
+        exponent: e as u32 - shift,
+        mantissas: m.map(|m| m << shift),
+        signs: parts.signs,
+    }
+}
+
+#[inline]
+pub fn decompose_rgb9e5(v: u32) -> SharedExponentParts {
+    SharedExponentParts {
+        exponent: bitfield_extract(v, 27, RGB9E5_EXPONENT_BITS),
+        mantissas: [0, 9, 18].map(|o| bitfield_extract(v, o, RGB9E5_MANTISSA_BITSU)),
+        signs: [0; 3],
+    }
+}
+
+#[inline]
+pub fn compose_rgb9e5(parts: SharedExponentParts) -> u32 {
+    let m = parts.mantissas.map(|m| m & MAX_RGB9E5_MANTISSAU);
+    #[allow(clippy::identity_op)]
+    let ret = ((parts.exponent & RGB9E5_MAX_VALID_BIASED_EXP) << 27u32)
+        | (m[2] << 18u32)
+        | (m[1] << 9u32)
+        | (m[0] << 0u32);
+    ret
+}
+
+#[inline]
+pub fn ldexp_rgb9e5(v: u32, k: i32) -> u32 {
+    compose_rgb9e5(ldexp_parts(
+        decompose_rgb9e5(v),
+        k,
+        RGB9E5_EXPONENT_BITS,
+        RGB9E5_MANTISSA_BITSU,
+    ))
+}
+
+#[inline]
+pub fn decompose_xyz8e5(v: u32) -> SharedExponentParts {
+    let b = [0, 9, 18].map(|o| bitfield_extract(v, o, XYZ8E5_MANTISSA_BITSU + 1));
+    SharedExponentParts {
+        exponent: bitfield_extract(v, 27, XYZ8E5_EXPONENT_BITS),
+        mantissas: b.map(|b| b & MAX_XYZ8E5_MANTISSAU),
+        signs: b.map(|b| b >> XYZ8E5_MANTISSA_BITSU),
+    }
+}
+
+#[inline]
+pub fn compose_xyz8e5(parts: SharedExponentParts) -> u32 {
+    let b = [0, 1, 2].map(|i| {
+        (parts.mantissas[i] & MAX_XYZ8E5_MANTISSAU)
+            | ((parts.signs[i] & 1) << XYZ8E5_MANTISSA_BITSU)
+    });
+    #[allow(clippy::identity_op)]
+    let ret = ((parts.exponent & XYZ8E5_MAX_VALID_BIASED_EXP) << 27u32)
+        | (b[2] << 18u32)
+        | (b[1] << 9u32)
+        | (b[0] << 0u32);
+    ret
+}
+
+#[inline]
+pub fn negate_xyz8e5(v: u32) -> u32 {
+    v ^ 0x04020100
+}
+
+#[inline]
+pub fn abs_xyz8e5(v: u32) -> u32 {
+    v & !0x04020100
+}
+
+#[inline]
+pub fn ldexp_xyz8e5(v: u32, k: i32) -> u32 {
+    compose_xyz8e5(ldexp_parts(
+        decompose_xyz8e5(v),
+        k,
+        XYZ8E5_EXPONENT_BITS,
+        XYZ8E5_MANTISSA_BITSU,
+    ))
+}
+
+#[inline]
+pub fn decompose_xyz9e2(v: u32) -> SharedExponentParts {
+    let b = [0, 10, 20].map(|o| bitfield_extract(v, o, XYZ9E2_MANTISSA_BITSU + 1));
+    SharedExponentParts {
+        exponent: bitfield_extract(v, 30, XYZ9E2_EXPONENT_BITS),
+        mantissas: b.map(|b| b & MAX_XYZ9E2_MANTISSAU),
+        signs: b.map(|b| b >> XYZ9E2_MANTISSA_BITSU),
+    }
+}
+
+#[inline]
+pub fn compose_xyz9e2(parts: SharedExponentParts) -> u32 {
+    let b = [0, 1, 2].map(|i| {
+        (parts.mantissas[i] & MAX_XYZ9E2_MANTISSAU)
+            | ((parts.signs[i] & 1) << XYZ9E2_MANTISSA_BITSU)
+    });
+    #[allow(clippy::identity_op)]
+    let ret = ((parts.exponent & XYZ9E2_MAX_VALID_BIASED_EXP) << 30u32)
+        | (b[2] << 20u32)
+        | (b[1] << 10u32)
+        | (b[0] << 0u32);
+    ret
+}
+
+#[inline]
+pub fn negate_xyz9e2(v: u32) -> u32 {
+    v ^ 0x20080200
+}
+
+#[inline]
+pub fn abs_xyz9e2(v: u32) -> u32 {
+    v & !0x20080200
+}
+
+#[inline]
+pub fn ldexp_xyz9e2(v: u32, k: i32) -> u32 {
+    compose_xyz9e2(ldexp_parts(
+        decompose_xyz9e2(v),
+        k,
+        XYZ9E2_EXPONENT_BITS,
+        XYZ9E2_MANTISSA_BITSU,
+    ))
+}
+
+#[inline]
+pub fn decompose_xyz13e6(v: [u32; 2]) -> SharedExponentParts {
+    SharedExponentParts {
+        exponent: bitfield_extract(v[0], 26, XYZ13E6_EXPONENT_BITS),
+        mantissas: [
+            bitfield_extract(v[0], 0, XYZ13E6_MANTISSA_BITSU),
+            bitfield_extract(v[0], 13, XYZ13E6_MANTISSA_BITSU),
+            bitfield_extract(v[1], 0, XYZ13E6_MANTISSA_BITSU),
+        ],
+        signs: [13, 14, 15].map(|o| bitfield_extract(v[1], o, 1)),
+    }
+}
+
+#[inline]
+pub fn compose_xyz13e6(parts: SharedExponentParts) -> [u32; 2] {
+    let e = parts.exponent & XYZ13E6_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas.map(|m| m & MAX_XYZ13E6_MANTISSAU);
+    let s = parts.signs.map(|s| s & 1);
+    #[allow(clippy::identity_op)]
+    [
+        (e << 26u32) | (m[1] << 13u32) | (m[0] << 0u32),
+        (s[2] << 15u32) | (s[1] << 14u32) | (s[0] << 13u32) | (m[2] << 0u32),
+    ]
+}
+
+#[inline]
+pub fn negate_xyz13e6(v: [u32; 2]) -> [u32; 2] {
+    [v[0], v[1] ^ 0x0000E000]
+}
+
+#[inline]
+pub fn abs_xyz13e6(v: [u32; 2]) -> [u32; 2] {
+    [v[0], v[1] & !0x0000E000]
+}
+
+#[inline]
+pub fn ldexp_xyz13e6(v: [u32; 2], k: i32) -> [u32; 2] {
+    compose_xyz13e6(ldexp_parts(
+        decompose_xyz13e6(v),
+        k,
+        XYZ13E6_EXPONENT_BITS,
+        XYZ13E6_MANTISSA_BITSU,
+    ))
+}
+
+#[inline]
+pub fn decompose_xyz14e3(v: [u32; 2]) -> SharedExponentParts {
+    SharedExponentParts {
+        exponent: bitfield_extract(v[0], 31, 1) | (bitfield_extract(v[1], 14, 2) << 1u32),
+        mantissas: [
+            bitfield_extract(v[0], 0, XYZ14E3_MANTISSA_BITSU),
+            bitfield_extract(v[0], 14, XYZ14E3_MANTISSA_BITSU),
+            bitfield_extract(v[1], 0, XYZ14E3_MANTISSA_BITSU),
+        ],
+        signs: [28, 29, 30].map(|o| bitfield_extract(v[0], o, 1)),
+    }
+}
+
+#[inline]
+pub fn compose_xyz14e3(parts: SharedExponentParts) -> [u32; 2] {
+    let e = parts.exponent & XYZ14E3_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas.map(|m| m & MAX_XYZ14E3_MANTISSAU);
+    let s = parts.signs.map(|s| s & 1);
+    #[allow(clippy::identity_op)]
+    [
+        ((e & 1) << 31u32)
+            | (s[2] << 30u32)
+            | (s[1] << 29u32)
+            | (s[0] << 28u32)
+            | (m[1] << 14u32)
+            | (m[0] << 0u32),
+        ((e & 6) << 13u32) | (m[2] << 0u32),
+    ]
+}
+
+#[inline]
+pub fn negate_xyz14e3(v: [u32; 2]) -> [u32; 2] {
+    [v[0] ^ 0x70000000, v[1]]
+}
+
+#[inline]
+pub fn abs_xyz14e3(v: [u32; 2]) -> [u32; 2] {
+    [v[0] & !0x70000000, v[1]]
+}
+
+#[inline]
+pub fn ldexp_xyz14e3(v: [u32; 2], k: i32) -> [u32; 2] {
+    compose_xyz14e3(ldexp_parts(
+        decompose_xyz14e3(v),
+        k,
+        XYZ14E3_EXPONENT_BITS,
+        XYZ14E3_MANTISSA_BITSU,
+    ))
+}
+
+#[inline]
+pub fn decompose_xyz18e7(v: [u32; 2]) -> SharedExponentParts {
+    SharedExponentParts {
+        exponent: bitfield_extract(v[1], 25, XYZ18E7_EXPONENT_BITS),
+        mantissas: [
+            bitfield_extract(v[0], 0, XYZ18E7_MANTISSA_BITSU),
+            bitfield_extract(v[0], 18, 14) | bitfield_extract(v[1], 0, 4) << 14u32,
+            bitfield_extract(v[1], 4, XYZ18E7_MANTISSA_BITSU),
+        ],
+        signs: [22, 23, 24].map(|o| bitfield_extract(v[1], o, 1)),
+    }
+}
+
+#[inline]
+pub fn compose_xyz18e7(parts: SharedExponentParts) -> [u32; 2] {
+    let e = parts.exponent & XYZ18E7_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas.map(|m| m & MAX_XYZ18E7_MANTISSAU);
+    let s = parts.signs.map(|s| s & 1);
+    #[allow(clippy::identity_op)]
+    [
+        (m[1] << 18u32) | (m[0] << 0u32),
+        (e << 25u32)
+            | (s[2] << 24u32)
+            | (s[1] << 23u32)
+            | (s[0] << 22u32)
+            | (m[2] << 4u32)
+            | (m[1] >> 14u32),
+    ]
+}
+
+#[inline]
+pub fn negate_xyz18e7(v: [u32; 2]) -> [u32; 2] {
+    [v[0], v[1] ^ 0x01C00000]
+}
+
+#[inline]
+pub fn abs_xyz18e7(v: [u32; 2]) -> [u32; 2] {
+    [v[0], v[1] & !0x01C00000]
+}
+
+#[inline]
+pub fn ldexp_xyz18e7(v: [u32; 2], k: i32) -> [u32; 2] {
+    compose_xyz18e7(ldexp_parts(
+        decompose_xyz18e7(v),
+        k,
+        XYZ18E7_EXPONENT_BITS,
+        XYZ18E7_MANTISSA_BITSU,
+    ))
+}
+
+// Mirrors the code emitted by `shader_gen::generate` for the format.
+pub fn vec3_to_desc(desc: &FormatDesc, xyz_in: [f32; 3]) -> Vec<u32> {
+    let signs = xyz_in.map(is_sign_negative);
+    let max_ = desc.max_value();
+    let mut xyz = if desc.signed {
+        xyz_in.map(|c| min(abs_nan_to_zero(c), max_))
+    } else {
+        xyz_in.map(|c| clamp(nan_to_zero(c), 0.0, max_))
+    };
+    if desc.scale != 1.0 {
+        xyz = xyz.map(|c| c / desc.scale);
+    }
+    let exp_bias = desc.exp_bias;
+    let mantissa_bits = desc.mantissa_bits as i32;
+
+    let maxxyz = max(xyz[0], max(xyz[1], xyz[2]));
+    let mut exp_shared = (-exp_bias - 1).max(floor_log2(maxxyz)) + 1 + exp_bias;
+    let mut denom = exp2((exp_shared - exp_bias - mantissa_bits) as f32);
+
+    let maxm = floorf(maxxyz / denom + 0.5) as i32;
+    if maxm == desc.mantissa_values() {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = xyz.map(|c| floorf(c / denom + 0.5) as u32);
+    let e = exp_shared as u32;
+
+    let mut words = vec![0u32; desc.layout.words as usize];
+    pack(&mut words, &desc.layout.exponent, e);
+    for i in 0..3 {
+        pack(&mut words, &desc.layout.mantissas[i], s[i]);
+        if let Some(sign_ranges) = &desc.layout.signs {
+            pack(&mut words, &[sign_ranges[i]], signs[i]);
+        }
+    }
+    words
+}
+
+// Mirrors the code emitted by `shader_gen::generate` for the format.
+pub fn desc_to_vec3(desc: &FormatDesc, v: &[u32]) -> [f32; 3] {
+    let exponent =
+        unpack(v, &desc.layout.exponent) as i32 - desc.exp_bias - desc.mantissa_bits as i32;
+    let mut scale = exp2(exponent as f32);
+    if desc.scale != 1.0 {
+        scale *= desc.scale;
+    }
+
+    let b = [0, 1, 2].map(|i| unpack(v, &desc.layout.mantissas[i]));
+
+    match &desc.layout.signs {
+        Some(signs) => [0, 1, 2].map(|i| {
+            f32::from_bits((b[i] as f32).to_bits() | unpack(v, &[signs[i]]) << 31u32) * scale
+        }),
+        None => b.map(|b| b as f32 * scale),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use std::fmt::Debug;
+
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+
+    use crate::parts::Parts;
+    use crate::{packed48, rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+    use super::*;
+
+    const RANDOM_ITERATIONS: usize = 1000000;
+
+    pub fn edge_values() -> Vec<f32> {
+        let mut values = vec![
+            0.0,
+            1.0,
+            0.5,
+            2.0,
+            f32::MAX,
+            f32::INFINITY,
+            f32::NAN,
+            f32::from_bits(0x7F800001),
+            f32::from_bits(0x7FFFFFFF),
+            f32::MIN_POSITIVE,
+            f32::from_bits(1),
+            f32::EPSILON,
+            rgb9e5::MAX_RGB9E5,
+            rgb9e5::EPSILON_RGB9E5,
+            xyz8e5::MAX_XYZ8E5,
+            xyz8e5::EPSILON_XYZ8E5,
+            xyz9e2::MAX_XYZ9E2,
+            xyz9e2::EPSILON_XYZ9E2,
+            xyz13e6::MAX_XYZ13E6,
+            xyz13e6::EPSILON_XYZ13E6,
+            xyz14e3::MAX_XYZ14E3,
+            xyz14e3::EPSILON_XYZ14E3,
+            xyz18e7::MAX_XYZ18E7,
+            xyz18e7::EPSILON_XYZ18E7,
+        ];
+        // Values just around each power of two, where the exponent and mantissa rounding meet.
+        for e in -100..100 {
+            let p = 2.0f32.powi(e);
+            values.extend([p, f32::from_bits(p.to_bits() - 1), p * 1.5]);
+        }
+        let mut values: Vec<f32> = values.iter().flat_map(|&v| [v, -v]).collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        values.dedup_by(|a, b| a.to_bits() == b.to_bits());
+        values
+    }
+
+    // Edge values combined over the three axes, followed by random bit patterns and uniform values.
+    pub fn test_inputs() -> Vec<[f32; 3]> {
+        let edges = edge_values();
+        let mut rng = rand::thread_rng();
+        let mut inputs = Vec::new();
+        for &e in &edges {
+            inputs.push([e; 3]);
+            for _ in 0..16 {
+                let a = edges[rng.gen_range(0..edges.len())];
+                let b = edges[rng.gen_range(0..edges.len())];
+                inputs.extend([[e, a, b], [a, e, b], [a, b, e]]);
+            }
+        }
+        while inputs.len() < RANDOM_ITERATIONS {
+            let v = [rng.gen::<u32>(), rng.gen::<u32>(), rng.gen::<u32>()].map(f32::from_bits);
+            inputs.push(v);
+            let d = 10.0f32.powi(rng.gen_range(-3..5));
+            inputs.push([
+                rng.gen_range(-d..d),
+                rng.gen_range(-d..d),
+                rng.gen_range(-d..d),
+            ]);
+        }
+        inputs
+    }
+
+    // Runs both functions on every input and reports each input where the results differ.
+    pub fn divergences<I, O, R, S>(name: &str, inputs: &[I], rust: R, shader: S) -> Vec<String>
+    where
+        I: Copy + Debug,
+        O: PartialEq + Debug,
+        R: Fn(I) -> O,
+        S: Fn(I) -> O,
+    {
+        let mut out = Vec::new();
+        for &input in inputs {
+            let r = rust(input);
+            let s = shader(input);
+            if r != s {
+                out.push(format!("{name}: {input:?} rust {r:?} shader {s:?}"));
+            }
+        }
+        out
+    }
+
+    pub fn bits3(v: [f32; 3]) -> [u32; 3] {
+        v.map(f32::to_bits)
+    }
+
+    pub fn assert_no_divergences(divergences: Vec<String>) {
+        for d in divergences.iter().take(32) {
+            println!("{d}");
+        }
+        assert!(divergences.is_empty(), "{} divergences", divergences.len());
+    }
+
+    pub fn random_codes(n: usize) -> Vec<u32> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_special_values() {
+        let nan = [f32::NAN, -f32::NAN, f32::from_bits(0x7F800001)];
+        assert_eq!(rgb9e5_to_vec3(vec3_to_rgb9e5(nan)), [0.0; 3]);
+        assert_eq!(xyz8e5_to_vec3(vec3_to_xyz8e5(nan)), [0.0; 3]);
+        assert_eq!(xyz9e2_to_vec3(vec3_to_xyz9e2(nan)), [0.0; 3]);
+        assert_eq!(xyz13e6_to_vec3(vec3_to_xyz13e6(nan)), [0.0; 3]);
+        assert_eq!(xyz14e3_to_vec3(vec3_to_xyz14e3(nan)), [0.0; 3]);
+        assert_eq!(xyz18e7_to_vec3(vec3_to_xyz18e7(nan)), [0.0; 3]);
+
+        let inf = [f32::INFINITY, -f32::INFINITY, f32::INFINITY];
+        assert_eq!(
+            rgb9e5_to_vec3(vec3_to_rgb9e5(inf)),
+            [MAX_RGB9E5_, 0.0, MAX_RGB9E5_]
+        );
+        assert_eq!(
+            xyz8e5_to_vec3(vec3_to_xyz8e5(inf)),
+            [MAX_XYZ8E5_, -MAX_XYZ8E5_, MAX_XYZ8E5_]
+        );
+        assert_eq!(
+            xyz9e2_to_vec3(vec3_to_xyz9e2(inf)),
+            [MAX_XYZ9E2_, -MAX_XYZ9E2_, MAX_XYZ9E2_]
+        );
+        assert_eq!(
+            xyz13e6_to_vec3(vec3_to_xyz13e6(inf)),
+            [MAX_XYZ13E6_, -MAX_XYZ13E6_, MAX_XYZ13E6_]
+        );
+        assert_eq!(
+            xyz14e3_to_vec3(vec3_to_xyz14e3(inf)),
+            [MAX_XYZ14E3_, -MAX_XYZ14E3_, MAX_XYZ14E3_]
+        );
+        assert_eq!(
+            xyz18e7_to_vec3(vec3_to_xyz18e7(inf)),
+            [MAX_XYZ18E7_, -MAX_XYZ18E7_, MAX_XYZ18E7_]
+        );
+
+        // -0.0 keeps its sign in the xyz formats, and becomes 0.0 in rgb9e5.
+        let neg_zero = [-0.0, 0.0, -0.0];
+        assert_eq!(
+            bits3(rgb9e5_to_vec3(vec3_to_rgb9e5(neg_zero))),
+            bits3([0.0; 3])
+        );
+        assert_eq!(
+            bits3(xyz8e5_to_vec3(vec3_to_xyz8e5(neg_zero))),
+            bits3(neg_zero)
+        );
+        assert_eq!(
+            bits3(xyz9e2_to_vec3(vec3_to_xyz9e2(neg_zero))),
+            bits3(neg_zero)
+        );
+        assert_eq!(
+            bits3(xyz13e6_to_vec3(vec3_to_xyz13e6(neg_zero))),
+            bits3(neg_zero)
+        );
+        assert_eq!(
+            bits3(xyz14e3_to_vec3(vec3_to_xyz14e3(neg_zero))),
+            bits3(neg_zero)
+        );
+        assert_eq!(
+            bits3(xyz18e7_to_vec3(vec3_to_xyz18e7(neg_zero))),
+            bits3(neg_zero)
+        );
+    }
+
+    #[test]
+    fn test_rgb9e5() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_rgb9e5",
+            &inputs,
+            rgb9e5::vec3_to_rgb9e5,
+            vec3_to_rgb9e5,
+        ));
+        assert_no_divergences(divergences(
+            "rgb9e5_to_vec3",
+            &random_codes(RANDOM_ITERATIONS),
+            |v| bits3(rgb9e5::rgb9e5_to_vec3(v)),
+            |v| bits3(rgb9e5_to_vec3(v)),
+        ));
+    }
+
+    #[test]
+    fn test_rgb9e5_unorm8() {
+        // The bytes are recovered from what the texture returns, even if it is a little off.
+        for byte in 0..=255u8 {
+            for offset in [0.0, -0.25 / 255.0, 0.25 / 255.0] {
+                let c = byte as f32 / 255.0 + offset;
+                assert_eq!(unorm8_to_bytes([c; 4]), [byte as f32; 4]);
+            }
+        }
+
+        // The powers of two are built without exp2 and log2, so they don't depend on their
+        // precision.
+        for e in -32..=32 {
+            assert_eq!(pow2_unorm8(e as f32), 2.0f32.powi(e));
+        }
+        for e in -16..16 {
+            let p = 2.0f32.powi(e);
+            assert_eq!(floor_log2_unorm8(p), e as f32);
+            let below = floor_log2_unorm8(p * (1.0 - f32::EPSILON / 2.0));
+            assert_eq!(below, (e - 1).max(-16) as f32);
+            assert_eq!(floor_log2_unorm8(p * (2.0 - f32::EPSILON)), e as f32);
+        }
+        assert_eq!(floor_log2_unorm8(0.0), -16.0);
+
+        let mut codes = random_codes(RANDOM_ITERATIONS);
+        codes.extend([0, u32::MAX, 0x07FFFFFF, 0xF8000000]);
+        codes.extend((0..32).map(|e| e << 27 | 0x1FF << 18 | 0x100 << 9 | 1));
+        assert_no_divergences(divergences(
+            "rgb9e5_unorm8_to_vec3",
+            &codes,
+            |v| bits3(rgb9e5::rgb9e5_to_vec3(v)),
+            |v| {
+                bits3(rgb9e5_unorm8_to_vec3(
+                    v.to_le_bytes().map(|b| b as f32 / 255.0),
+                ))
+            },
+        ));
+
+        // NaN is undefined in the float only encoder.
+        let inputs: Vec<[f32; 3]> = test_inputs()
+            .into_iter()
+            .filter(|v| !v.iter().any(|c| c.is_nan()))
+            .collect();
+        assert_no_divergences(divergences(
+            "vec3_to_rgb9e5_unorm8",
+            &inputs,
+            rgb9e5::vec3_to_rgb9e5,
+            |v| u32::from_le_bytes(vec3_to_rgb9e5_unorm8(v).map(|c| (c * 255.0).round() as u8)),
+        ));
+    }
+
+    #[test]
+    fn test_xyz8e5() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_xyz8e5",
+            &inputs,
+            xyz8e5::vec3_to_xyz8e5,
+            vec3_to_xyz8e5,
+        ));
+        assert_no_divergences(divergences(
+            "xyz8e5_to_vec3",
+            &random_codes(RANDOM_ITERATIONS),
+            |v| bits3(xyz8e5::xyz8e5_to_vec3(v)),
+            |v| bits3(xyz8e5_to_vec3(v)),
+        ));
+    }
+
+    #[test]
+    fn test_xyz9e2() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_xyz9e2",
+            &inputs,
+            xyz9e2::vec3_to_xyz9e2,
+            vec3_to_xyz9e2,
+        ));
+        assert_no_divergences(divergences(
+            "xyz9e2_to_vec3",
+            &random_codes(RANDOM_ITERATIONS),
+            |v| bits3(xyz9e2::xyz9e2_to_vec3(v)),
+            |v| bits3(xyz9e2_to_vec3(v)),
+        ));
+    }
+
+    #[test]
+    fn test_xyz13e6() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_xyz13e6",
+            &inputs,
+            |v| {
+                let (a, b) = xyz13e6::vec3_to_xyz13e6(v);
+                [a, b as u32]
+            },
+            vec3_to_xyz13e6,
+        ));
+        let codes: Vec<[u32; 2]> = random_codes(RANDOM_ITERATIONS)
+            .chunks(2)
+            .map(|c| [c[0], c[1] & 0xFFFF])
+            .collect();
+        assert_no_divergences(divergences(
+            "xyz13e6_to_vec3",
+            &codes,
+            |v| bits3(xyz13e6::xyz13e6_to_vec3((v[0], v[1] as u16))),
+            |v| bits3(xyz13e6_to_vec3(v)),
+        ));
+    }
+
+    #[test]
+    fn test_xyz14e3() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_xyz14e3",
+            &inputs,
+            |v| {
+                let (a, b) = xyz14e3::vec3_to_xyz14e3(v);
+                [a, b as u32]
+            },
+            vec3_to_xyz14e3,
+        ));
+        let codes: Vec<[u32; 2]> = random_codes(RANDOM_ITERATIONS)
+            .chunks(2)
+            .map(|c| [c[0], c[1] & 0xFFFF])
+            .collect();
+        assert_no_divergences(divergences(
+            "xyz14e3_to_vec3",
+            &codes,
+            |v| bits3(xyz14e3::xyz14e3_to_vec3((v[0], v[1] as u16))),
+            |v| bits3(xyz14e3_to_vec3(v)),
+        ));
+    }
+
+    // The shader helpers read and write the same arrays as the rust packer, one value at a time.
+    #[test]
+    fn test_packed48() {
+        let values = packed48::tests::random_values(1001);
+        let words = packed48::pack(&values);
+        for (i, v) in values.iter().enumerate() {
+            let w = packed48_word(i as u32) as usize;
+            assert_eq!(
+                packed48_load(words[w], words[w + 1], i as u32),
+                [v.0, v.1 as u32]
+            );
+        }
+
+        // In random order, so stores have to keep the neighbouring values whether or not they
+        // have been stored yet.
+        let mut indices: Vec<usize> = (0..values.len()).collect();
+        indices.shuffle(&mut rand::thread_rng());
+        let mut stored = packed48::pack(&packed48::tests::random_values(values.len()));
+        for i in indices {
+            let w = packed48_word(i as u32) as usize;
+            let v = [values[i].0, values[i].1 as u32];
+            let [lo, hi] = packed48_store(stored[w], stored[w + 1], i as u32, v);
+            stored[w] = lo;
+            stored[w + 1] = hi;
+        }
+        assert_eq!(stored, words);
+
+        let inputs = test_inputs();
+        let mut words = vec![0; packed48::packed48_len(inputs.len())];
+        for (i, xyz) in inputs.iter().enumerate() {
+            let w = packed48_word(i as u32) as usize;
+            let [lo, hi] = store_xyz13e6(words[w], words[w + 1], i as u32, *xyz);
+            words[w] = lo;
+            words[w + 1] = hi;
+        }
+        for (i, xyz) in inputs.iter().enumerate() {
+            let w = packed48_word(i as u32) as usize;
+            let rust = xyz13e6::xyz13e6_to_vec3(xyz13e6::vec3_to_xyz13e6(*xyz));
+            assert_eq!(
+                bits3(load_xyz13e6(words[w], words[w + 1], i as u32)),
+                bits3(rust)
+            );
+            assert_eq!(packed48::load(&words, i), xyz13e6::vec3_to_xyz13e6(*xyz));
+        }
+
+        for (i, xyz) in inputs.iter().enumerate() {
+            let w = packed48_word(i as u32) as usize;
+            let [lo, hi] = store_xyz14e3(words[w], words[w + 1], i as u32, *xyz);
+            words[w] = lo;
+            words[w + 1] = hi;
+        }
+        for (i, xyz) in inputs.iter().enumerate() {
+            let w = packed48_word(i as u32) as usize;
+            let rust = xyz14e3::xyz14e3_to_vec3(xyz14e3::vec3_to_xyz14e3(*xyz));
+            assert_eq!(
+                bits3(load_xyz14e3(words[w], words[w + 1], i as u32)),
+                bits3(rust)
+            );
+        }
+    }
+
+    #[test]
+    fn test_xyz18e7() {
+        let inputs = test_inputs();
+        assert_no_divergences(divergences(
+            "vec3_to_xyz18e7",
+            &inputs,
+            |v| {
+                let (a, b) = xyz18e7::vec3_to_xyz18e7(v);
+                [a, b]
+            },
+            vec3_to_xyz18e7,
+        ));
+        let codes: Vec<[u32; 2]> = random_codes(RANDOM_ITERATIONS)
+            .chunks(2)
+            .map(|c| [c[0], c[1]])
+            .collect();
+        assert_no_divergences(divergences(
+            "xyz18e7_to_vec3",
+            &codes,
+            |v| bits3(xyz18e7::xyz18e7_to_vec3((v[0], v[1]))),
+            |v| bits3(xyz18e7_to_vec3(v)),
+        ));
+    }
+
+    fn shader_parts(parts: Parts) -> SharedExponentParts {
+        SharedExponentParts {
+            exponent: parts.exponent,
+            mantissas: parts.mantissas,
+            signs: parts.signs.map(|s| s as u32),
+        }
+    }
+
+    // Random codes with a scale, from staying in range to saturating or rounding to zero.
+    fn random_scaled_codes() -> Vec<([u32; 2], i32)> {
+        let mut rng = rand::thread_rng();
+        (0..RANDOM_ITERATIONS / 4)
+            .map(|_| ([rng.gen(), rng.gen()], rng.gen_range(-140..140)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parts() {
+        let inputs = random_scaled_codes();
+        assert_no_divergences(divergences(
+            "rgb9e5 parts",
+            &inputs,
+            |([v, _], k)| {
+                let parts = shader_parts(rgb9e5::decompose_rgb9e5(v));
+                (parts, v, rgb9e5::ldexp_rgb9e5(v, k))
+            },
+            |([v, _], k)| {
+                let parts = decompose_rgb9e5(v);
+                (parts, compose_rgb9e5(parts), ldexp_rgb9e5(v, k))
+            },
+        ));
+        assert_no_divergences(divergences(
+            "xyz8e5 parts",
+            &inputs,
+            |([v, _], k)| {
+                let parts = shader_parts(xyz8e5::decompose_xyz8e5(v));
+                let ops = [xyz8e5::negate_xyz8e5(v), xyz8e5::abs_xyz8e5(v)];
+                (parts, v, ops, xyz8e5::ldexp_xyz8e5(v, k))
+            },
+            |([v, _], k)| {
+                let parts = decompose_xyz8e5(v);
+                let ops = [negate_xyz8e5(v), abs_xyz8e5(v)];
+                (parts, compose_xyz8e5(parts), ops, ldexp_xyz8e5(v, k))
+            },
+        ));
+        assert_no_divergences(divergences(
+            "xyz9e2 parts",
+            &inputs,
+            |([v, _], k)| {
+                let parts = shader_parts(xyz9e2::decompose_xyz9e2(v));
+                let ops = [xyz9e2::negate_xyz9e2(v), xyz9e2::abs_xyz9e2(v)];
+                (parts, v, ops, xyz9e2::ldexp_xyz9e2(v, k))
+            },
+            |([v, _], k)| {
+                let parts = decompose_xyz9e2(v);
+                let ops = [negate_xyz9e2(v), abs_xyz9e2(v)];
+                (parts, compose_xyz9e2(parts), ops, ldexp_xyz9e2(v, k))
+            },
+        ));
+
+        // The upper half of the second word is padding, which negate and abs keep and compose
+        // clears.
+        let padded = |(a, b): (u32, u16), padding: u32| [a, b as u32 | padding & 0xFFFF0000];
+        assert_no_divergences(divergences(
+            "xyz13e6 parts",
+            &inputs,
+            |([a, b], k)| {
+                let v = (a, b as u16);
+                let parts = shader_parts(xyz13e6::decompose_xyz13e6(v));
+                let ops = [xyz13e6::negate_xyz13e6(v), xyz13e6::abs_xyz13e6(v)];
+                let ldexp = xyz13e6::ldexp_xyz13e6(v, k);
+                (
+                    parts,
+                    padded(v, 0),
+                    ops.map(|o| padded(o, b)),
+                    padded(ldexp, 0),
+                )
+            },
+            |(v, k)| {
+                let parts = decompose_xyz13e6(v);
+                let ops = [negate_xyz13e6(v), abs_xyz13e6(v)];
+                (parts, compose_xyz13e6(parts), ops, ldexp_xyz13e6(v, k))
+            },
+        ));
+        assert_no_divergences(divergences(
+            "xyz14e3 parts",
+            &inputs,
+            |([a, b], k)| {
+                let v = (a, b as u16);
+                let parts = shader_parts(xyz14e3::decompose_xyz14e3(v));
+                let ops = [xyz14e3::negate_xyz14e3(v), xyz14e3::abs_xyz14e3(v)];
+                let ldexp = xyz14e3::ldexp_xyz14e3(v, k);
+                (
+                    parts,
+                    padded(v, 0),
+                    ops.map(|o| padded(o, b)),
+                    padded(ldexp, 0),
+                )
+            },
+            |(v, k)| {
+                let parts = decompose_xyz14e3(v);
+                let ops = [negate_xyz14e3(v), abs_xyz14e3(v)];
+                (parts, compose_xyz14e3(parts), ops, ldexp_xyz14e3(v, k))
+            },
+        ));
+        assert_no_divergences(divergences(
+            "xyz18e7 parts",
+            &inputs,
+            |([a, b], k)| {
+                let v = (a, b);
+                let parts = shader_parts(xyz18e7::decompose_xyz18e7(v));
+                let ops = [xyz18e7::negate_xyz18e7(v), xyz18e7::abs_xyz18e7(v)];
+                let ldexp = xyz18e7::ldexp_xyz18e7(v, k);
+                (parts, [a, b], ops.map(|o| [o.0, o.1]), [ldexp.0, ldexp.1])
+            },
+            |(v, k)| {
+                let parts = decompose_xyz18e7(v);
+                let ops = [negate_xyz18e7(v), abs_xyz18e7(v)];
+                (parts, compose_xyz18e7(parts), ops, ldexp_xyz18e7(v, k))
+            },
+        ));
+    }
+}
@@ -0,0 +1,1240 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::custom_shared_format::SharedExponentFormat;
+use crate::shaders::{Format, ShaderLanguage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub word: u32,
+    pub offset: u32,
+    pub bits: u32,
+}
+
+impl BitRange {
+    pub const fn new(word: u32, offset: u32, bits: u32) -> Self {
+        BitRange { word, offset, bits }
+    }
+}
+
+// Where each field is stored in the packed u32 words. A field can be split over several ranges,
+// which are listed starting with its least significant bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub words: u32,
+    pub exponent: Vec<BitRange>,
+    pub mantissas: [Vec<BitRange>; 3],
+    pub signs: Option<[BitRange; 3]>,
+}
+
+impl Layout {
+    // x, y and z mantissas each followed by their sign bit if signed, then the exponent. This is
+    // the layout of rgb9e5, xyz8e5 and xyz9e2. Fields that cross a word boundary are split.
+    pub fn packed(exponent_bits: u32, mantissa_bits: u32, signed: bool) -> Self {
+        fn field(cursor: &mut u32, bits: u32) -> Vec<BitRange> {
+            let mut parts = Vec::new();
+            let mut remaining = bits;
+            while remaining > 0 {
+                let offset = *cursor % 32;
+                let n = remaining.min(32 - offset);
+                parts.push(BitRange::new(*cursor / 32, offset, n));
+                *cursor += n;
+                remaining -= n;
+            }
+            parts
+        }
+
+        let mut cursor = 0;
+        let mut mantissas: [Vec<BitRange>; 3] = Default::default();
+        let mut signs = [BitRange::new(0, 0, 1); 3];
+        for i in 0..3 {
+            mantissas[i] = field(&mut cursor, mantissa_bits);
+            if signed {
+                signs[i] = field(&mut cursor, 1)[0];
+            }
+        }
+        let exponent = field(&mut cursor, exponent_bits);
+
+        Layout {
+            words: cursor.div_ceil(32),
+            exponent,
+            mantissas,
+            signs: signed.then_some(signs),
+        }
+    }
+
+    // Checks that the fields have the given sizes and don't overlap or leave the words.
+    pub fn is_valid(&self, exponent_bits: u32, mantissa_bits: u32, signed: bool) -> bool {
+        let mut used = vec![0u32; self.words as usize];
+        let mut check = |ranges: &[BitRange], bits: u32| {
+            let mut total = 0;
+            for r in ranges {
+                if r.word >= self.words || r.bits == 0 || r.offset + r.bits > 32 {
+                    return false;
+                }
+                let mask = (u32::MAX >> (32 - r.bits)) << r.offset;
+                if used[r.word as usize] & mask != 0 {
+                    return false;
+                }
+                used[r.word as usize] |= mask;
+                total += r.bits;
+            }
+            total == bits
+        };
+        let mut valid = check(&self.exponent, exponent_bits);
+        for m in &self.mantissas {
+            valid &= check(m, mantissa_bits);
+        }
+        match self.signs {
+            Some(signs) => valid && signed && signs.iter().all(|s| check(&[*s], 1)),
+            None => valid && !signed,
+        }
+    }
+}
+
+// Stores `value` in the ranges of a field, with one shifted term per range like the code emitted
+// by `generate`. The value has to fit the field, as the last term isn't masked.
+pub fn pack(words: &mut [u32], ranges: &[BitRange], value: u32) {
+    let mut consumed = 0;
+    for (i, r) in ranges.iter().enumerate() {
+        let mut term = value;
+        if consumed > 0 {
+            term >>= consumed;
+        }
+        if i + 1 < ranges.len() {
+            term &= u32::MAX >> (32 - r.bits);
+        }
+        words[r.word as usize] |= term << r.offset;
+        consumed += r.bits;
+    }
+}
+
+// Reads a field back from its ranges.
+pub fn unpack(words: &[u32], ranges: &[BitRange]) -> u32 {
+    let mut consumed = 0;
+    let mut value = 0;
+    for r in ranges {
+        value |= (words[r.word as usize] >> r.offset & u32::MAX >> (32 - r.bits)) << consumed;
+        consumed += r.bits;
+    }
+    value
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatDesc {
+    // Used for the function names and as the constant prefix.
+    pub name: String,
+    pub exponent_bits: u32,
+    pub mantissa_bits: u32,
+    pub exp_bias: i32,
+    pub signed: bool,
+    // Decoded values are multiplied by this, see NORM_MULT in xyz9e2.
+    pub scale: f32,
+    // Largest encodable value before the scale is applied.
+    pub max: f32,
+    pub layout: Layout,
+}
+
+impl FormatDesc {
+    pub fn new(name: &str, format: &SharedExponentFormat, signed: bool) -> Self {
+        let exponent_bits = format.exponent_bits as u32;
+        let mantissa_bits = format.mantissa_bits as u32;
+        FormatDesc {
+            name: name.to_string(),
+            exponent_bits,
+            mantissa_bits,
+            exp_bias: format.exp_bias,
+            signed,
+            scale: 1.0,
+            max: format.max,
+            layout: Layout::packed(exponent_bits, mantissa_bits, signed),
+        }
+    }
+
+    // Also recomputes max for the new bias, the same way as the format modules do. Unlike
+    // SharedExponentFormat::new this doesn't floor it.
+    pub fn with_exp_bias(mut self, exp_bias: i32) -> Self {
+        debug_assert!(exp_bias <= self.max_valid_biased_exp());
+        self.exp_bias = exp_bias;
+        let max_exp = self.max_valid_biased_exp() - exp_bias;
+        self.max = (self.max_mantissa() as f32) / self.mantissa_values() as f32
+            * (1u128 << max_exp) as f32;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        debug_assert!(layout.is_valid(self.exponent_bits, self.mantissa_bits, self.signed));
+        self.layout = layout;
+        self
+    }
+
+    pub fn rgb9e5() -> Self {
+        FormatDesc::new("rgb9e5", &SharedExponentFormat::new(5, 9), false)
+    }
+
+    pub fn xyz8e5() -> Self {
+        FormatDesc::new("xyz8e5", &SharedExponentFormat::new(5, 8), true)
+    }
+
+    pub fn xyz9e2() -> Self {
+        FormatDesc::new("xyz9e2", &SharedExponentFormat::new(2, 9), true)
+            .with_exp_bias(3)
+            .with_scale(crate::xyz9e2::NORM_MULT)
+    }
+
+    pub fn xyz13e6() -> Self {
+        let r = BitRange::new;
+        FormatDesc::new("xyz13e6", &SharedExponentFormat::new(6, 13), true).with_layout(Layout {
+            words: 2,
+            exponent: vec![r(0, 26, 6)],
+            mantissas: [vec![r(0, 0, 13)], vec![r(0, 13, 13)], vec![r(1, 0, 13)]],
+            signs: Some([r(1, 13, 1), r(1, 14, 1), r(1, 15, 1)]),
+        })
+    }
+
+    pub fn xyz14e3() -> Self {
+        let r = BitRange::new;
+        FormatDesc::new("xyz14e3", &SharedExponentFormat::new(3, 14), true)
+            .with_exp_bias(3)
+            .with_scale(crate::xyz14e3::NORM_MULT)
+            .with_layout(Layout {
+                words: 2,
+                exponent: vec![r(0, 31, 1), r(1, 14, 2)],
+                mantissas: [vec![r(0, 0, 14)], vec![r(0, 14, 14)], vec![r(1, 0, 14)]],
+                signs: Some([r(0, 28, 1), r(0, 29, 1), r(0, 30, 1)]),
+            })
+    }
+
+    pub fn xyz18e7() -> Self {
+        let r = BitRange::new;
+        FormatDesc::new("xyz18e7", &SharedExponentFormat::new(7, 18), true).with_layout(Layout {
+            words: 2,
+            exponent: vec![r(1, 25, 7)],
+            mantissas: [
+                vec![r(0, 0, 18)],
+                vec![r(0, 18, 14), r(1, 0, 4)],
+                vec![r(1, 4, 18)],
+            ],
+            signs: Some([r(1, 22, 1), r(1, 23, 1), r(1, 24, 1)]),
+        })
+    }
+
+    pub fn from_format(format: Format) -> Self {
+        match format {
+            Format::Rgb9e5 => FormatDesc::rgb9e5(),
+            Format::Xyz8e5 => FormatDesc::xyz8e5(),
+            Format::Xyz9e2 => FormatDesc::xyz9e2(),
+            Format::Xyz13e6 => FormatDesc::xyz13e6(),
+            Format::Xyz14e3 => FormatDesc::xyz14e3(),
+            Format::Xyz18e7 => FormatDesc::xyz18e7(),
+        }
+    }
+
+    pub fn max_valid_biased_exp(&self) -> i32 {
+        (1 << self.exponent_bits) - 1
+    }
+
+    pub fn max_exp(&self) -> i32 {
+        self.max_valid_biased_exp() - self.exp_bias
+    }
+
+    pub fn mantissa_values(&self) -> i32 {
+        1 << self.mantissa_bits
+    }
+
+    pub fn max_mantissa(&self) -> i32 {
+        self.mantissa_values() - 1
+    }
+
+    // The MAX_ constant, the largest value that can be encoded.
+    pub fn max_value(&self) -> f32 {
+        self.max * self.scale
+    }
+
+    // The EPSILON_ constant, the smallest non zero value that can be encoded.
+    pub fn epsilon(&self) -> f32 {
+        (1.0 / self.mantissa_values() as f32) / (1u128 << self.exp_bias) as f32
+    }
+}
+
+// Generates constants and encode/decode functions for the format in the same style as the files
+// in `glsl/`, `wgsl/`, `hlsl/` and `msl/`. The helper functions are the same as in those files,
+// so the output can be combined with them using `shaders::combine_sources`.
+pub fn generate(desc: &FormatDesc, language: ShaderLanguage) -> String {
+    debug_assert!(desc
+        .layout
+        .is_valid(desc.exponent_bits, desc.mantissa_bits, desc.signed));
+    let lang = Lang(language);
+    let mut out = String::new();
+    if language == ShaderLanguage::Msl {
+        out.push_str("#include <metal_stdlib>\nusing namespace metal;\n\n");
+    }
+    constants(&mut out, desc, lang);
+    out.push('\n');
+    out.push_str(lang.helper_floor_log2());
+    out.push('\n');
+    if desc.signed {
+        out.push_str(lang.helper_is_sign_negative());
+        out.push('\n');
+        out.push_str(lang.helper_abs_nan_to_zero());
+    } else {
+        out.push_str(lang.helper_nan_to_zero());
+    }
+    out.push('\n');
+    encoder(&mut out, desc, lang);
+    out.push('\n');
+    if let Some(helper) = lang.helper_bitfield_extract() {
+        out.push_str(helper);
+        out.push('\n');
+    }
+    decoder(&mut out, desc, lang);
+    out
+}
+
+#[derive(Clone, Copy)]
+struct Lang(ShaderLanguage);
+
+impl Lang {
+    fn is_wgsl(self) -> bool {
+        self.0 == ShaderLanguage::Wgsl
+    }
+
+    // WGSL function names get a trailing underscore, like the files in `wgsl/`.
+    fn function(self, name: &str) -> String {
+        if self.is_wgsl() {
+            format!("{name}_")
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn float_type(self) -> &'static str {
+        if self.is_wgsl() {
+            "f32"
+        } else {
+            "float"
+        }
+    }
+
+    fn int_type(self) -> &'static str {
+        if self.is_wgsl() {
+            "i32"
+        } else {
+            "int"
+        }
+    }
+
+    fn uint_type(self) -> &'static str {
+        if self.is_wgsl() {
+            "u32"
+        } else {
+            "uint"
+        }
+    }
+
+    fn vec3_type(self) -> &'static str {
+        match self.0 {
+            ShaderLanguage::Glsl => "vec3",
+            ShaderLanguage::Wgsl => "vec3<f32>",
+            ShaderLanguage::Hlsl | ShaderLanguage::Msl => "float3",
+        }
+    }
+
+    fn words_type(self, words: u32) -> &'static str {
+        match (self.0, words) {
+            (ShaderLanguage::Glsl, 1) => "uint",
+            (ShaderLanguage::Glsl, 2) => "uvec2",
+            (ShaderLanguage::Glsl, 3) => "uvec3",
+            (ShaderLanguage::Glsl, _) => "uvec4",
+            (ShaderLanguage::Wgsl, 1) => "u32",
+            (ShaderLanguage::Wgsl, 2) => "vec2<u32>",
+            (ShaderLanguage::Wgsl, 3) => "vec3<u32>",
+            (ShaderLanguage::Wgsl, _) => "vec4<u32>",
+            (ShaderLanguage::Hlsl | ShaderLanguage::Msl, 1) => "uint",
+            (ShaderLanguage::Hlsl | ShaderLanguage::Msl, 2) => "uint2",
+            (ShaderLanguage::Hlsl | ShaderLanguage::Msl, 3) => "uint3",
+            (ShaderLanguage::Hlsl | ShaderLanguage::Msl, _) => "uint4",
+        }
+    }
+
+    // The constructor of the vector returned by the encoder.
+    fn words_constructor(self, words: u32) -> String {
+        if self.is_wgsl() {
+            format!("vec{words}")
+        } else {
+            self.words_type(words).to_string()
+        }
+    }
+
+    // A vec3 with all components set to `value`. HLSL promotes scalars in min and clamp instead.
+    fn splat(self, value: &str) -> String {
+        match self.0 {
+            ShaderLanguage::Glsl | ShaderLanguage::Wgsl => format!("vec3({value})"),
+            ShaderLanguage::Hlsl => value.to_string(),
+            ShaderLanguage::Msl => format!("float3({value})"),
+        }
+    }
+
+    fn float_to_bits(self, value: &str) -> String {
+        match self.0 {
+            ShaderLanguage::Glsl => format!("floatBitsToUint({value})"),
+            ShaderLanguage::Wgsl => format!("bitcast<u32>({value})"),
+            ShaderLanguage::Hlsl => format!("asuint({value})"),
+            ShaderLanguage::Msl => format!("as_type<uint>({value})"),
+        }
+    }
+
+    fn bits_to_float(self, value: &str) -> String {
+        match self.0 {
+            ShaderLanguage::Glsl => format!("uintBitsToFloat({value})"),
+            ShaderLanguage::Wgsl => format!("bitcast<f32>({value})"),
+            ShaderLanguage::Hlsl => format!("asfloat({value})"),
+            ShaderLanguage::Msl => format!("as_type<float>({value})"),
+        }
+    }
+
+    fn word(self, words: u32, word: u32) -> String {
+        match (self.0, words) {
+            (_, 1) => "v".to_string(),
+            (ShaderLanguage::Wgsl, _) => format!("v[{word}]"),
+            (_, _) => format!("v.{}", ["x", "y", "z", "w"][word as usize]),
+        }
+    }
+
+    fn extract(self, value: &str, offset: u32, bits: u32) -> String {
+        match self.0 {
+            ShaderLanguage::Glsl | ShaderLanguage::Hlsl => {
+                format!("bitfield_extract({value}, {offset}u, {bits}u)")
+            }
+            ShaderLanguage::Wgsl => format!("extractBits({value}, {offset}u, {bits}u)"),
+            ShaderLanguage::Msl => format!("extract_bits({value}, {offset}u, {bits}u)"),
+        }
+    }
+
+    fn helper_floor_log2(self) -> &'static str {
+        match self.0 {
+            ShaderLanguage::Glsl => {
+                "int floor_log2(float x) {
+    uint f = floatBitsToUint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+"
+            }
+            ShaderLanguage::Wgsl => {
+                "fn floor_log2_(x: f32) -> i32 {
+    let f = bitcast<u32>(x);
+    let biasedexponent = (f & 0x7F800000u) >> 23u;
+    return i32(biasedexponent) - 127;
+}
+"
+            }
+            ShaderLanguage::Hlsl => {
+                "int floor_log2(float x) {
+    uint f = asuint(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+"
+            }
+            ShaderLanguage::Msl => {
+                "int floor_log2(float x) {
+    uint f = as_type<uint>(x);
+    uint biasedexponent = (f & 0x7F800000u) >> 23u;
+    return int(biasedexponent) - 127;
+}
+"
+            }
+        }
+    }
+
+    fn helper_is_sign_negative(self) -> &'static str {
+        match self.0 {
+            ShaderLanguage::Glsl => {
+                "uint is_sign_negative(float v) {
+    return (floatBitsToUint(v) >> 31u) & 1u;
+}
+"
+            }
+            ShaderLanguage::Wgsl => {
+                "fn is_sign_negative(v: f32) -> u32 {
+    return (bitcast<u32>(v) >> 31u) & 1u;
+}
+"
+            }
+            ShaderLanguage::Hlsl => {
+                "uint is_sign_negative(float v) {
+    return (asuint(v) >> 31u) & 1u;
+}
+"
+            }
+            ShaderLanguage::Msl => {
+                "uint is_sign_negative(float v) {
+    return (as_type<uint>(v) >> 31u) & 1u;
+}
+"
+            }
+        }
+    }
+
+    fn helper_nan_to_zero(self) -> &'static str {
+        match self.0 {
+            ShaderLanguage::Glsl => {
+                "// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v);
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b & 0x7FFFFFFFu, uvec3(0x7F800000u))));
+}
+"
+            }
+            ShaderLanguage::Wgsl => {
+                "// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, (b & vec3(0x7FFFFFFFu)) <= vec3(0x7F800000u)));
+}
+"
+            }
+            ShaderLanguage::Hlsl => {
+                "// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 nan_to_zero(float3 v) {
+    uint3 b = asuint(v);
+    return asfloat(b * uint3((b & 0x7FFFFFFFu) <= 0x7F800000u));
+}
+"
+            }
+            ShaderLanguage::Msl => {
+                "// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 nan_to_zero(float3 v) {
+    uint3 b = as_type<uint3>(v);
+    return as_type<float3>(select(uint3(0u), b, (b & 0x7FFFFFFFu) <= 0x7F800000u));
+}
+"
+            }
+        }
+    }
+
+    fn helper_abs_nan_to_zero(self) -> &'static str {
+        match self.0 {
+            ShaderLanguage::Glsl => {
+                "// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+vec3 abs_nan_to_zero(vec3 v) {
+    uvec3 b = floatBitsToUint(v) & 0x7FFFFFFFu;
+    return uintBitsToFloat(b * uvec3(lessThanEqual(b, uvec3(0x7F800000u))));
+}
+"
+            }
+            ShaderLanguage::Wgsl => {
+                "// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn abs_nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v) & vec3(0x7FFFFFFFu);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, b <= vec3(0x7F800000u)));
+}
+"
+            }
+            ShaderLanguage::Hlsl => {
+                "// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = asuint(v) & 0x7FFFFFFFu;
+    return asfloat(b * uint3(b <= 0x7F800000u));
+}
+"
+            }
+            ShaderLanguage::Msl => {
+                "// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that isnan() is always false, and min, max and clamp leave the result of a NaN input undefined.
+float3 abs_nan_to_zero(float3 v) {
+    uint3 b = as_type<uint3>(v) & 0x7FFFFFFFu;
+    return as_type<float3>(select(uint3(0u), b, b <= 0x7F800000u));
+}
+"
+            }
+        }
+    }
+
+    // WGSL and MSL have it built in.
+    fn helper_bitfield_extract(self) -> Option<&'static str> {
+        match self.0 {
+            ShaderLanguage::Glsl => Some(
+                "// Provided for compatibility. With opengl 4.0 and later bitfieldExtract can be used instead.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+",
+            ),
+            ShaderLanguage::Wgsl | ShaderLanguage::Msl => None,
+            ShaderLanguage::Hlsl => Some(
+                "// HLSL has no intrinsic for extracting a bitfield.
+uint bitfield_extract(uint value, uint offset, uint bits) {
+    uint mask = (1u << bits) - 1u;
+    return (value >> offset) & mask;
+}
+",
+            ),
+        }
+    }
+}
+
+// Formats a float so that it parses back to exactly the same f32.
+fn float_literal(v: f32) -> String {
+    format!("{v:?}")
+}
+
+fn constants(out: &mut String, desc: &FormatDesc, lang: Lang) {
+    let n = desc.name.to_uppercase();
+    let block = |out: &mut String, consts: &[(String, String, &str)]| {
+        let width = consts.iter().map(|c| c.0.len()).max().unwrap_or(0);
+        for (name, value, ty) in consts {
+            let _ = match lang.0 {
+                ShaderLanguage::Glsl => writeln!(out, "const {ty:<5} {name:<width$} = {value};"),
+                ShaderLanguage::Wgsl => writeln!(out, "const {name:<width$} = {value};"),
+                ShaderLanguage::Hlsl => {
+                    writeln!(out, "static const {ty:<5} {name:<width$} = {value};")
+                }
+                ShaderLanguage::Msl => writeln!(out, "constant {ty:<5} {name:<width$} = {value};"),
+            };
+        }
+    };
+    block(
+        out,
+        &[
+            (
+                format!("{n}_EXPONENT_BITS"),
+                format!("{}u", desc.exponent_bits),
+                "uint",
+            ),
+            (
+                format!("{n}_MANTISSA_BITS"),
+                format!("{}", desc.mantissa_bits),
+                "int",
+            ),
+            (
+                format!("{n}_MANTISSA_BITSU"),
+                format!("{}u", desc.mantissa_bits),
+                "uint",
+            ),
+            (format!("{n}_EXP_BIAS"), format!("{}", desc.exp_bias), "int"),
+            (
+                format!("{n}_MAX_VALID_BIASED_EXP"),
+                format!("{}u", desc.max_valid_biased_exp()),
+                "uint",
+            ),
+        ],
+    );
+    out.push('\n');
+    let mut derived = vec![
+        (
+            format!("MAX_{n}_EXP"),
+            format!("{}u", desc.max_exp()),
+            "uint",
+        ),
+        (
+            format!("{n}_MANTISSA_VALUES"),
+            format!("{}", desc.mantissa_values()),
+            "int",
+        ),
+        (
+            format!("MAX_{n}_MANTISSA"),
+            format!("{}", desc.max_mantissa()),
+            "int",
+        ),
+        (
+            format!("MAX_{n}_MANTISSAU"),
+            format!("{}u", desc.max_mantissa()),
+            "uint",
+        ),
+        (
+            format!("MAX_{n}_"),
+            float_literal(desc.max_value()),
+            "float",
+        ),
+        (
+            format!("EPSILON_{n}_"),
+            float_literal(desc.epsilon()),
+            "float",
+        ),
+    ];
+    if desc.scale != 1.0 {
+        derived.push((format!("{n}_NORM_MULT"), float_literal(desc.scale), "float"));
+    }
+    block(out, &derived);
+}
+
+const AXES: [&str; 3] = ["x", "y", "z"];
+
+// `value` stored in `ranges`, as one shifted term per range.
+fn pack_terms(value: &str, ranges: &[BitRange], terms: &mut [Vec<(u32, String)>]) {
+    let mut consumed = 0;
+    for (i, r) in ranges.iter().enumerate() {
+        let mut term = value.to_string();
+        if consumed > 0 {
+            term = format!("({term} >> {consumed}u)");
+        }
+        if i + 1 < ranges.len() {
+            term = format!("({term} & 0x{:X}u)", u32::MAX >> (32 - r.bits));
+        }
+        terms[r.word as usize].push((r.offset, format!("({term} << {}u)", r.offset)));
+        consumed += r.bits;
+    }
+}
+
+// The value stored in `ranges` of `v`.
+fn unpack_expr(ranges: &[BitRange], words: u32, lang: Lang) -> String {
+    let mut consumed = 0;
+    let mut parts = Vec::new();
+    for r in ranges {
+        let mut part = lang.extract(&lang.word(words, r.word), r.offset, r.bits);
+        if consumed > 0 {
+            part = format!("{part} << {consumed}u");
+        }
+        parts.push(part);
+        consumed += r.bits;
+    }
+    parts.join(" | ")
+}
+
+fn encoder(out: &mut String, desc: &FormatDesc, lang: Lang) {
+    let n = desc.name.to_uppercase();
+    let words = desc.layout.words;
+    let fn_name = lang.function(&format!("vec3_to_{}", desc.name));
+    let ret = lang.words_type(words);
+    let (f, i, u) = (lang.float_type(), lang.int_type(), lang.uint_type());
+    let (decl_f, decl_i, decl_u, var_f, var_i) = if lang.is_wgsl() {
+        ("let ", "let ", "let ", "var ", "var ")
+    } else {
+        ("float ", "int ", "uint ", "float ", "int ")
+    };
+    let floor_log2 = lang.function("floor_log2");
+
+    let _ = writeln!(
+        out,
+        "// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt"
+    );
+    if lang.is_wgsl() {
+        let _ = writeln!(out, "fn {fn_name}(xyz_in: vec3<f32>) -> {ret} {{");
+    } else {
+        let _ = writeln!(out, "{ret} {fn_name}({} xyz_in) {{", lang.vec3_type());
+    }
+    if desc.signed {
+        for a in AXES {
+            let _ = writeln!(out, "    {decl_u}{a}sign = is_sign_negative(xyz_in.{a});");
+        }
+        out.push('\n');
+    }
+    let max = lang.splat(&format!("MAX_{n}_"));
+    let clamped = if desc.signed {
+        format!("min(abs_nan_to_zero(xyz_in), {max})")
+    } else {
+        format!("clamp(nan_to_zero(xyz_in), {}, {max})", lang.splat("0.0"))
+    };
+    let scaled = if desc.scale != 1.0 {
+        format!("{clamped} / {n}_NORM_MULT")
+    } else {
+        clamped
+    };
+    if lang.is_wgsl() {
+        let _ = writeln!(out, "    let xyz = {scaled};");
+    } else {
+        let _ = writeln!(out, "    {} xyz = {scaled};", lang.vec3_type());
+    }
+    out.push('\n');
+    let _ = writeln!(
+        out,
+        "    {decl_f}maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    {var_i}exp_shared = max(-{n}_EXP_BIAS - 1, {floor_log2}(maxxyz)) + 1 + {n}_EXP_BIAS;
+    {var_f}denom = exp2({f}(exp_shared - {n}_EXP_BIAS - {n}_MANTISSA_BITS));
+
+    {decl_i}maxm = {i}(floor(maxxyz / denom + 0.5));
+    if (maxm == {n}_MANTISSA_VALUES) {{
+        denom *= 2.0;
+        exp_shared += 1;
+    }}
+"
+    );
+    match lang.0 {
+        ShaderLanguage::Glsl => {
+            let _ = writeln!(out, "    uvec3 s = uvec3(floor(xyz / denom + 0.5));");
+        }
+        ShaderLanguage::Wgsl => {
+            let _ = writeln!(out, "    let s = vec3<u32>(floor(xyz / denom + 0.5));");
+        }
+        ShaderLanguage::Hlsl | ShaderLanguage::Msl => {
+            let _ = writeln!(out, "    uint3 s = uint3(floor(xyz / denom + 0.5));");
+        }
+    }
+    let _ = writeln!(out, "    {decl_u}e = {u}(exp_shared);");
+    out.push('\n');
+
+    let mut terms = vec![Vec::new(); words as usize];
+    pack_terms("e", &desc.layout.exponent, &mut terms);
+    for (i, a) in AXES.iter().enumerate() {
+        pack_terms(&format!("s.{a}"), &desc.layout.mantissas[i], &mut terms);
+        if let Some(signs) = &desc.layout.signs {
+            pack_terms(&format!("{a}sign"), &[signs[i]], &mut terms);
+        }
+    }
+    let word_exprs: Vec<String> = terms
+        .iter_mut()
+        .map(|t| {
+            if t.is_empty() {
+                return "0u".to_string();
+            }
+            // Highest bits first, like the hand written shaders.
+            t.sort_by_key(|t| core::cmp::Reverse(t.0));
+            t.iter()
+                .map(|t| t.1.as_str())
+                .collect::<Vec<_>>()
+                .join(" | ")
+        })
+        .collect();
+    if words == 1 {
+        let _ = writeln!(out, "    return {};", word_exprs[0]);
+    } else {
+        let _ = writeln!(out, "    return {}(", lang.words_constructor(words));
+        for (i, w) in word_exprs.iter().enumerate() {
+            let sep = if i + 1 < word_exprs.len() { "," } else { "" };
+            let _ = writeln!(out, "        {w}{sep}");
+        }
+        let _ = writeln!(out, "    );");
+    }
+    let _ = writeln!(out, "}}");
+}
+
+fn decoder(out: &mut String, desc: &FormatDesc, lang: Lang) {
+    let n = desc.name.to_uppercase();
+    let words = desc.layout.words;
+    let fn_name = lang.function(&format!("{}_to_vec3", desc.name));
+    let arg = lang.words_type(words);
+    let (f, i) = (lang.float_type(), lang.int_type());
+    let (decl_i, decl_f, decl_u) = if lang.is_wgsl() {
+        ("let ", "let ", "let ")
+    } else {
+        ("int ", "float ", "uint ")
+    };
+
+    if lang.is_wgsl() {
+        let _ = writeln!(out, "fn {fn_name}(v: {arg}) -> vec3<f32> {{");
+    } else {
+        let _ = writeln!(out, "{} {fn_name}({arg} v) {{", lang.vec3_type());
+    }
+    let _ = writeln!(
+        out,
+        "    {decl_i}exponent = {i}({}) - {n}_EXP_BIAS - {n}_MANTISSA_BITS;",
+        unpack_expr(&desc.layout.exponent, words, lang)
+    );
+    if desc.scale != 1.0 {
+        let _ = writeln!(
+            out,
+            "    {decl_f}scale = exp2({f}(exponent)) * {n}_NORM_MULT;"
+        );
+    } else {
+        let _ = writeln!(out, "    {decl_f}scale = exp2({f}(exponent));");
+    }
+    out.push('\n');
+    for (ax, a) in AXES.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    {decl_u}{a}b = {};",
+            unpack_expr(&desc.layout.mantissas[ax], words, lang)
+        );
+    }
+    out.push('\n');
+    let ctor = match lang.0 {
+        ShaderLanguage::Glsl | ShaderLanguage::Wgsl => "vec3",
+        ShaderLanguage::Hlsl | ShaderLanguage::Msl => "float3",
+    };
+    if let Some(signs) = &desc.layout.signs {
+        let _ = writeln!(
+            out,
+            "    // The sign bit is shifted over to the corresponding IEEE 754 sign location."
+        );
+        let _ = writeln!(out, "    return {ctor}(");
+        for (ax, a) in AXES.iter().enumerate() {
+            let sign = unpack_expr(&[signs[ax]], words, lang);
+            let sep = if ax < 2 || lang.is_wgsl() { "," } else { "" };
+            let bits = lang.float_to_bits(&format!("{f}({a}b)"));
+            let value = lang.bits_to_float(&format!("{bits} | {sign} << 31u"));
+            let _ = writeln!(out, "        {value}{sep}");
+        }
+        let _ = writeln!(out, "    ) * scale;");
+    } else {
+        let _ = writeln!(out, "    return {ctor}({f}(xb), {f}(yb), {f}(zb)) * scale;");
+    }
+    let _ = writeln!(out, "}}");
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use crate::shader_emulation::tests::{
+        assert_no_divergences, bits3, divergences, random_codes, test_inputs,
+    };
+    use crate::shader_emulation::{desc_to_vec3, vec3_to_desc};
+    use crate::shaders::tests::LANGUAGES;
+    use crate::shaders::{self, combine_sources, Format};
+    use crate::{rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+    use super::*;
+
+    fn set_field(words: &mut [u32], ranges: &[BitRange], value: u32) {
+        let mut bit = 0;
+        for r in ranges {
+            for i in 0..r.bits {
+                words[r.word as usize] |= ((value >> bit) & 1) << (r.offset + i);
+                bit += 1;
+            }
+        }
+    }
+
+    // Reference encoder built on SharedExponentFormat, with the result packed bit by bit.
+    fn reference_encode(desc: &FormatDesc, format: &SharedExponentFormat, v: [f32; 3]) -> Vec<u32> {
+        let abs = if desc.signed { v.map(f32::abs) } else { v };
+        let (mantissas, exp_shared) = format.encode3(abs);
+        let mut words = vec![0; desc.layout.words as usize];
+        set_field(&mut words, &desc.layout.exponent, exp_shared as u32);
+        for i in 0..3 {
+            set_field(&mut words, &desc.layout.mantissas[i], mantissas[i]);
+            if let Some(signs) = &desc.layout.signs {
+                set_field(&mut words, &[signs[i]], v[i].is_sign_negative() as u32);
+            }
+        }
+        words
+    }
+
+    fn builtins() -> Vec<FormatDesc> {
+        vec![
+            FormatDesc::rgb9e5(),
+            FormatDesc::xyz8e5(),
+            FormatDesc::xyz9e2(),
+            FormatDesc::xyz13e6(),
+            FormatDesc::xyz14e3(),
+            FormatDesc::xyz18e7(),
+        ]
+    }
+
+    #[test]
+    fn test_builtin_layouts() {
+        for desc in builtins() {
+            assert!(
+                desc.layout
+                    .is_valid(desc.exponent_bits, desc.mantissa_bits, desc.signed),
+                "{}",
+                desc.name
+            );
+        }
+        assert_eq!(FormatDesc::rgb9e5().max_value(), rgb9e5::MAX_RGB9E5);
+        assert_eq!(FormatDesc::xyz8e5().max_value(), xyz8e5::MAX_XYZ8E5);
+        assert_eq!(FormatDesc::xyz9e2().max_value(), xyz9e2::MAX_XYZ9E2);
+        assert_eq!(FormatDesc::xyz13e6().max_value(), xyz13e6::MAX_XYZ13E6);
+        assert_eq!(FormatDesc::xyz14e3().max_value(), xyz14e3::MAX_XYZ14E3);
+        assert_eq!(FormatDesc::xyz18e7().max_value(), xyz18e7::MAX_XYZ18E7);
+        assert_eq!(FormatDesc::xyz9e2().epsilon(), xyz9e2::EPSILON_XYZ9E2);
+        assert_eq!(FormatDesc::xyz18e7().epsilon(), xyz18e7::EPSILON_XYZ18E7);
+    }
+
+    #[test]
+    fn test_from_format_unpacks_parts() {
+        let mut rng = rand::thread_rng();
+        for format in Format::ALL {
+            let desc = FormatDesc::from_format(format);
+            assert_eq!(desc.name, format.name());
+            let layout = &desc.layout;
+            for _ in 0..10000 {
+                let v = format.encode([(); 3].map(|_| rng.gen_range(-10.0..10.0)));
+                let words = &v[..layout.words as usize];
+                let parts = format.decompose(v);
+                assert_eq!(unpack(words, &layout.exponent), parts.exponent);
+                let mut packed = vec![0; layout.words as usize];
+                pack(&mut packed, &layout.exponent, parts.exponent);
+                for i in 0..3 {
+                    assert_eq!(unpack(words, &layout.mantissas[i]), parts.mantissas[i]);
+                    pack(&mut packed, &layout.mantissas[i], parts.mantissas[i]);
+                    if let Some(signs) = layout.signs {
+                        assert_eq!(unpack(words, &[signs[i]]) != 0, parts.signs[i]);
+                        pack(&mut packed, &[signs[i]], parts.signs[i] as u32);
+                    }
+                }
+                assert_eq!(packed, words, "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtins_match_rust() {
+        let inputs = test_inputs();
+        let codes = random_codes(200000);
+        let codes2: Vec<[u32; 2]> = codes.chunks(2).map(|c| [c[0], c[1]]).collect();
+
+        let desc = FormatDesc::rgb9e5();
+        assert_no_divergences(divergences(
+            "rgb9e5",
+            &inputs,
+            |v| vec![rgb9e5::vec3_to_rgb9e5(v)],
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "rgb9e5",
+            &codes,
+            |v| bits3(rgb9e5::rgb9e5_to_vec3(v)),
+            |v| bits3(desc_to_vec3(&desc, &[v])),
+        ));
+
+        let desc = FormatDesc::xyz8e5();
+        assert_no_divergences(divergences(
+            "xyz8e5",
+            &inputs,
+            |v| vec![xyz8e5::vec3_to_xyz8e5(v)],
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "xyz8e5",
+            &codes,
+            |v| bits3(xyz8e5::xyz8e5_to_vec3(v)),
+            |v| bits3(desc_to_vec3(&desc, &[v])),
+        ));
+
+        let desc = FormatDesc::xyz9e2();
+        assert_no_divergences(divergences(
+            "xyz9e2",
+            &inputs,
+            |v| vec![xyz9e2::vec3_to_xyz9e2(v)],
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "xyz9e2",
+            &codes,
+            |v| bits3(xyz9e2::xyz9e2_to_vec3(v)),
+            |v| bits3(desc_to_vec3(&desc, &[v])),
+        ));
+
+        let desc = FormatDesc::xyz13e6();
+        assert_no_divergences(divergences(
+            "xyz13e6",
+            &inputs,
+            |v| {
+                let (a, b) = xyz13e6::vec3_to_xyz13e6(v);
+                vec![a, b as u32]
+            },
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "xyz13e6",
+            &codes2,
+            |v| bits3(xyz13e6::xyz13e6_to_vec3((v[0], v[1] as u16))),
+            |v| bits3(desc_to_vec3(&desc, &[v[0], v[1] & 0xFFFF])),
+        ));
+
+        let desc = FormatDesc::xyz14e3();
+        assert_no_divergences(divergences(
+            "xyz14e3",
+            &inputs,
+            |v| {
+                let (a, b) = xyz14e3::vec3_to_xyz14e3(v);
+                vec![a, b as u32]
+            },
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "xyz14e3",
+            &codes2,
+            |v| bits3(xyz14e3::xyz14e3_to_vec3((v[0], v[1] as u16))),
+            |v| bits3(desc_to_vec3(&desc, &[v[0], v[1] & 0xFFFF])),
+        ));
+
+        let desc = FormatDesc::xyz18e7();
+        assert_no_divergences(divergences(
+            "xyz18e7",
+            &inputs,
+            |v| {
+                let (a, b) = xyz18e7::vec3_to_xyz18e7(v);
+                vec![a, b]
+            },
+            |v| vec3_to_desc(&desc, v),
+        ));
+        assert_no_divergences(divergences(
+            "xyz18e7",
+            &codes2,
+            |v| bits3(xyz18e7::xyz18e7_to_vec3((v[0], v[1]))),
+            |v| bits3(desc_to_vec3(&desc, &v)),
+        ));
+    }
+
+    #[test]
+    fn test_custom_formats_match_rust() {
+        let inputs: Vec<[f32; 3]> = test_inputs().into_iter().step_by(16).collect();
+        for exponent_bits in 1..=7u8 {
+            for mantissa_bits in [1, 2, 5, 8, 10, 12, 16, 19] {
+                let format = SharedExponentFormat::new(exponent_bits, mantissa_bits);
+                for signed in [false, true] {
+                    let desc = FormatDesc::new("custom", &format, signed);
+                    let name = format!("{exponent_bits}e {mantissa_bits}m signed {signed}");
+                    assert_no_divergences(divergences(
+                        &name,
+                        &inputs,
+                        |v| reference_encode(&desc, &format, v),
+                        |v| vec3_to_desc(&desc, v),
+                    ));
+                    // Decoding is checked on what the encoder produces, as SharedExponentFormat
+                    // only decodes the separate parts.
+                    assert_no_divergences(divergences(
+                        &name,
+                        &inputs,
+                        |v| {
+                            let abs = if signed { v.map(f32::abs) } else { v };
+                            let (mantissas, exp_shared) = format.encode3(abs);
+                            let mut d = format.decode3(mantissas, exp_shared);
+                            for i in 0..3 {
+                                if signed && v[i].is_sign_negative() {
+                                    d[i] = -d[i];
+                                }
+                            }
+                            bits3(d)
+                        },
+                        |v| bits3(desc_to_vec3(&desc, &vec3_to_desc(&desc, v))),
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_constants() {
+        for desc in builtins() {
+            let n = desc.name.to_uppercase();
+            for language in LANGUAGES {
+                let src = generate(&desc, language);
+                for (name, value) in [
+                    (format!("MAX_{n}_"), desc.max_value()),
+                    (format!("EPSILON_{n}_"), desc.epsilon()),
+                ] {
+                    let line = src
+                        .lines()
+                        .find(|l| l.split_whitespace().any(|w| w == name))
+                        .unwrap();
+                    let literal = line.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+                    assert_eq!(literal.parse::<f32>().unwrap(), value, "{name}");
+                }
+            }
+        }
+    }
+
+    // Constant names and values, from lines like `const uint RGB9E5_EXPONENT_BITS = 5u;`, with
+    // the `static const` of HLSL and `constant` of MSL.
+    pub fn parse_constants(source: &str) -> Vec<(String, f64)> {
+        source
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_start_matches("static ");
+                let line = line
+                    .strip_prefix("const ")
+                    .or_else(|| line.strip_prefix("constant "))?;
+                let (head, value) = line.split_once('=')?;
+                let name = head.split_whitespace().last()?;
+                let value = value.trim().trim_end_matches(';').trim_end_matches('u');
+                Some((name.to_string(), value.parse().ok()?))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shipped_constants_match_generated() {
+        for (format, desc) in Format::ALL.into_iter().zip(builtins()) {
+            for language in LANGUAGES {
+                let shipped = parse_constants(format.source(language));
+                let generated = parse_constants(&generate(&desc, language));
+                assert_eq!(shipped.len(), generated.len(), "{language:?} {format:?}");
+                for (name, value) in generated {
+                    assert!(
+                        shipped.contains(&(name.clone(), value)),
+                        "{language:?} {format:?} {name} {value}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_combines_with_shipped() {
+        // The generated helpers must be identical to the shipped ones, or combining would drop
+        // a different definition.
+        for language in LANGUAGES {
+            let generated = generate(&FormatDesc::xyz9e2(), language);
+            let generated_rgb = generate(
+                &FormatDesc::new("rgb7e4", &SharedExponentFormat::new(4, 7), false),
+                language,
+            );
+            let mut sources: Vec<&str> = Format::ALL.iter().map(|f| f.source(language)).collect();
+            sources.push(&generated);
+            sources.push(&generated_rgb);
+            let combined = combine_sources(&sources);
+            let shipped = shaders::combine(language, &Format::ALL);
+            for item in shaders::tests::items_of(&shipped) {
+                assert!(combined.contains(&item));
+            }
+            for helper in [
+                "floor_log2",
+                "is_sign_negative",
+                "nan_to_zero",
+                "abs_nan_to_zero",
+            ] {
+                let helper = if language == ShaderLanguage::Wgsl && helper == "floor_log2" {
+                    "floor_log2_"
+                } else {
+                    helper
+                };
+                assert_eq!(
+                    shaders::tests::function_count(&combined, helper),
+                    1,
+                    "{language:?} {helper}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_sources_validate() {
+        let mut descs = builtins();
+        descs.extend([
+            FormatDesc::new("rgb7e4", &SharedExponentFormat::new(4, 7), false),
+            FormatDesc::new("xyz10e5", &SharedExponentFormat::new(5, 10), true),
+            FormatDesc::new("xyz6e3", &SharedExponentFormat::new(3, 6), true)
+                .with_exp_bias(2)
+                .with_scale(0.5),
+        ]);
+        for desc in descs {
+            let source = format!(
+                "#version 450\n{}\nlayout(local_size_x = 1) in;\nvoid main() {{}}\n",
+                generate(&desc, ShaderLanguage::Glsl)
+            );
+            let module = naga::front::glsl::Frontend::default()
+                .parse(
+                    &naga::front::glsl::Options::from(naga::ShaderStage::Compute),
+                    &source,
+                )
+                .unwrap_or_else(|e| panic!("{} {e:?}", desc.name));
+            shaders::tests::validate(&module);
+
+            let module = naga::front::wgsl::parse_str(&generate(&desc, ShaderLanguage::Wgsl))
+                .unwrap_or_else(|e| panic!("{} {e:?}", desc.name));
+            shaders::tests::validate(&module);
+        }
+    }
+}
@@ -0,0 +1,675 @@
+#[cfg(any(feature = "alloc", test))]
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::parts::{Parts, PartsError};
+use crate::{rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+pub const GLSL_RGB9E5: &str = include_str!("glsl/rgb9e5.glsl");
+pub const GLSL_XYZ8E5: &str = include_str!("glsl/xyz8e5.glsl");
+pub const GLSL_XYZ9E2: &str = include_str!("glsl/xyz9e2.glsl");
+pub const GLSL_XYZ13E6: &str = include_str!("glsl/xyz13e6.glsl");
+pub const GLSL_XYZ14E3: &str = include_str!("glsl/xyz14e3.glsl");
+pub const GLSL_XYZ18E7: &str = include_str!("glsl/xyz18e7.glsl");
+
+// rgb9e5 in RGBA8 textures for GLSL ES 1.0 and WebGL 1, using float math only. This can't be
+// combined with the other GLSL sources.
+pub const GLSL_RGB9E5_UNORM8: &str = include_str!("glsl/rgb9e5_unorm8.glsl");
+
+pub const WGSL_RGB9E5: &str = include_str!("wgsl/rgb9e5.wgsl");
+pub const WGSL_XYZ8E5: &str = include_str!("wgsl/xyz8e5.wgsl");
+pub const WGSL_XYZ9E2: &str = include_str!("wgsl/xyz9e2.wgsl");
+pub const WGSL_XYZ13E6: &str = include_str!("wgsl/xyz13e6.wgsl");
+pub const WGSL_XYZ14E3: &str = include_str!("wgsl/xyz14e3.wgsl");
+pub const WGSL_XYZ18E7: &str = include_str!("wgsl/xyz18e7.wgsl");
+
+// naga_oil style modules, with `#define_import_path shared_exponent_formats::rgb9e5` etc. The
+// helpers are in `shared_exponent_formats::common`, which has to be added to the composer first.
+pub const WGSL_MODULE_COMMON: &str = include_str!("wgsl/modules/common.wgsl");
+pub const WGSL_MODULE_RGB9E5: &str = include_str!("wgsl/modules/rgb9e5.wgsl");
+pub const WGSL_MODULE_XYZ8E5: &str = include_str!("wgsl/modules/xyz8e5.wgsl");
+pub const WGSL_MODULE_XYZ9E2: &str = include_str!("wgsl/modules/xyz9e2.wgsl");
+pub const WGSL_MODULE_XYZ13E6: &str = include_str!("wgsl/modules/xyz13e6.wgsl");
+pub const WGSL_MODULE_XYZ14E3: &str = include_str!("wgsl/modules/xyz14e3.wgsl");
+pub const WGSL_MODULE_XYZ18E7: &str = include_str!("wgsl/modules/xyz18e7.wgsl");
+
+// GLSL with include guards around each file and each shared helper, so any combination of
+// formats can be concatenated or #included into one shader.
+pub const GLSL_GUARDED_RGB9E5: &str = include_str!("glsl/guarded/rgb9e5.glsl");
+pub const GLSL_GUARDED_XYZ8E5: &str = include_str!("glsl/guarded/xyz8e5.glsl");
+pub const GLSL_GUARDED_XYZ9E2: &str = include_str!("glsl/guarded/xyz9e2.glsl");
+pub const GLSL_GUARDED_XYZ13E6: &str = include_str!("glsl/guarded/xyz13e6.glsl");
+pub const GLSL_GUARDED_XYZ14E3: &str = include_str!("glsl/guarded/xyz14e3.glsl");
+pub const GLSL_GUARDED_XYZ18E7: &str = include_str!("glsl/guarded/xyz18e7.glsl");
+
+pub const HLSL_RGB9E5: &str = include_str!("hlsl/rgb9e5.hlsl");
+pub const HLSL_XYZ8E5: &str = include_str!("hlsl/xyz8e5.hlsl");
+pub const HLSL_XYZ9E2: &str = include_str!("hlsl/xyz9e2.hlsl");
+pub const HLSL_XYZ13E6: &str = include_str!("hlsl/xyz13e6.hlsl");
+pub const HLSL_XYZ14E3: &str = include_str!("hlsl/xyz14e3.hlsl");
+pub const HLSL_XYZ18E7: &str = include_str!("hlsl/xyz18e7.hlsl");
+
+pub const MSL_RGB9E5: &str = include_str!("msl/rgb9e5.metal");
+pub const MSL_XYZ8E5: &str = include_str!("msl/xyz8e5.metal");
+pub const MSL_XYZ9E2: &str = include_str!("msl/xyz9e2.metal");
+pub const MSL_XYZ13E6: &str = include_str!("msl/xyz13e6.metal");
+pub const MSL_XYZ14E3: &str = include_str!("msl/xyz14e3.metal");
+pub const MSL_XYZ18E7: &str = include_str!("msl/xyz18e7.metal");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderLanguage {
+    Glsl,
+    Wgsl,
+    Hlsl,
+    Msl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Rgb9e5,
+    Xyz8e5,
+    Xyz9e2,
+    Xyz13e6,
+    Xyz14e3,
+    Xyz18e7,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Rgb9e5,
+        Format::Xyz8e5,
+        Format::Xyz9e2,
+        Format::Xyz13e6,
+        Format::Xyz14e3,
+        Format::Xyz18e7,
+    ];
+
+    pub fn source(self, language: ShaderLanguage) -> &'static str {
+        match (language, self) {
+            (ShaderLanguage::Glsl, Format::Rgb9e5) => GLSL_RGB9E5,
+            (ShaderLanguage::Glsl, Format::Xyz8e5) => GLSL_XYZ8E5,
+            (ShaderLanguage::Glsl, Format::Xyz9e2) => GLSL_XYZ9E2,
+            (ShaderLanguage::Glsl, Format::Xyz13e6) => GLSL_XYZ13E6,
+            (ShaderLanguage::Glsl, Format::Xyz14e3) => GLSL_XYZ14E3,
+            (ShaderLanguage::Glsl, Format::Xyz18e7) => GLSL_XYZ18E7,
+            (ShaderLanguage::Wgsl, Format::Rgb9e5) => WGSL_RGB9E5,
+            (ShaderLanguage::Wgsl, Format::Xyz8e5) => WGSL_XYZ8E5,
+            (ShaderLanguage::Wgsl, Format::Xyz9e2) => WGSL_XYZ9E2,
+            (ShaderLanguage::Wgsl, Format::Xyz13e6) => WGSL_XYZ13E6,
+            (ShaderLanguage::Wgsl, Format::Xyz14e3) => WGSL_XYZ14E3,
+            (ShaderLanguage::Wgsl, Format::Xyz18e7) => WGSL_XYZ18E7,
+            (ShaderLanguage::Hlsl, Format::Rgb9e5) => HLSL_RGB9E5,
+            (ShaderLanguage::Hlsl, Format::Xyz8e5) => HLSL_XYZ8E5,
+            (ShaderLanguage::Hlsl, Format::Xyz9e2) => HLSL_XYZ9E2,
+            (ShaderLanguage::Hlsl, Format::Xyz13e6) => HLSL_XYZ13E6,
+            (ShaderLanguage::Hlsl, Format::Xyz14e3) => HLSL_XYZ14E3,
+            (ShaderLanguage::Hlsl, Format::Xyz18e7) => HLSL_XYZ18E7,
+            (ShaderLanguage::Msl, Format::Rgb9e5) => MSL_RGB9E5,
+            (ShaderLanguage::Msl, Format::Xyz8e5) => MSL_XYZ8E5,
+            (ShaderLanguage::Msl, Format::Xyz9e2) => MSL_XYZ9E2,
+            (ShaderLanguage::Msl, Format::Xyz13e6) => MSL_XYZ13E6,
+            (ShaderLanguage::Msl, Format::Xyz14e3) => MSL_XYZ14E3,
+            (ShaderLanguage::Msl, Format::Xyz18e7) => MSL_XYZ18E7,
+        }
+    }
+
+    // The naga_oil module for the format, which imports `WGSL_MODULE_COMMON`.
+    pub fn wgsl_module(self) -> &'static str {
+        match self {
+            Format::Rgb9e5 => WGSL_MODULE_RGB9E5,
+            Format::Xyz8e5 => WGSL_MODULE_XYZ8E5,
+            Format::Xyz9e2 => WGSL_MODULE_XYZ9E2,
+            Format::Xyz13e6 => WGSL_MODULE_XYZ13E6,
+            Format::Xyz14e3 => WGSL_MODULE_XYZ14E3,
+            Format::Xyz18e7 => WGSL_MODULE_XYZ18E7,
+        }
+    }
+
+    pub fn glsl_guarded(self) -> &'static str {
+        match self {
+            Format::Rgb9e5 => GLSL_GUARDED_RGB9E5,
+            Format::Xyz8e5 => GLSL_GUARDED_XYZ8E5,
+            Format::Xyz9e2 => GLSL_GUARDED_XYZ9E2,
+            Format::Xyz13e6 => GLSL_GUARDED_XYZ13E6,
+            Format::Xyz14e3 => GLSL_GUARDED_XYZ14E3,
+            Format::Xyz18e7 => GLSL_GUARDED_XYZ18E7,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Rgb9e5 => "rgb9e5",
+            Format::Xyz8e5 => "xyz8e5",
+            Format::Xyz9e2 => "xyz9e2",
+            Format::Xyz13e6 => "xyz13e6",
+            Format::Xyz14e3 => "xyz14e3",
+            Format::Xyz18e7 => "xyz18e7",
+        }
+    }
+
+    // Every format through one interface, for tools that pick the format at runtime. Values are
+    // the words of the format's texture format from `Format::gpu_format`: the 32 bit formats use
+    // the first word and leave the second 0, the u16 of xyz13e6 and xyz14e3 is the low half of
+    // the second word.
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        Format::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    // The size of a value stored tightly.
+    pub const fn bytes(self) -> u8 {
+        match self {
+            Format::Rgb9e5 => rgb9e5::BYTES,
+            Format::Xyz8e5 => xyz8e5::BYTES,
+            Format::Xyz9e2 => xyz9e2::BYTES,
+            Format::Xyz13e6 => xyz13e6::BYTES,
+            Format::Xyz14e3 => xyz14e3::BYTES,
+            Format::Xyz18e7 => xyz18e7::BYTES,
+        }
+    }
+
+    pub const fn signed(self) -> bool {
+        match self {
+            Format::Rgb9e5 => rgb9e5::SIGNED,
+            Format::Xyz8e5 => xyz8e5::SIGNED,
+            Format::Xyz9e2 => xyz9e2::SIGNED,
+            Format::Xyz13e6 => xyz13e6::SIGNED,
+            Format::Xyz14e3 => xyz14e3::SIGNED,
+            Format::Xyz18e7 => xyz18e7::SIGNED,
+        }
+    }
+
+    pub const fn exponent_bits(self) -> u32 {
+        (match self {
+            Format::Rgb9e5 => rgb9e5::RGB9E5_EXPONENT_BITS,
+            Format::Xyz8e5 => xyz8e5::XYZ8E5_EXPONENT_BITS,
+            Format::Xyz9e2 => xyz9e2::XYZ9E2_EXPONENT_BITS,
+            Format::Xyz13e6 => xyz13e6::XYZ13E6_EXPONENT_BITS,
+            Format::Xyz14e3 => xyz14e3::XYZ14E3_EXPONENT_BITS,
+            Format::Xyz18e7 => xyz18e7::XYZ18E7_EXPONENT_BITS,
+        }) as u32
+    }
+
+    // The bits of each mantissa, without the sign.
+    pub const fn mantissa_bits(self) -> u32 {
+        (match self {
+            Format::Rgb9e5 => rgb9e5::RGB9E5_MANTISSA_BITS,
+            Format::Xyz8e5 => xyz8e5::XYZ8E5_MANTISSA_BITS,
+            Format::Xyz9e2 => xyz9e2::XYZ9E2_MANTISSA_BITS,
+            Format::Xyz13e6 => xyz13e6::XYZ13E6_MANTISSA_BITS,
+            Format::Xyz14e3 => xyz14e3::XYZ14E3_MANTISSA_BITS,
+            Format::Xyz18e7 => xyz18e7::XYZ18E7_MANTISSA_BITS,
+        }) as u32
+    }
+
+    pub const fn exp_bias(self) -> i32 {
+        match self {
+            Format::Rgb9e5 => rgb9e5::RGB9E5_EXP_BIAS,
+            Format::Xyz8e5 => xyz8e5::XYZ8E5_EXP_BIAS,
+            Format::Xyz9e2 => xyz9e2::XYZ9E2_EXP_BIAS,
+            Format::Xyz13e6 => xyz13e6::XYZ13E6_EXP_BIAS,
+            Format::Xyz14e3 => xyz14e3::XYZ14E3_EXP_BIAS,
+            Format::Xyz18e7 => xyz18e7::XYZ18E7_EXP_BIAS,
+        }
+    }
+
+    // Whether the largest mantissa is 1.0 instead of 1 - 2^-mantissa_bits, as in xyz9e2 and
+    // xyz14e3.
+    pub const fn normalized(self) -> bool {
+        matches!(self, Format::Xyz9e2 | Format::Xyz14e3)
+    }
+
+    // The largest component, which larger values are clamped to.
+    pub const fn max(self) -> f32 {
+        match self {
+            Format::Rgb9e5 => rgb9e5::MAX_RGB9E5,
+            Format::Xyz8e5 => xyz8e5::MAX_XYZ8E5,
+            Format::Xyz9e2 => xyz9e2::MAX_XYZ9E2,
+            Format::Xyz13e6 => xyz13e6::MAX_XYZ13E6,
+            Format::Xyz14e3 => xyz14e3::MAX_XYZ14E3,
+            Format::Xyz18e7 => xyz18e7::MAX_XYZ18E7,
+        }
+    }
+
+    pub const fn epsilon(self) -> f32 {
+        match self {
+            Format::Rgb9e5 => rgb9e5::EPSILON_RGB9E5,
+            Format::Xyz8e5 => xyz8e5::EPSILON_XYZ8E5,
+            Format::Xyz9e2 => xyz9e2::EPSILON_XYZ9E2,
+            Format::Xyz13e6 => xyz13e6::EPSILON_XYZ13E6,
+            Format::Xyz14e3 => xyz14e3::EPSILON_XYZ14E3,
+            Format::Xyz18e7 => xyz18e7::EPSILON_XYZ18E7,
+        }
+    }
+
+    pub const fn encode(self, xyz: [f32; 3]) -> [u32; 2] {
+        match self {
+            Format::Rgb9e5 => [rgb9e5::vec3_to_rgb9e5(xyz), 0],
+            Format::Xyz8e5 => [xyz8e5::vec3_to_xyz8e5(xyz), 0],
+            Format::Xyz9e2 => [xyz9e2::vec3_to_xyz9e2(xyz), 0],
+            Format::Xyz13e6 => {
+                let (a, b) = xyz13e6::vec3_to_xyz13e6(xyz);
+                [a, b as u32]
+            }
+            Format::Xyz14e3 => {
+                let (a, b) = xyz14e3::vec3_to_xyz14e3(xyz);
+                [a, b as u32]
+            }
+            Format::Xyz18e7 => {
+                let (a, b) = xyz18e7::vec3_to_xyz18e7(xyz);
+                [a, b]
+            }
+        }
+    }
+
+    // The bits past the format in the second word are ignored.
+    pub const fn decode(self, v: [u32; 2]) -> [f32; 3] {
+        match self {
+            Format::Rgb9e5 => rgb9e5::rgb9e5_to_vec3(v[0]),
+            Format::Xyz8e5 => xyz8e5::xyz8e5_to_vec3(v[0]),
+            Format::Xyz9e2 => xyz9e2::xyz9e2_to_vec3(v[0]),
+            Format::Xyz13e6 => xyz13e6::xyz13e6_to_vec3((v[0], v[1] as u16)),
+            Format::Xyz14e3 => xyz14e3::xyz14e3_to_vec3((v[0], v[1] as u16)),
+            Format::Xyz18e7 => xyz18e7::xyz18e7_to_vec3((v[0], v[1])),
+        }
+    }
+
+    pub const fn decompose(self, v: [u32; 2]) -> Parts {
+        match self {
+            Format::Rgb9e5 => rgb9e5::decompose_rgb9e5(v[0]),
+            Format::Xyz8e5 => xyz8e5::decompose_xyz8e5(v[0]),
+            Format::Xyz9e2 => xyz9e2::decompose_xyz9e2(v[0]),
+            Format::Xyz13e6 => xyz13e6::decompose_xyz13e6((v[0], v[1] as u16)),
+            Format::Xyz14e3 => xyz14e3::decompose_xyz14e3((v[0], v[1] as u16)),
+            Format::Xyz18e7 => xyz18e7::decompose_xyz18e7((v[0], v[1])),
+        }
+    }
+
+    pub fn compose(self, parts: Parts) -> Result<[u32; 2], PartsError> {
+        Ok(match self {
+            Format::Rgb9e5 => [rgb9e5::compose_rgb9e5(parts)?, 0],
+            Format::Xyz8e5 => [xyz8e5::compose_xyz8e5(parts)?, 0],
+            Format::Xyz9e2 => [xyz9e2::compose_xyz9e2(parts)?, 0],
+            Format::Xyz13e6 => {
+                let (a, b) = xyz13e6::compose_xyz13e6(parts)?;
+                [a, b as u32]
+            }
+            Format::Xyz14e3 => {
+                let (a, b) = xyz14e3::compose_xyz14e3(parts)?;
+                [a, b as u32]
+            }
+            Format::Xyz18e7 => {
+                let (a, b) = xyz18e7::compose_xyz18e7(parts)?;
+                [a, b]
+            }
+        })
+    }
+
+    // The value as `bytes()` little endian bytes, as stored tightly. The rest of the array is 0.
+    pub fn to_le_bytes(self, v: [u32; 2]) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&v[0].to_le_bytes());
+        bytes[4..].copy_from_slice(&v[1].to_le_bytes());
+        bytes[self.bytes() as usize..].fill(0);
+        bytes
+    }
+
+    // The value from the first `bytes()` bytes, or None if there are fewer.
+    pub fn from_le_bytes(self, bytes: &[u8]) -> Option<[u32; 2]> {
+        let mut words = [0; 8];
+        let len = self.bytes() as usize;
+        words[..len].copy_from_slice(bytes.get(..len)?);
+        Some([
+            u32::from_le_bytes(words[..4].try_into().unwrap()),
+            u32::from_le_bytes(words[4..].try_into().unwrap()),
+        ])
+    }
+}
+
+// Splits a source into top level items separated by blank lines. Comments directly above an
+// item are kept with it.
+#[cfg(any(feature = "alloc", test))]
+fn items(source: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut depth = 0i32;
+    for line in source.lines() {
+        if depth == 0 && line.trim().is_empty() {
+            if !item.is_empty() {
+                items.push(core::mem::take(&mut item));
+            }
+            continue;
+        }
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+        item.push_str(line.trim_end());
+        item.push('\n');
+    }
+    if !item.is_empty() {
+        items.push(item);
+    }
+    items
+}
+
+// The name of the function defined by an item, if it is a function. Works for both
+// `uint bitfield_extract(uint value, ...) {` and `fn floor_log2_(x: f32) -> i32 {`.
+#[cfg(any(feature = "alloc", test))]
+fn function_name(item: &str) -> Option<&str> {
+    let line = item
+        .lines()
+        .find(|line| !line.trim_start().starts_with("//"))?;
+    if !line.trim_end().ends_with('{') {
+        return None;
+    }
+    let (head, _) = line.split_once('(')?;
+    head.split_whitespace().last()
+}
+
+// Combines the sources of several formats into one. Helper functions like `floor_log2` that
+// are defined by more than one format are only included once.
+#[cfg(any(feature = "alloc", test))]
+pub fn combine(language: ShaderLanguage, formats: &[Format]) -> String {
+    let mut sources = Vec::new();
+    for format in formats {
+        let source = format.source(language);
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    combine_sources(&sources)
+}
+
+// Like `combine`, for any sources written in the same style, such as the output of
+// `shader_gen::generate`. Only the first definition of each function is kept, and items that
+// repeat exactly, like the `#include <metal_stdlib>` at the top of the MSL files, are only
+// included once.
+#[cfg(any(feature = "alloc", test))]
+pub fn combine_sources(sources: &[&str]) -> String {
+    let mut out = String::new();
+    let mut functions = Vec::new();
+    let mut seen = Vec::new();
+    for source in sources {
+        for item in items(source) {
+            if let Some(name) = function_name(&item) {
+                if functions.iter().any(|f| f == name) {
+                    continue;
+                }
+                functions.push(name.to_string());
+            } else if seen.contains(&item) {
+                continue;
+            } else {
+                seen.push(item.clone());
+            }
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&item);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use std::collections::HashMap;
+
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+    use naga_oil::compose::{ComposableModuleDescriptor, Composer, NagaModuleDescriptor};
+
+    use super::*;
+
+    pub const LANGUAGES: [ShaderLanguage; 4] = [
+        ShaderLanguage::Glsl,
+        ShaderLanguage::Wgsl,
+        ShaderLanguage::Hlsl,
+        ShaderLanguage::Msl,
+    ];
+
+    pub fn items_of(source: &str) -> Vec<String> {
+        items(source)
+    }
+
+    // How many times a function with this name is defined in the source.
+    pub fn function_count(source: &str, name: &str) -> usize {
+        items(source)
+            .iter()
+            .filter(|item| function_name(item) == Some(name))
+            .count()
+    }
+
+    #[test]
+    fn test_values() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        for format in Format::ALL {
+            assert_eq!(Format::from_name(format.name()), Some(format));
+            assert!(format.bytes() as u32 <= format.gpu_format().stride);
+            // Every bit is a mantissa, a sign or the exponent.
+            let bits = format.exponent_bits() + 3 * format.mantissa_bits();
+            let sign_bits = if format.signed() { 3 } else { 0 };
+            assert_eq!(bits + sign_bits, format.bytes() as u32 * 8);
+            for _ in 0..10000 {
+                let xyz = [(); 3].map(|_| rng.gen_range(-10.0..10.0));
+                let v = format.encode(xyz);
+                let bytes = format.to_le_bytes(v);
+                assert_eq!(
+                    format.from_le_bytes(&bytes[..format.bytes() as usize]),
+                    Some(v)
+                );
+                assert_eq!(format.compose(format.decompose(v)), Ok(v));
+                // Within a few steps of the largest component, after clamping to the range.
+                let min = if format.signed() { -format.max() } else { 0.0 };
+                let clamped = xyz.map(|x| x.clamp(min, format.max()));
+                let largest = clamped.map(f32::abs).into_iter().fold(0.0, f32::max);
+                let decoded = format.decode(v);
+                for i in 0..3 {
+                    let error = (decoded[i] - clamped[i]).abs();
+                    assert!(error <= largest / 64.0, "{format:?} {xyz:?}");
+                }
+            }
+            assert_eq!(
+                format.decode(format.encode([f32::MAX; 3])),
+                [format.max(); 3]
+            );
+            assert!(format.epsilon() > 0.0 && format.epsilon() < format.max());
+        }
+        assert_eq!(Format::from_name("rgb9e6"), None);
+        assert_eq!(Format::Xyz13e6.from_le_bytes(&[0; 5]), None);
+    }
+
+    #[test]
+    fn test_shared_helpers_are_identical() {
+        // combine drops later definitions of a helper, which is only correct if they all match.
+        for language in LANGUAGES {
+            let mut seen = HashMap::new();
+            for format in Format::ALL {
+                for item in items(format.source(language)) {
+                    if let Some(name) = function_name(&item) {
+                        let first = seen.entry(name.to_string()).or_insert(item.clone());
+                        assert_eq!(first, &item, "{language:?} {format:?} {name}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        for language in LANGUAGES {
+            let all = combine(language, &Format::ALL);
+            let mut names = HashMap::new();
+            for item in items(&all) {
+                if let Some(name) = function_name(&item) {
+                    *names.entry(name.to_string()).or_insert(0) += 1;
+                }
+            }
+            for (name, count) in &names {
+                assert_eq!(*count, 1, "{language:?} {name}");
+            }
+            // Every format defines the same parts struct, so it is only kept once.
+            assert_eq!(
+                all.matches("struct SharedExponentParts").count(),
+                1,
+                "{language:?}"
+            );
+            for name in [
+                "floor_log2",
+                "vec3_to_rgb9e5",
+                "rgb9e5_to_vec3",
+                "vec3_to_xyz8e5",
+                "xyz8e5_to_vec3",
+                "vec3_to_xyz9e2",
+                "xyz9e2_to_vec3",
+                "vec3_to_xyz13e6",
+                "xyz13e6_to_vec3",
+                "vec3_to_xyz14e3",
+                "xyz14e3_to_vec3",
+                "vec3_to_xyz18e7",
+                "xyz18e7_to_vec3",
+            ] {
+                let name = match language {
+                    ShaderLanguage::Wgsl => format!("{name}_"),
+                    _ => name.to_string(),
+                };
+                assert!(names.contains_key(&name), "{language:?} {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_combine_single() {
+        for language in LANGUAGES {
+            for format in Format::ALL {
+                let combined = combine(language, &[format, format]);
+                assert_eq!(items(&combined), items(format.source(language)));
+            }
+        }
+    }
+
+    // Every non empty combination of formats.
+    fn format_subsets() -> Vec<Vec<Format>> {
+        (1..1u32 << Format::ALL.len())
+            .map(|mask| {
+                Format::ALL
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, f)| f)
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn validate(module: &naga::Module) {
+        Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(module)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_wgsl_modules_match_sources() {
+        let common = items(WGSL_MODULE_COMMON);
+        for format in Format::ALL {
+            // Each item is either in the format module, or a helper in the common module.
+            let module = items(format.wgsl_module());
+            for item in items(format.source(ShaderLanguage::Wgsl)) {
+                assert!(
+                    module.contains(&item) || common.contains(&item),
+                    "{format:?} {item}"
+                );
+            }
+            for item in module.iter().filter(|item| !item.starts_with('#')) {
+                assert!(items(format.source(ShaderLanguage::Wgsl)).contains(item));
+            }
+        }
+    }
+
+    #[test]
+    fn test_glsl_guarded_match_sources() {
+        for format in Format::ALL {
+            let without_guards: String = format
+                .glsl_guarded()
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .map(|line| format!("{line}\n"))
+                .collect();
+            assert_eq!(
+                items(&without_guards),
+                items(format.source(ShaderLanguage::Glsl)),
+                "{format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wgsl_modules_compose() {
+        let mut composer = Composer::default();
+        for (source, path) in [(WGSL_MODULE_COMMON, "common")]
+            .into_iter()
+            .chain(Format::ALL.map(|f| (f.wgsl_module(), f.name())))
+        {
+            composer
+                .add_composable_module(ComposableModuleDescriptor {
+                    source,
+                    file_path: &format!("{path}.wgsl"),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        for formats in format_subsets() {
+            let mut source = String::new();
+            let mut body = String::new();
+            for format in &formats {
+                let name = format.name();
+                source.push_str(&format!(
+                    "#import shared_exponent_formats::{name}::{{vec3_to_{name}_, {name}_to_vec3_}}\n"
+                ));
+                body.push_str(&format!(
+                    "    let {name} = {name}_to_vec3_(vec3_to_{name}_(vec3(1.0, -2.0, 3.0)));\n"
+                ));
+            }
+            source.push_str(&format!(
+                "\n@compute @workgroup_size(1)\nfn main() {{\n{body}}}\n"
+            ));
+            let module = composer
+                .make_naga_module(NagaModuleDescriptor {
+                    source: &source,
+                    file_path: "main.wgsl",
+                    ..Default::default()
+                })
+                .unwrap_or_else(|e| panic!("{formats:?} {}", e.emit_to_string(&composer)));
+            validate(&module);
+        }
+    }
+
+    #[test]
+    fn test_glsl_guarded_combine() {
+        for formats in format_subsets() {
+            // Each file twice, as if it was included by two other files.
+            let mut source = "#version 450\n".to_string();
+            for format in formats.iter().chain(&formats) {
+                source.push_str(format.glsl_guarded());
+            }
+            let mut body = String::new();
+            for format in &formats {
+                let name = format.name();
+                body.push_str(&format!(
+                    "    vec3 {name} = {name}_to_vec3(vec3_to_{name}(vec3(1.0, -2.0, 3.0)));\n"
+                ));
+            }
+            source.push_str(&format!(
+                "\nlayout(local_size_x = 1) in;\nvoid main() {{\n{body}}}\n"
+            ));
+            let module = naga::front::glsl::Frontend::default()
+                .parse(
+                    &naga::front::glsl::Options::from(naga::ShaderStage::Compute),
+                    &source,
+                )
+                .unwrap_or_else(|e| panic!("{formats:?} {e:?}"));
+            validate(&module);
+        }
+    }
+}
@@ -0,0 +1,531 @@
+// Conversions between the formats on the packed bits, using integer math only. Every value is
+// m * 2^(e - bias - mantissa_bits), or m / (2^mantissa_bits - 1) * 2^(e - bias) in the
+// normalized formats (xyz9e2 and xyz14e3) whose largest mantissa is 1.0. The exact value is
+// rounded to the target like the encoders round, half up, with the same choice of exponent and
+// saturation at the target's MAX. Negative values become 0.0 in rgb9e5.
+//
+// Between the formats without normalization this gives the same bits as encoding the decoded
+// value. The normalized formats decode to the nearest f32 of their exact value, so going through
+// f32 can differ from these in the last bit when the value is close to a rounding tie.
+
+use crate::parts::Parts;
+use crate::shaders::Format;
+use crate::{rgb9e5, xyz13e6, xyz14e3, xyz18e7, xyz8e5, xyz9e2};
+
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    exponent_bits: u32,
+    mantissa_bits: u32,
+    bias: i32,
+    signed: bool,
+    normalized: bool,
+}
+
+const fn layout(format: Format) -> Layout {
+    Layout {
+        exponent_bits: format.exponent_bits(),
+        mantissa_bits: format.mantissa_bits(),
+        bias: format.exp_bias(),
+        signed: format.signed(),
+        normalized: format.normalized(),
+    }
+}
+
+impl Layout {
+    // A mantissa of the format is worth 2^(e - shift) / divisor at exponent e.
+    const fn shift(self) -> i32 {
+        if self.normalized {
+            self.bias
+        } else {
+            self.bias + self.mantissa_bits as i32
+        }
+    }
+
+    const fn divisor(self) -> u64 {
+        if self.normalized {
+            (1 << self.mantissa_bits) - 1
+        } else {
+            1
+        }
+    }
+
+    const fn max_exponent(self) -> u32 {
+        (1 << self.exponent_bits) - 1
+    }
+
+    const fn max_mantissa(self) -> u64 {
+        (1 << self.mantissa_bits) - 1
+    }
+}
+
+// floor(log2(a / b)) for a and b above 0.
+const fn floor_log2_ratio(a: u64, b: u64) -> i32 {
+    let c = b.leading_zeros() as i32 - a.leading_zeros() as i32;
+    let below = if c >= 0 {
+        (a as u128) < (b as u128) << c
+    } else {
+        (a as u128) << -c < b as u128
+    };
+    c - below as i32
+}
+
+// a * 2^s / b rounded half up, and whether that was exact. Results that can't fit any mantissa
+// are u64::MAX.
+const fn round_scaled(a: u64, b: u64, s: i32) -> (u64, bool) {
+    if a == 0 {
+        (0, true)
+    } else if s > 40 {
+        (u64::MAX, false)
+    } else if s >= 0 {
+        let n = (a as u128) << s;
+        let b = b as u128;
+        (((2 * n + b) / (2 * b)) as u64, n % b == 0)
+    } else if s >= -80 {
+        let d = (b as u128) << -s;
+        let a = a as u128;
+        (((2 * a + d) / (2 * d)) as u64, a % d == 0)
+    } else {
+        (0, false)
+    }
+}
+
+// Whether every value of `from` converts to `to` exactly. The normalized formats only convert
+// exactly to themselves, as their mantissas are fractions of 2^M - 1.
+pub const fn is_lossless(from: Format, to: Format) -> bool {
+    let f = layout(from);
+    let t = layout(to);
+    if from as u32 == to as u32 {
+        return true;
+    }
+    if f.normalized || t.normalized || (f.signed && !t.signed) {
+        return false;
+    }
+    // As much precision, reaching as far down and as far up.
+    t.mantissa_bits >= f.mantissa_bits
+        && t.shift() >= f.shift()
+        && t.max_exponent() as i32 - t.bias >= f.max_exponent() as i32 - f.bias
+}
+
+// Converts the parts of a `from` value to the parts of the `to` value the encoder gives for it,
+// and whether the value is the same. -0.0 becoming 0.0 in rgb9e5 counts as the same.
+pub const fn transcode(from: Format, to: Format, parts: Parts) -> (Parts, bool) {
+    let f = layout(from);
+    let t = layout(to);
+
+    // m * 2^(c - e') / b is the value in mantissas of `to` at exponent e'.
+    let mut a = [0u64; 3];
+    let mut exact = true;
+    let mut i = 0;
+    while i < 3 {
+        a[i] = parts.mantissas[i] as u64 * t.divisor();
+        if parts.signs[i] && !t.signed {
+            exact = exact && a[i] == 0;
+            a[i] = 0;
+        }
+        i += 1;
+    }
+    let b = f.divisor();
+    let c = parts.exponent as i32 - f.shift() + t.shift();
+
+    let a_max = if a[0] > a[1] { a[0] } else { a[1] };
+    let a_max = if a_max > a[2] { a_max } else { a[2] };
+    let max_exponent = t.max_exponent() as i32;
+
+    // The exponent that puts the largest mantissa's top bit at the top of the mantissa bits,
+    // or one more if it rounds up past it, like the encoders.
+    let mut exponent = if a_max == 0 {
+        0
+    } else {
+        floor_log2_ratio(a_max, b) + c - t.mantissa_bits as i32 + 1
+    };
+    exponent = if exponent < 0 { 0 } else { exponent };
+    if exponent > max_exponent {
+        exponent = max_exponent;
+    } else if exponent < max_exponent && round_scaled(a_max, b, c - exponent).0 > t.max_mantissa() {
+        exponent += 1;
+    }
+
+    let mut mantissas = [0u32; 3];
+    let mut i = 0;
+    while i < 3 {
+        let (m, m_exact) = round_scaled(a[i], b, c - exponent);
+        // Past the range, saturate at MAX like the encoders clamp.
+        mantissas[i] = if m > t.max_mantissa() {
+            t.max_mantissa() as u32
+        } else {
+            m as u32
+        };
+        exact = exact && m_exact && m <= t.max_mantissa();
+        i += 1;
+    }
+
+    let signs = if t.signed { parts.signs } else { [false; 3] };
+    let parts = Parts {
+        exponent: exponent as u32,
+        mantissas,
+        signs,
+    };
+    (parts, exact)
+}
+
+#[inline]
+pub const fn rgb9e5_to_xyz8e5(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Rgb9e5, Format::Xyz8e5, rgb9e5::decompose_rgb9e5(v));
+    xyz8e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn rgb9e5_to_xyz9e2(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Rgb9e5, Format::Xyz9e2, rgb9e5::decompose_rgb9e5(v));
+    xyz9e2::pack_parts(parts)
+}
+
+#[inline]
+pub const fn rgb9e5_to_xyz13e6(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Rgb9e5, Format::Xyz13e6, rgb9e5::decompose_rgb9e5(v));
+    xyz13e6::pack_parts(parts)
+}
+
+#[inline]
+pub const fn rgb9e5_to_xyz14e3(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Rgb9e5, Format::Xyz14e3, rgb9e5::decompose_rgb9e5(v));
+    xyz14e3::pack_parts(parts)
+}
+
+#[inline]
+pub const fn rgb9e5_to_xyz18e7(v: u32) -> (u32, u32) {
+    let (parts, _) = transcode(Format::Rgb9e5, Format::Xyz18e7, rgb9e5::decompose_rgb9e5(v));
+    xyz18e7::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz8e5_to_rgb9e5(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Xyz8e5, Format::Rgb9e5, xyz8e5::decompose_xyz8e5(v));
+    rgb9e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz8e5_to_xyz9e2(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Xyz8e5, Format::Xyz9e2, xyz8e5::decompose_xyz8e5(v));
+    xyz9e2::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz8e5_to_xyz13e6(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Xyz8e5, Format::Xyz13e6, xyz8e5::decompose_xyz8e5(v));
+    xyz13e6::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz8e5_to_xyz14e3(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Xyz8e5, Format::Xyz14e3, xyz8e5::decompose_xyz8e5(v));
+    xyz14e3::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz8e5_to_xyz18e7(v: u32) -> (u32, u32) {
+    let (parts, _) = transcode(Format::Xyz8e5, Format::Xyz18e7, xyz8e5::decompose_xyz8e5(v));
+    xyz18e7::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz9e2_to_rgb9e5(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Xyz9e2, Format::Rgb9e5, xyz9e2::decompose_xyz9e2(v));
+    rgb9e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz9e2_to_xyz8e5(v: u32) -> u32 {
+    let (parts, _) = transcode(Format::Xyz9e2, Format::Xyz8e5, xyz9e2::decompose_xyz9e2(v));
+    xyz8e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz9e2_to_xyz13e6(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Xyz9e2, Format::Xyz13e6, xyz9e2::decompose_xyz9e2(v));
+    xyz13e6::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz9e2_to_xyz14e3(v: u32) -> (u32, u16) {
+    let (parts, _) = transcode(Format::Xyz9e2, Format::Xyz14e3, xyz9e2::decompose_xyz9e2(v));
+    xyz14e3::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz9e2_to_xyz18e7(v: u32) -> (u32, u32) {
+    let (parts, _) = transcode(Format::Xyz9e2, Format::Xyz18e7, xyz9e2::decompose_xyz9e2(v));
+    xyz18e7::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz13e6_to_rgb9e5(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz13e6,
+        Format::Rgb9e5,
+        xyz13e6::decompose_xyz13e6(v),
+    );
+    rgb9e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz13e6_to_xyz8e5(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz13e6,
+        Format::Xyz8e5,
+        xyz13e6::decompose_xyz13e6(v),
+    );
+    xyz8e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz13e6_to_xyz9e2(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz13e6,
+        Format::Xyz9e2,
+        xyz13e6::decompose_xyz13e6(v),
+    );
+    xyz9e2::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz13e6_to_xyz14e3(v: (u32, u16)) -> (u32, u16) {
+    let (parts, _) = transcode(
+        Format::Xyz13e6,
+        Format::Xyz14e3,
+        xyz13e6::decompose_xyz13e6(v),
+    );
+    xyz14e3::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz13e6_to_xyz18e7(v: (u32, u16)) -> (u32, u32) {
+    let (parts, _) = transcode(
+        Format::Xyz13e6,
+        Format::Xyz18e7,
+        xyz13e6::decompose_xyz13e6(v),
+    );
+    xyz18e7::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz14e3_to_rgb9e5(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz14e3,
+        Format::Rgb9e5,
+        xyz14e3::decompose_xyz14e3(v),
+    );
+    rgb9e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz14e3_to_xyz8e5(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz14e3,
+        Format::Xyz8e5,
+        xyz14e3::decompose_xyz14e3(v),
+    );
+    xyz8e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz14e3_to_xyz9e2(v: (u32, u16)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz14e3,
+        Format::Xyz9e2,
+        xyz14e3::decompose_xyz14e3(v),
+    );
+    xyz9e2::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz14e3_to_xyz13e6(v: (u32, u16)) -> (u32, u16) {
+    let (parts, _) = transcode(
+        Format::Xyz14e3,
+        Format::Xyz13e6,
+        xyz14e3::decompose_xyz14e3(v),
+    );
+    xyz13e6::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz14e3_to_xyz18e7(v: (u32, u16)) -> (u32, u32) {
+    let (parts, _) = transcode(
+        Format::Xyz14e3,
+        Format::Xyz18e7,
+        xyz14e3::decompose_xyz14e3(v),
+    );
+    xyz18e7::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz18e7_to_rgb9e5(v: (u32, u32)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz18e7,
+        Format::Rgb9e5,
+        xyz18e7::decompose_xyz18e7(v),
+    );
+    rgb9e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz18e7_to_xyz8e5(v: (u32, u32)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz18e7,
+        Format::Xyz8e5,
+        xyz18e7::decompose_xyz18e7(v),
+    );
+    xyz8e5::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz18e7_to_xyz9e2(v: (u32, u32)) -> u32 {
+    let (parts, _) = transcode(
+        Format::Xyz18e7,
+        Format::Xyz9e2,
+        xyz18e7::decompose_xyz18e7(v),
+    );
+    xyz9e2::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz18e7_to_xyz13e6(v: (u32, u32)) -> (u32, u16) {
+    let (parts, _) = transcode(
+        Format::Xyz18e7,
+        Format::Xyz13e6,
+        xyz18e7::decompose_xyz18e7(v),
+    );
+    xyz13e6::pack_parts(parts)
+}
+
+#[inline]
+pub const fn xyz18e7_to_xyz14e3(v: (u32, u32)) -> (u32, u16) {
+    let (parts, _) = transcode(
+        Format::Xyz18e7,
+        Format::Xyz14e3,
+        xyz18e7::decompose_xyz18e7(v),
+    );
+    xyz14e3::pack_parts(parts)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use rand::Rng;
+
+    use super::*;
+
+    // Random codes, and values of the `from` format encoded from values around the range of
+    // `to`, so that each pair sees saturation, rounding to zero and everything in between.
+    fn test_codes(from: Format, to: Format) -> Vec<[u32; 2]> {
+        let mut rng = rand::thread_rng();
+        let mut codes = Vec::new();
+        let to_max = to.decode(to.encode([f32::MAX; 3]))[0];
+        for _ in 0..100000 {
+            codes.push([rng.gen(), rng.gen()]);
+            let scale = to_max * 2.0f32.powi(rng.gen_range(-80..4));
+            let xyz = [0; 3].map(|_| rng.gen_range(-1.0..1.0) * scale);
+            codes.push(from.encode(xyz));
+        }
+        codes
+    }
+
+    // The exact value of parts in f64: m * 2^(e - bias - M), or m / (2^M - 1) * 2^(e - bias)
+    // in the normalized formats. The division rounds correctly, so equal values compare equal
+    // across formats and different ones stay apart.
+    fn exact_value(format: Format, parts: Parts) -> [f64; 3] {
+        let m_bits = format.mantissa_bits() as i32;
+        let e = parts.exponent as i32 - format.exp_bias();
+        std::array::from_fn(|i| {
+            let m = parts.mantissas[i] as f64;
+            let v = if format.normalized() {
+                m / ((1u64 << m_bits) - 1) as f64 * 2.0f64.powi(e)
+            } else {
+                m * 2.0f64.powi(e - m_bits)
+            };
+            if parts.signs[i] {
+                -v
+            } else {
+                v
+            }
+        })
+    }
+
+    #[test]
+    fn test_matches_encoders() {
+        for from in Format::ALL {
+            for to in Format::ALL {
+                let smallest = Parts {
+                    mantissas: [1, 0, 0],
+                    ..Parts::default()
+                };
+                let min_step = to.decode(to.compose(smallest).unwrap())[0];
+                for v in test_codes(from, to) {
+                    let (parts, exact) = transcode(from, to, from.decompose(v));
+                    let same = exact_value(to, parts) == exact_value(from, from.decompose(v));
+                    assert_eq!(exact, same, "{from:?} {to:?} {v:?}");
+                    let expected = to.encode(from.decode(v));
+                    if from.normalized() || to.normalized() {
+                        // Going through f32 can round the other way near ties, by one step of
+                        // the largest mantissa.
+                        let got = to.decode(to.compose(parts).unwrap());
+                        let want = to.decode(expected);
+                        let step = want.map(f32::abs).into_iter().fold(0.0, f32::max)
+                            * 2.0f32.powi(1 - layout(to).mantissa_bits as i32);
+                        for i in 0..3 {
+                            let diff = (got[i] - want[i]).abs();
+                            assert!(diff <= step.max(min_step), "{from:?} {to:?} {v:?}");
+                        }
+                    } else {
+                        assert_eq!(to.compose(parts), Ok(expected), "{from:?} {to:?} {v:?}");
+                        let same = to.decode(expected) == from.decode(v);
+                        assert_eq!(exact, same, "{from:?} {to:?} {v:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lossless() {
+        for from in Format::ALL {
+            for to in Format::ALL {
+                let lossless = is_lossless(from, to);
+                let codes = test_codes(from, to);
+                let mut all_exact = true;
+                for &v in &codes {
+                    let (parts, exact) = transcode(from, to, from.decompose(v));
+                    all_exact &= exact;
+                    if lossless {
+                        assert!(exact, "{from:?} {to:?} {v:?}");
+                        let back = from.compose(transcode(to, from, parts).0).unwrap();
+                        assert_eq!(
+                            from.decode(back).map(f32::to_bits),
+                            from.decode(v).map(f32::to_bits)
+                        );
+                    }
+                }
+                assert_eq!(all_exact, lossless, "{from:?} {to:?}");
+            }
+        }
+        assert!(is_lossless(Format::Rgb9e5, Format::Xyz13e6));
+        assert!(is_lossless(Format::Xyz8e5, Format::Xyz13e6));
+        assert!(is_lossless(Format::Xyz13e6, Format::Xyz18e7));
+        assert!(!is_lossless(Format::Xyz8e5, Format::Rgb9e5));
+        assert!(!is_lossless(Format::Rgb9e5, Format::Xyz8e5));
+        assert!(!is_lossless(Format::Xyz9e2, Format::Xyz14e3));
+    }
+
+    #[test]
+    fn test_pairs() {
+        let xyz = [1.0, -0.5, 3.0];
+        let v = xyz8e5::vec3_to_xyz8e5(xyz);
+        assert_eq!(xyz8e5_to_xyz13e6(v), xyz13e6::vec3_to_xyz13e6(xyz));
+        assert_eq!(xyz8e5_to_rgb9e5(v), rgb9e5::vec3_to_rgb9e5(xyz));
+        assert_eq!(xyz13e6::xyz13e6_to_vec3(xyz8e5_to_xyz13e6(v)), xyz);
+        assert_eq!(
+            xyz18e7_to_xyz8e5(xyz18e7::vec3_to_xyz18e7([1e10, 1.0, -1e-10])),
+            xyz8e5::vec3_to_xyz8e5([1e10, 1.0, -1e-10])
+        );
+    }
+}
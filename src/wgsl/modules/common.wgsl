@@ -0,0 +1,86 @@
+#define_import_path shared_exponent_formats::common
+
+fn floor_log2_(x: f32) -> i32 {
+    let f = bitcast<u32>(x);
+    let biasedexponent = (f & 0x7F800000u) >> 23u;
+    return i32(biasedexponent) - 127;
+}
+
+fn is_sign_negative(v: f32) -> u32 {
+    return (bitcast<u32>(v) >> 31u) & 1u;
+}
+
+// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, (b & vec3(0x7FFFFFFFu)) <= vec3(0x7F800000u)));
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn abs_nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v) & vec3(0x7FFFFFFFu);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, b <= vec3(0x7F800000u)));
+}
+
+// Arrays of 48 bit values can be stored tightly in an array<u32>, with two values in every three
+// words. Value `index` is in words packed48_word(index) and packed48_word(index) + 1u, so an
+// array with an odd length needs one word of padding. Storage buffers can't be passed to
+// functions, so the helpers take and return these two words.
+fn packed48_word(index: u32) -> u32 {
+    return (index * 3u) >> 1u;
+}
+
+fn packed48_load(lo: u32, hi: u32, index: u32) -> vec2<u32> {
+    if ((index & 1u) == 0u) {
+        return vec2(lo, hi & 0xFFFFu);
+    }
+    return vec2((lo >> 16u) | (hi << 16u), hi >> 16u);
+}
+
+// The two words with value `index` replaced. Neighbouring values share a word, so they must not
+// be stored concurrently.
+fn packed48_store(lo: u32, hi: u32, index: u32, v: vec2<u32>) -> vec2<u32> {
+    if ((index & 1u) == 0u) {
+        return vec2(v.x, (hi & 0xFFFF0000u) | (v.y & 0xFFFFu));
+    }
+    return vec2((lo & 0xFFFFu) | (v.x << 16u), (v.x >> 16u) | (v.y << 16u));
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    exponent: u32,
+    mantissas: vec3<u32>,
+    signs: vec3<u32>,
+}
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+fn ldexp_parts(parts: SharedExponentParts, k: i32, exponent_bits: u32, mantissa_bits: u32) -> SharedExponentParts {
+    let max_exponent = (1u << exponent_bits) - 1u;
+    let max_mantissa = (1u << mantissa_bits) - 1u;
+    var m = parts.mantissas;
+    var e = i32(parts.exponent) + clamp(k, -256, 256);
+    if (e > i32(max_exponent)) {
+        let d = min(u32(e) - max_exponent, mantissa_bits);
+        let over = vec3<u32>(m > vec3(max_mantissa >> d));
+        m = (m << vec3(d)) * (1u - over) + max_mantissa * over;
+        e = i32(max_exponent);
+    } else if (e < 0) {
+        let s = u32(min(-e, 31));
+        m = (m + vec3((1u << s) >> 1u)) >> vec3(s);
+        e = 0;
+    }
+
+    let any_mantissa = m.x | m.y | m.z;
+    var shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(u32(e), mantissa_bits - 1u - u32(floor_log2_(f32(any_mantissa))));
+    }
+    return SharedExponentParts(u32(e) - shift, m << vec3(shift), parts.signs);
+}
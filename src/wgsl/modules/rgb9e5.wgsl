@@ -0,0 +1,63 @@
+#define_import_path shared_exponent_formats::rgb9e5
+
+#import shared_exponent_formats::common::{floor_log2_, nan_to_zero, SharedExponentParts, ldexp_parts}
+
+const RGB9E5_EXPONENT_BITS        = 5u;
+const RGB9E5_MANTISSA_BITS        = 9;
+const RGB9E5_MANTISSA_BITSU       = 9u;
+const RGB9E5_EXP_BIAS             = 15;
+const RGB9E5_MAX_VALID_BIASED_EXP = 31u;
+
+const MAX_RGB9E5_EXP              = 16u;
+const RGB9E5_MANTISSA_VALUES      = 512;
+const MAX_RGB9E5_MANTISSA         = 511;
+const MAX_RGB9E5_MANTISSAU        = 511u;
+const MAX_RGB9E5_                 = 65408.0;
+const EPSILON_RGB9E5_             = 0.000000059604645;
+
+// https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_rgb9e5_(rgb_in: vec3<f32>) -> u32 {
+    let rgb = clamp(nan_to_zero(rgb_in), vec3(0.0), vec3(MAX_RGB9E5_));
+
+    let maxrgb = max(rgb.r, max(rgb.g, rgb.b));
+    var exp_shared = max(-RGB9E5_EXP_BIAS - 1, floor_log2_(maxrgb)) + 1 + RGB9E5_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxrgb / denom + 0.5));
+    if (maxm == RGB9E5_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let n = vec3<u32>(floor(rgb / denom + 0.5));
+
+    return (u32(exp_shared) << 27u) | (n.b << 18u) | (n.g << 9u) | (n.r << 0u);
+}
+
+fn rgb9e5_to_vec3_(v: u32) -> vec3<f32> {
+    let exponent = i32(extractBits(v, 27u, RGB9E5_EXPONENT_BITS)) - RGB9E5_EXP_BIAS - RGB9E5_MANTISSA_BITS;
+    let scale = exp2(f32(exponent));
+
+    return vec3(
+        f32(extractBits(v, 0u, RGB9E5_MANTISSA_BITSU)),
+        f32(extractBits(v, 9u, RGB9E5_MANTISSA_BITSU)),
+        f32(extractBits(v, 18u, RGB9E5_MANTISSA_BITSU))
+    ) * scale;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_rgb9e5_(v: u32) -> SharedExponentParts {
+    let m = vec3(extractBits(v, 0u, RGB9E5_MANTISSA_BITSU), extractBits(v, 9u, RGB9E5_MANTISSA_BITSU), extractBits(v, 18u, RGB9E5_MANTISSA_BITSU));
+    return SharedExponentParts(extractBits(v, 27u, RGB9E5_EXPONENT_BITS), m, vec3(0u));
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_rgb9e5 checks them instead.
+fn compose_rgb9e5_(parts: SharedExponentParts) -> u32 {
+    let m = parts.mantissas & vec3(MAX_RGB9E5_MANTISSAU);
+    return ((parts.exponent & RGB9E5_MAX_VALID_BIASED_EXP) << 27u) | (m.z << 18u) | (m.y << 9u) | (m.x << 0u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_rgb9e5_ of the scaled value.
+fn ldexp_rgb9e5_(v: u32, k: i32) -> u32 {
+    return compose_rgb9e5_(ldexp_parts(decompose_rgb9e5_(v), k, RGB9E5_EXPONENT_BITS, RGB9E5_MANTISSA_BITSU));
+}
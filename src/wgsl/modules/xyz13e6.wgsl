@@ -0,0 +1,92 @@
+#define_import_path shared_exponent_formats::xyz13e6
+
+#import shared_exponent_formats::common::{floor_log2_, is_sign_negative, abs_nan_to_zero, packed48_load, packed48_store, SharedExponentParts, ldexp_parts}
+
+const XYZ13E6_EXPONENT_BITS        = 6u;
+const XYZ13E6_MANTISSA_BITS        = 13;
+const XYZ13E6_MANTISSA_BITSU       = 13u;
+const XYZ13E6_EXP_BIAS             = 31;
+const XYZ13E6_MAX_VALID_BIASED_EXP = 63u;
+
+const MAX_XYZ13E6_EXP              = 32u;
+const XYZ13E6_MANTISSA_VALUES      = 8192;
+const MAX_XYZ13E6_MANTISSA         = 8191;
+const MAX_XYZ13E6_MANTISSAU        = 8191u;
+const MAX_XYZ13E6_                 = 4294443000.0;
+const EPSILON_XYZ13E6_             = 0.00000000000005684342;
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_xyz13e6_(xyz_in: vec3<f32>) -> vec2<u32> {
+    let xsign = is_sign_negative(xyz_in.x);
+    let ysign = is_sign_negative(xyz_in.y);
+    let zsign = is_sign_negative(xyz_in.z);
+
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ13E6_));
+
+    let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    var exp_shared = max(-XYZ13E6_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ13E6_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ13E6_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = vec3<u32>(floor(xyz / denom + 0.5));
+
+    let out_a = (u32(exp_shared) << 26u) | (s.y << 13u) | (s.x << 0u);
+    let out_b = (zsign << 15u) | (ysign << 14u) | (xsign << 13u) | (s.z << 0u);
+
+    return vec2(out_a, out_b);
+}
+
+fn xyz13e6_to_vec3_(v: vec2<u32>) -> vec3<f32> {
+    let exponent = i32(extractBits(v[0], 26u, XYZ13E6_EXPONENT_BITS)) - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS;
+    let scale = exp2(f32(exponent));
+
+    // extractBits(v[1], 13u, 1u) << 31u is extracting the sign bit and
+    // shifts it over to the corresponding IEEE 754 sign location
+    return vec3(
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[0],  0u, XYZ13E6_MANTISSA_BITSU))) | extractBits(v[1], 13u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[0], 13u, XYZ13E6_MANTISSA_BITSU))) | extractBits(v[1], 14u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[1],  0u, XYZ13E6_MANTISSA_BITSU))) | extractBits(v[1], 15u, 1u) << 31u),
+    ) * scale;
+}
+
+fn load_xyz13e6_(lo: u32, hi: u32, index: u32) -> vec3<f32> {
+    return xyz13e6_to_vec3_(packed48_load(lo, hi, index));
+}
+
+fn store_xyz13e6_(lo: u32, hi: u32, index: u32, xyz: vec3<f32>) -> vec2<u32> {
+    return packed48_store(lo, hi, index, vec3_to_xyz13e6_(xyz));
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz13e6_(v: vec2<u32>) -> SharedExponentParts {
+    let m = vec3(extractBits(v[0], 0u, XYZ13E6_MANTISSA_BITSU), extractBits(v[0], 13u, XYZ13E6_MANTISSA_BITSU), extractBits(v[1], 0u, XYZ13E6_MANTISSA_BITSU));
+    let s = vec3(extractBits(v[1], 13u, 1u), extractBits(v[1], 14u, 1u), extractBits(v[1], 15u, 1u));
+    return SharedExponentParts(extractBits(v[0], 26u, XYZ13E6_EXPONENT_BITS), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz13e6 checks them instead.
+fn compose_xyz13e6_(parts: SharedExponentParts) -> vec2<u32> {
+    let e = parts.exponent & XYZ13E6_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas & vec3(MAX_XYZ13E6_MANTISSAU);
+    let s = parts.signs & vec3(1u);
+    return vec2((e << 26u) | (m.y << 13u) | (m.x << 0u), (s.z << 15u) | (s.y << 14u) | (s.x << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz13e6_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0], v[1] ^ 0x0000E000u);
+}
+
+fn abs_xyz13e6_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0], v[1] & ~0x0000E000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz13e6_ of the scaled value.
+fn ldexp_xyz13e6_(v: vec2<u32>, k: i32) -> vec2<u32> {
+    return compose_xyz13e6_(ldexp_parts(decompose_xyz13e6_(v), k, XYZ13E6_EXPONENT_BITS, XYZ13E6_MANTISSA_BITSU));
+}
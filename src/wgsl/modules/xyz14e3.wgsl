@@ -0,0 +1,98 @@
+#define_import_path shared_exponent_formats::xyz14e3
+
+#import shared_exponent_formats::common::{floor_log2_, is_sign_negative, abs_nan_to_zero, packed48_load, packed48_store, SharedExponentParts, ldexp_parts}
+
+const XYZ14E3_EXPONENT_BITS        = 3u;
+const XYZ14E3_MANTISSA_BITS        = 14;
+const XYZ14E3_MANTISSA_BITSU       = 14u;
+const XYZ14E3_EXP_BIAS             = 3;
+const XYZ14E3_MAX_VALID_BIASED_EXP = 7u;
+
+const MAX_XYZ14E3_EXP              = 4u;
+const XYZ14E3_MANTISSA_VALUES      = 16384;
+const MAX_XYZ14E3_MANTISSA         = 16383;
+const MAX_XYZ14E3_MANTISSAU        = 16383u;
+const MAX_XYZ14E3_                 = 16.0;
+const EPSILON_XYZ14E3_             = 0.0000076293945;
+
+// MAX_XYZ14E3 would be 15.999023, this is used to scale to exactly 16.0
+const XYZ14E3_NORM_MULT            = 1.000061;
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_xyz14e3_(xyz_in: vec3<f32>) -> vec2<u32> {
+    let xsign = is_sign_negative(xyz_in.x);
+    let ysign = is_sign_negative(xyz_in.y);
+    let zsign = is_sign_negative(xyz_in.z);
+
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ14E3_)) / f32(XYZ14E3_NORM_MULT);
+
+    let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    var exp_shared = max(-XYZ14E3_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ14E3_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ14E3_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = vec3<u32>(floor(xyz / denom + 0.5));
+    let e = u32(exp_shared);
+
+    // The lowest exponent bit is stored in the first word, the other two in the second.
+    let out_a = ((e & 1u) << 31u) | (zsign << 30u) | (ysign << 29u) | (xsign << 28u) | (s.y << 14u) | (s.x << 0u);
+    let out_b = ((e & 6u) << 13u) | (s.z << 0u);
+
+    return vec2(out_a, out_b);
+}
+
+fn xyz14e3_to_vec3_(v: vec2<u32>) -> vec3<f32> {
+    let biased_exp = extractBits(v[0], 31u, 1u) | (extractBits(v[1], 14u, 2u) << 1u);
+    let exponent = i32(biased_exp) - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS;
+    let scale = exp2(f32(exponent)) * f32(XYZ14E3_NORM_MULT);
+
+    // extractBits(v[0], 28u, 1u) << 31u is extracting the sign bit and
+    // shifts it over to the corresponding IEEE 754 sign location
+    return vec3(
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[0],  0u, XYZ14E3_MANTISSA_BITSU))) | extractBits(v[0], 28u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[0], 14u, XYZ14E3_MANTISSA_BITSU))) | extractBits(v[0], 29u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(extractBits(v[1],  0u, XYZ14E3_MANTISSA_BITSU))) | extractBits(v[0], 30u, 1u) << 31u),
+    ) * scale;
+}
+
+fn load_xyz14e3_(lo: u32, hi: u32, index: u32) -> vec3<f32> {
+    return xyz14e3_to_vec3_(packed48_load(lo, hi, index));
+}
+
+fn store_xyz14e3_(lo: u32, hi: u32, index: u32, xyz: vec3<f32>) -> vec2<u32> {
+    return packed48_store(lo, hi, index, vec3_to_xyz14e3_(xyz));
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz14e3_(v: vec2<u32>) -> SharedExponentParts {
+    let m = vec3(extractBits(v[0], 0u, XYZ14E3_MANTISSA_BITSU), extractBits(v[0], 14u, XYZ14E3_MANTISSA_BITSU), extractBits(v[1], 0u, XYZ14E3_MANTISSA_BITSU));
+    let s = vec3(extractBits(v[0], 28u, 1u), extractBits(v[0], 29u, 1u), extractBits(v[0], 30u, 1u));
+    return SharedExponentParts(extractBits(v[0], 31u, 1u) | (extractBits(v[1], 14u, 2u) << 1u), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz14e3 checks them instead.
+fn compose_xyz14e3_(parts: SharedExponentParts) -> vec2<u32> {
+    let e = parts.exponent & XYZ14E3_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas & vec3(MAX_XYZ14E3_MANTISSAU);
+    let s = parts.signs & vec3(1u);
+    return vec2(((e & 1u) << 31u) | (s.z << 30u) | (s.y << 29u) | (s.x << 28u) | (m.y << 14u) | (m.x << 0u), ((e & 6u) << 13u) | (m.z << 0u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz14e3_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0] ^ 0x70000000u, v[1]);
+}
+
+fn abs_xyz14e3_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0] & ~0x70000000u, v[1]);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz14e3_ of the scaled value.
+fn ldexp_xyz14e3_(v: vec2<u32>, k: i32) -> vec2<u32> {
+    return compose_xyz14e3_(ldexp_parts(decompose_xyz14e3_(v), k, XYZ14E3_EXPONENT_BITS, XYZ14E3_MANTISSA_BITSU));
+}
@@ -0,0 +1,88 @@
+#define_import_path shared_exponent_formats::xyz18e7
+
+#import shared_exponent_formats::common::{floor_log2_, is_sign_negative, abs_nan_to_zero, SharedExponentParts, ldexp_parts}
+
+const XYZ18E7_EXPONENT_BITS        = 7u;
+const XYZ18E7_MANTISSA_BITS        = 18;
+const XYZ18E7_MANTISSA_BITSU       = 18u;
+const XYZ18E7_EXP_BIAS             = 63;
+const XYZ18E7_MAX_VALID_BIASED_EXP = 127u;
+
+const MAX_XYZ18E7_EXP              = 64u;
+const XYZ18E7_MANTISSA_VALUES      = 262144;
+const MAX_XYZ18E7_MANTISSA         = 262143;
+const MAX_XYZ18E7_MANTISSAU        = 262143u;
+const MAX_XYZ18E7_                 = 1.8446674e+19;
+const EPSILON_XYZ18E7_             = 4.135903e-25;
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_xyz18e7_(xyz_in: vec3<f32>) -> vec2<u32> {
+    let xsign = is_sign_negative(xyz_in.x);
+    let ysign = is_sign_negative(xyz_in.y);
+    let zsign = is_sign_negative(xyz_in.z);
+
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ18E7_));
+
+    let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    var exp_shared = max(-XYZ18E7_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ18E7_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ18E7_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = vec3<u32>(floor(xyz / denom + 0.5));
+
+    let out_a = (s.y << 18u) | (s.x << 0u);
+    let out_b = (u32(exp_shared) << 25u) | (zsign << 24u) | (ysign << 23u) | (xsign << 22u) | (s.z << 4u) | (s.y >> 14u);
+
+    return vec2(out_a, out_b);
+}
+
+fn xyz18e7_to_vec3_(v: vec2<u32>) -> vec3<f32> {
+    let exponent = i32(extractBits(v[1], 25u, XYZ18E7_EXPONENT_BITS)) - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS;
+    let scale = exp2(f32(exponent));
+
+
+    let xb = extractBits(v[0], 0u, XYZ18E7_MANTISSA_BITSU);
+    let yb = extractBits(v[0], 18u, XYZ18E7_MANTISSA_BITSU) | extractBits(v[1], 0u, 4u) << 14u;
+    let zb = extractBits(v[1], 4u, XYZ18E7_MANTISSA_BITSU);
+
+    // Extract the sign bits, then extractBits(v[1], 22u, 1u) << 31u shifts it over to the corresponding IEEE 754 sign location.
+    return vec3(
+        bitcast<f32>(bitcast<u32>(f32(xb)) | extractBits(v[1], 22u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(yb)) | extractBits(v[1], 23u, 1u) << 31u),
+        bitcast<f32>(bitcast<u32>(f32(zb)) | extractBits(v[1], 24u, 1u) << 31u),
+    ) * scale;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz18e7_(v: vec2<u32>) -> SharedExponentParts {
+    let m = vec3(extractBits(v[0], 0u, XYZ18E7_MANTISSA_BITSU), extractBits(v[0], 18u, 14u) | extractBits(v[1], 0u, 4u) << 14u, extractBits(v[1], 4u, XYZ18E7_MANTISSA_BITSU));
+    let s = vec3(extractBits(v[1], 22u, 1u), extractBits(v[1], 23u, 1u), extractBits(v[1], 24u, 1u));
+    return SharedExponentParts(extractBits(v[1], 25u, XYZ18E7_EXPONENT_BITS), m, s);
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz18e7 checks them instead.
+fn compose_xyz18e7_(parts: SharedExponentParts) -> vec2<u32> {
+    let e = parts.exponent & XYZ18E7_MAX_VALID_BIASED_EXP;
+    let m = parts.mantissas & vec3(MAX_XYZ18E7_MANTISSAU);
+    let s = parts.signs & vec3(1u);
+    return vec2((m.y << 18u) | (m.x << 0u), (e << 25u) | (s.z << 24u) | (s.y << 23u) | (s.x << 22u) | (m.z << 4u) | (m.y >> 14u));
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz18e7_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0], v[1] ^ 0x01C00000u);
+}
+
+fn abs_xyz18e7_(v: vec2<u32>) -> vec2<u32> {
+    return vec2(v[0], v[1] & ~0x01C00000u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz18e7_ of the scaled value.
+fn ldexp_xyz18e7_(v: vec2<u32>, k: i32) -> vec2<u32> {
+    return compose_xyz18e7_(ldexp_parts(decompose_xyz18e7_(v), k, XYZ18E7_EXPONENT_BITS, XYZ18E7_MANTISSA_BITSU));
+}
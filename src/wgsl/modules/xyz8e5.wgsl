@@ -0,0 +1,85 @@
+#define_import_path shared_exponent_formats::xyz8e5
+
+#import shared_exponent_formats::common::{floor_log2_, is_sign_negative, abs_nan_to_zero, SharedExponentParts, ldexp_parts}
+
+const XYZ8E5_EXPONENT_BITS        = 5u;
+const XYZ8E5_MANTISSA_BITS        = 8;
+const XYZ8E5_MANTISSA_BITSU       = 8u;
+const XYZ8E5_EXP_BIAS             = 15;
+const XYZ8E5_MAX_VALID_BIASED_EXP = 31u;
+
+const MAX_XYZ8E5_EXP              = 16u;
+const XYZ8E5_MANTISSA_VALUES      = 256;
+const MAX_XYZ8E5_MANTISSA         = 255;
+const MAX_XYZ8E5_MANTISSAU        = 255u;
+const MAX_XYZ8E5_                 = 65280.0;
+const EPSILON_XYZ8E5_             = 0.00000011920929;
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_xyz8e5_(xyz_in: vec3<f32>) -> u32 {
+    let xsign = is_sign_negative(xyz_in.x) << 8u;
+    let ysign = is_sign_negative(xyz_in.y) << 8u;
+    let zsign = is_sign_negative(xyz_in.z) << 8u;
+
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ8E5_));
+
+    let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    var exp_shared = max(-XYZ8E5_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ8E5_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - XYZ8E5_EXP_BIAS - XYZ8E5_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ8E5_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = vec3<u32>(floor(xyz / denom + 0.5));
+
+    return (u32(exp_shared) << 27u) | ((s.z | zsign) << 18u) | ((s.y | ysign) << 9u) | ((s.x | xsign) << 0u);
+}
+
+fn xyz8e5_to_vec3_(v: u32) -> vec3<f32> {
+    let exponent = i32(extractBits(v, 27u, XYZ8E5_EXPONENT_BITS)) - XYZ8E5_EXP_BIAS - XYZ8E5_MANTISSA_BITS;
+    let scale = exp2(f32(exponent));
+
+    // Extract both the mantissa and sign at the same time.
+    let xb = extractBits(v,  0u, XYZ8E5_MANTISSA_BITSU + 1u);
+    let yb = extractBits(v,  9u, XYZ8E5_MANTISSA_BITSU + 1u);
+    let zb = extractBits(v, 18u, XYZ8E5_MANTISSA_BITSU + 1u);
+
+    // xb & 0xFFu masks out for just the mantissa
+    // xb & 0x100u << 23u masks out just the sign bit and shifts it over
+    // to the corresponding IEEE 754 sign location
+    return vec3(
+        bitcast<f32>(bitcast<u32>(f32(xb & 0xFFu)) | (xb & 0x100u) << 23u),
+        bitcast<f32>(bitcast<u32>(f32(yb & 0xFFu)) | (yb & 0x100u) << 23u),
+        bitcast<f32>(bitcast<u32>(f32(zb & 0xFFu)) | (zb & 0x100u) << 23u),
+    ) * scale;
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz8e5_(v: u32) -> SharedExponentParts {
+    // Extract both the mantissa and sign at the same time.
+    let b = vec3(extractBits(v,  0u, XYZ8E5_MANTISSA_BITSU + 1u), extractBits(v, 9u, XYZ8E5_MANTISSA_BITSU + 1u), extractBits(v, 18u, XYZ8E5_MANTISSA_BITSU + 1u));
+    return SharedExponentParts(extractBits(v, 27u, XYZ8E5_EXPONENT_BITS), b & vec3(MAX_XYZ8E5_MANTISSAU), b >> vec3(XYZ8E5_MANTISSA_BITSU));
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz8e5 checks them instead.
+fn compose_xyz8e5_(parts: SharedExponentParts) -> u32 {
+    let b = (parts.mantissas & vec3(MAX_XYZ8E5_MANTISSAU)) | ((parts.signs & vec3(1u)) << vec3(XYZ8E5_MANTISSA_BITSU));
+    return ((parts.exponent & XYZ8E5_MAX_VALID_BIASED_EXP) << 27u) | (b.z << 18u) | (b.y << 9u) | (b.x << 0u);
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz8e5_(v: u32) -> u32 {
+    return v ^ 0x04020100u;
+}
+
+fn abs_xyz8e5_(v: u32) -> u32 {
+    return v & ~0x04020100u;
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz8e5_ of the scaled value.
+fn ldexp_xyz8e5_(v: u32, k: i32) -> u32 {
+    return compose_xyz8e5_(ldexp_parts(decompose_xyz8e5_(v), k, XYZ8E5_EXPONENT_BITS, XYZ8E5_MANTISSA_BITSU));
+}
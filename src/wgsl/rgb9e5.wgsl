@@ -17,9 +17,16 @@ fn floor_log2_(x: f32) -> i32 {
     return i32(biasedexponent) - 127;
 }
 
+// Replaces NaN with 0.0. This is done on the bits since fast-math compilers may assume that
+// NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, (b & vec3(0x7FFFFFFFu)) <= vec3(0x7F800000u)));
+}
+
 // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 fn vec3_to_rgb9e5_(rgb_in: vec3<f32>) -> u32 {
-    let rgb = clamp(rgb_in, vec3(0.0), vec3(MAX_RGB9E5_));
+    let rgb = clamp(nan_to_zero(rgb_in), vec3(0.0), vec3(MAX_RGB9E5_));
 
     let maxrgb = max(rgb.r, max(rgb.g, rgb.b));
     var exp_shared = max(-RGB9E5_EXP_BIAS - 1, floor_log2_(maxrgb)) + 1 + RGB9E5_EXP_BIAS;
@@ -45,4 +52,58 @@ fn rgb9e5_to_vec3_(v: u32) -> vec3<f32> {
         f32(extractBits(v, 9u, RGB9E5_MANTISSA_BITSU)),
         f32(extractBits(v, 18u, RGB9E5_MANTISSA_BITSU))
     ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    exponent: u32,
+    mantissas: vec3<u32>,
+    signs: vec3<u32>,
+}
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+fn ldexp_parts(parts: SharedExponentParts, k: i32, exponent_bits: u32, mantissa_bits: u32) -> SharedExponentParts {
+    let max_exponent = (1u << exponent_bits) - 1u;
+    let max_mantissa = (1u << mantissa_bits) - 1u;
+    var m = parts.mantissas;
+    var e = i32(parts.exponent) + clamp(k, -256, 256);
+    if (e > i32(max_exponent)) {
+        let d = min(u32(e) - max_exponent, mantissa_bits);
+        let over = vec3<u32>(m > vec3(max_mantissa >> d));
+        m = (m << vec3(d)) * (1u - over) + max_mantissa * over;
+        e = i32(max_exponent);
+    } else if (e < 0) {
+        let s = u32(min(-e, 31));
+        m = (m + vec3((1u << s) >> 1u)) >> vec3(s);
+        e = 0;
+    }
+
+    let any_mantissa = m.x | m.y | m.z;
+    var shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(u32(e), mantissa_bits - 1u - u32(floor_log2_(f32(any_mantissa))));
+    }
+    return SharedExponentParts(u32(e) - shift, m << vec3(shift), parts.signs);
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_rgb9e5_(v: u32) -> SharedExponentParts {
+    let m = vec3(extractBits(v, 0u, RGB9E5_MANTISSA_BITSU), extractBits(v, 9u, RGB9E5_MANTISSA_BITSU), extractBits(v, 18u, RGB9E5_MANTISSA_BITSU));
+    return SharedExponentParts(extractBits(v, 27u, RGB9E5_EXPONENT_BITS), m, vec3(0u));
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_rgb9e5 checks them instead.
+fn compose_rgb9e5_(parts: SharedExponentParts) -> u32 {
+    let m = parts.mantissas & vec3(MAX_RGB9E5_MANTISSAU);
+    return ((parts.exponent & RGB9E5_MAX_VALID_BIASED_EXP) << 27u) | (m.z << 18u) | (m.y << 9u) | (m.x << 0u);
+}
+
+// v * 2^k without decoding, the same as vec3_to_rgb9e5_ of the scaled value.
+fn ldexp_rgb9e5_(v: u32, k: i32) -> u32 {
+    return compose_rgb9e5_(ldexp_parts(decompose_rgb9e5_(v), k, RGB9E5_EXPONENT_BITS, RGB9E5_MANTISSA_BITSU));
 }
\ No newline at end of file
@@ -21,13 +21,20 @@ fn is_sign_negative(v: f32) -> u32 {
     return (bitcast<u32>(v) >> 31u) & 1u;
 }
 
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn abs_nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v) & vec3(0x7FFFFFFFu);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, b <= vec3(0x7F800000u)));
+}
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 fn vec3_to_xyz8e5_(xyz_in: vec3<f32>) -> u32 {
     let xsign = is_sign_negative(xyz_in.x) << 8u;
     let ysign = is_sign_negative(xyz_in.y) << 8u;
     let zsign = is_sign_negative(xyz_in.z) << 8u;
 
-    var xyz = min(abs(xyz_in), vec3(MAX_XYZ8E5_));
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ8E5_));
 
     let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
     var exp_shared = max(-XYZ8E5_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ8E5_EXP_BIAS;
@@ -61,4 +68,68 @@ fn xyz8e5_to_vec3_(v: u32) -> vec3<f32> {
         bitcast<f32>(bitcast<u32>(f32(yb & 0xFFu)) | (yb & 0x100u) << 23u),
         bitcast<f32>(bitcast<u32>(f32(zb & 0xFFu)) | (zb & 0x100u) << 23u),
     ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    exponent: u32,
+    mantissas: vec3<u32>,
+    signs: vec3<u32>,
+}
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+fn ldexp_parts(parts: SharedExponentParts, k: i32, exponent_bits: u32, mantissa_bits: u32) -> SharedExponentParts {
+    let max_exponent = (1u << exponent_bits) - 1u;
+    let max_mantissa = (1u << mantissa_bits) - 1u;
+    var m = parts.mantissas;
+    var e = i32(parts.exponent) + clamp(k, -256, 256);
+    if (e > i32(max_exponent)) {
+        let d = min(u32(e) - max_exponent, mantissa_bits);
+        let over = vec3<u32>(m > vec3(max_mantissa >> d));
+        m = (m << vec3(d)) * (1u - over) + max_mantissa * over;
+        e = i32(max_exponent);
+    } else if (e < 0) {
+        let s = u32(min(-e, 31));
+        m = (m + vec3((1u << s) >> 1u)) >> vec3(s);
+        e = 0;
+    }
+
+    let any_mantissa = m.x | m.y | m.z;
+    var shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(u32(e), mantissa_bits - 1u - u32(floor_log2_(f32(any_mantissa))));
+    }
+    return SharedExponentParts(u32(e) - shift, m << vec3(shift), parts.signs);
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz8e5_(v: u32) -> SharedExponentParts {
+    // Extract both the mantissa and sign at the same time.
+    let b = vec3(extractBits(v,  0u, XYZ8E5_MANTISSA_BITSU + 1u), extractBits(v, 9u, XYZ8E5_MANTISSA_BITSU + 1u), extractBits(v, 18u, XYZ8E5_MANTISSA_BITSU + 1u));
+    return SharedExponentParts(extractBits(v, 27u, XYZ8E5_EXPONENT_BITS), b & vec3(MAX_XYZ8E5_MANTISSAU), b >> vec3(XYZ8E5_MANTISSA_BITSU));
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz8e5 checks them instead.
+fn compose_xyz8e5_(parts: SharedExponentParts) -> u32 {
+    let b = (parts.mantissas & vec3(MAX_XYZ8E5_MANTISSAU)) | ((parts.signs & vec3(1u)) << vec3(XYZ8E5_MANTISSA_BITSU));
+    return ((parts.exponent & XYZ8E5_MAX_VALID_BIASED_EXP) << 27u) | (b.z << 18u) | (b.y << 9u) | (b.x << 0u);
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz8e5_(v: u32) -> u32 {
+    return v ^ 0x04020100u;
+}
+
+fn abs_xyz8e5_(v: u32) -> u32 {
+    return v & ~0x04020100u;
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz8e5_ of the scaled value.
+fn ldexp_xyz8e5_(v: u32, k: i32) -> u32 {
+    return compose_xyz8e5_(ldexp_parts(decompose_xyz8e5_(v), k, XYZ8E5_EXPONENT_BITS, XYZ8E5_MANTISSA_BITSU));
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+const XYZ9E2_EXPONENT_BITS        = 2u;
+const XYZ9E2_MANTISSA_BITS        = 9;
+const XYZ9E2_MANTISSA_BITSU       = 9u;
+const XYZ9E2_EXP_BIAS             = 3;
+const XYZ9E2_MAX_VALID_BIASED_EXP = 3u;
+
+const MAX_XYZ9E2_EXP              = 0u;
+const XYZ9E2_MANTISSA_VALUES      = 512;
+const MAX_XYZ9E2_MANTISSA         = 511;
+const MAX_XYZ9E2_MANTISSAU        = 511u;
+const MAX_XYZ9E2_                 = 1.0;
+const EPSILON_XYZ9E2_             = 0.00024414063;
+
+// MAX_XYZ9E2 would be 0.9980469, this is used to scale to exactly 1.0
+const XYZ9E2_NORM_MULT            = 1.0019569;
+
+fn floor_log2_(x: f32) -> i32 {
+    let f = bitcast<u32>(x);
+    let biasedexponent = (f & 0x7F800000u) >> 23u;
+    return i32(biasedexponent) - 127;
+}
+
+fn is_sign_negative(v: f32) -> u32 {
+    return (bitcast<u32>(v) >> 31u) & 1u;
+}
+
+// abs(v) with NaN replaced by 0.0. This is done on the bits since fast-math compilers may assume
+// that NaN never occurs, and min, max and clamp leave the result of a NaN input undefined.
+fn abs_nan_to_zero(v: vec3<f32>) -> vec3<f32> {
+    let b = bitcast<vec3<u32>>(v) & vec3(0x7FFFFFFFu);
+    return bitcast<vec3<f32>>(select(vec3(0u), b, b <= vec3(0x7F800000u)));
+}
+
+// Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_xyz9e2_(xyz_in: vec3<f32>) -> u32 {
+    let xsign = is_sign_negative(xyz_in.x) << 9u;
+    let ysign = is_sign_negative(xyz_in.y) << 9u;
+    let zsign = is_sign_negative(xyz_in.z) << 9u;
+
+    var xyz = min(abs_nan_to_zero(xyz_in), vec3(MAX_XYZ9E2_)) / f32(XYZ9E2_NORM_MULT);
+
+    let maxxyz = max(xyz.x, max(xyz.y, xyz.z));
+    var exp_shared = max(-XYZ9E2_EXP_BIAS - 1, floor_log2_(maxxyz)) + 1 + XYZ9E2_EXP_BIAS;
+    var denom = exp2(f32(exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS));
+
+    let maxm = i32(floor(maxxyz / denom + 0.5));
+    if (maxm == XYZ9E2_MANTISSA_VALUES) {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let s = vec3<u32>(floor(xyz / denom + 0.5));
+
+    return (u32(exp_shared) << 30u) | ((s.z | zsign) << 20u) | ((s.y | ysign) << 10u) | ((s.x | xsign) << 0u);
+}
+
+fn xyz9e2_to_vec3_(v: u32) -> vec3<f32> {
+    let exponent = i32(extractBits(v, 30u, XYZ9E2_EXPONENT_BITS)) - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS;
+    let scale = exp2(f32(exponent)) * f32(XYZ9E2_NORM_MULT);
+
+    // Extract both the mantissa and sign at the same time.
+    let xb = extractBits(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u);
+    let yb = extractBits(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u);
+    let zb = extractBits(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u);
+
+    // xb & 0x1FFu masks out for just the mantissa
+    // xb & 0x200u << 22u masks out just the sign bit and shifts it over 
+    // to the corresponding IEEE 754 sign location 
+    return vec3(
+        bitcast<f32>(bitcast<u32>(f32(xb & 0x1FFu)) | (xb & 0x200u) << 22u),
+        bitcast<f32>(bitcast<u32>(f32(yb & 0x1FFu)) | (yb & 0x200u) << 22u),
+        bitcast<f32>(bitcast<u32>(f32(zb & 0x1FFu)) | (zb & 0x200u) << 22u),
+    ) * scale;
+}
+
+// The fields of a packed value: the biased exponent as stored, the mantissas, and 0u or 1u for
+// each sign.
+struct SharedExponentParts {
+    exponent: u32,
+    mantissas: vec3<u32>,
+    signs: vec3<u32>,
+}
+
+// The parts times 2^k. Past the largest exponent the mantissas are shifted up instead and
+// saturate at the largest mantissa, below exponent 0 they are shifted down and rounded like the
+// encoders round. The result has the smallest exponent that fits, as the encoders give.
+fn ldexp_parts(parts: SharedExponentParts, k: i32, exponent_bits: u32, mantissa_bits: u32) -> SharedExponentParts {
+    let max_exponent = (1u << exponent_bits) - 1u;
+    let max_mantissa = (1u << mantissa_bits) - 1u;
+    var m = parts.mantissas;
+    var e = i32(parts.exponent) + clamp(k, -256, 256);
+    if (e > i32(max_exponent)) {
+        let d = min(u32(e) - max_exponent, mantissa_bits);
+        let over = vec3<u32>(m > vec3(max_mantissa >> d));
+        m = (m << vec3(d)) * (1u - over) + max_mantissa * over;
+        e = i32(max_exponent);
+    } else if (e < 0) {
+        let s = u32(min(-e, 31));
+        m = (m + vec3((1u << s) >> 1u)) >> vec3(s);
+        e = 0;
+    }
+
+    let any_mantissa = m.x | m.y | m.z;
+    var shift = 0u;
+    if (any_mantissa == 0u) {
+        e = 0;
+    } else {
+        shift = min(u32(e), mantissa_bits - 1u - u32(floor_log2_(f32(any_mantissa))));
+    }
+    return SharedExponentParts(u32(e) - shift, m << vec3(shift), parts.signs);
+}
+
+// The biased exponent, mantissas and signs of v as stored, without decoding it.
+fn decompose_xyz9e2_(v: u32) -> SharedExponentParts {
+    // Extract both the mantissa and sign at the same time.
+    let b = vec3(extractBits(v,  0u, XYZ9E2_MANTISSA_BITSU + 1u), extractBits(v, 10u, XYZ9E2_MANTISSA_BITSU + 1u), extractBits(v, 20u, XYZ9E2_MANTISSA_BITSU + 1u));
+    return SharedExponentParts(extractBits(v, 30u, XYZ9E2_EXPONENT_BITS), b & vec3(MAX_XYZ9E2_MANTISSAU), b >> vec3(XYZ9E2_MANTISSA_BITSU));
+}
+
+// Fields that don't fit in their bits are cut off, the rust compose_xyz9e2 checks them instead.
+fn compose_xyz9e2_(parts: SharedExponentParts) -> u32 {
+    let b = (parts.mantissas & vec3(MAX_XYZ9E2_MANTISSAU)) | ((parts.signs & vec3(1u)) << vec3(XYZ9E2_MANTISSA_BITSU));
+    return ((parts.exponent & XYZ9E2_MAX_VALID_BIASED_EXP) << 30u) | (b.z << 20u) | (b.y << 10u) | (b.x << 0u);
+}
+
+// -v by flipping the sign bits, so zero becomes -0.0 and the other way around.
+fn negate_xyz9e2_(v: u32) -> u32 {
+    return v ^ 0x20080200u;
+}
+
+fn abs_xyz9e2_(v: u32) -> u32 {
+    return v & ~0x20080200u;
+}
+
+// v * 2^k without decoding, the same as vec3_to_xyz9e2_ of the scaled value.
+fn ldexp_xyz9e2_(v: u32, k: i32) -> u32 {
+    return compose_xyz9e2_(ldexp_parts(decompose_xyz9e2_(v), k, XYZ9E2_EXPONENT_BITS, XYZ9E2_MANTISSA_BITSU));
+}
@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "xyz13e6";
 pub const BYTES: u8 = 6;
@@ -10,15 +12,6 @@ pub const XYZ13E6_MANTISSA_BITSU: u32 = 13;
 pub const XYZ13E6_EXP_BIAS: i32 = 31;
 pub const XYZ13E6_MAX_VALID_BIASED_EXP: i32 = 63;
 
-/*
-pub const MAX_XYZ13E6_EXP: i32 = 32;
-pub const XYZ13E6_MANTISSA_VALUES: i32 = 8192;
-pub const MAX_XYZ13E6_MANTISSA: i32 = 8191;
-pub const MAX_XYZ13E6_MANTISSAU: u32 = 8191;
-pub const MAX_XYZ13E6: f32 = 4294443000.0;
-pub const EPSILON_XYZ13E6: f32 = 5.684342e-14;
-*/
-
 pub const MAX_XYZ13E6_EXP: u64 = XYZ13E6_MAX_VALID_BIASED_EXP as u64 - XYZ13E6_EXP_BIAS as u64;
 pub const XYZ13E6_MANTISSA_VALUES: i32 = 1 << XYZ13E6_MANTISSA_BITS;
 pub const MAX_XYZ13E6_MANTISSA: i32 = XYZ13E6_MANTISSA_VALUES - 1;
@@ -28,9 +21,16 @@ pub const MAX_XYZ13E6: f32 = (MAX_XYZ13E6_MANTISSA as f32) / XYZ13E6_MANTISSA_VA
 pub const EPSILON_XYZ13E6: f32 =
     (1.0 / XYZ13E6_MANTISSA_VALUES as f32) / (1u64 << XYZ13E6_EXP_BIAS) as f32;
 
+const _: () = assert!(MAX_XYZ13E6_EXP == 32);
+const _: () = assert!(XYZ13E6_MANTISSA_VALUES == 8192);
+const _: () = assert!(MAX_XYZ13E6_MANTISSA == 8191);
+const _: () = assert!(MAX_XYZ13E6_MANTISSAU == 8191);
+const _: () = assert!(MAX_XYZ13E6 == 4294443000.0);
+const _: () = assert!(EPSILON_XYZ13E6 == 5.684342e-14);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
+pub const fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
     let xsign = xyz[0].is_sign_negative() as u16;
     let ysign = xyz[1].is_sign_negative() as u16;
     let zsign = xyz[2].is_sign_negative() as u16;
@@ -41,14 +41,15 @@ pub fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
 
     let maxxyz = xc.max(yc).max(zc);
     let mut exp_shared =
-        (-XYZ13E6_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ13E6_EXP_BIAS;
+        floor_log2f(maxxyz.max(pow2f(-XYZ13E6_EXP_BIAS - 1))) + 1 + XYZ13E6_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ13E6_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - XYZ13E6_EXP_BIAS - XYZ13E6_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ13E6_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -57,9 +58,9 @@ pub fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
         debug_assert!(maxm <= MAX_XYZ13E6_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ13E6_MANTISSA);
     debug_assert!(ym <= MAX_XYZ13E6_MANTISSA);
@@ -68,9 +69,9 @@ pub fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ13E6_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ13E6_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ13E6_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ13E6_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ13E6_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ13E6_MANTISSAU);
 
     let exp_shared = exp_shared as u32;
 
@@ -87,23 +88,23 @@ pub fn vec3_to_xyz13e6(xyz: [f32; 3]) -> (u32, u16) {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-fn bitfield_extract_u16(value: u16, offset: u16, bits: u16) -> u16 {
+const fn bitfield_extract_u16(value: u16, offset: u16, bits: u16) -> u16 {
     let mask = (1u16 << bits) - 1u16;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz13e6_to_vec3(v: (u32, u16)) -> [f32; 3] {
+pub const fn xyz13e6_to_vec3(v: (u32, u16)) -> [f32; 3] {
     let exponent = bitfield_extract(v.0, 26, XYZ13E6_EXPONENT_BITS as u32) as i32
         - XYZ13E6_EXP_BIAS
         - XYZ13E6_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2();
+    let scale = pow2f(exponent);
 
     let xb = bitfield_extract(v.0, 0, XYZ13E6_MANTISSA_BITSU);
     let yb = bitfield_extract(v.0, 13, XYZ13E6_MANTISSA_BITSU);
@@ -122,10 +123,102 @@ pub fn xyz13e6_to_vec3(v: (u32, u16)) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_xyz13e6` gives for the value `v` decodes to. The same value can also be stored
+// with a larger exponent and smaller mantissas, these are moved to the smallest exponent. Signs
+// are kept as they are, also on zero mantissas, as the encoder keeps the sign of -0.0.
+#[inline]
+pub const fn canonicalize_xyz13e6(v: (u32, u16)) -> (u32, u16) {
+    let exp = bitfield_extract(v.0, 26, XYZ13E6_EXPONENT_BITS as u32);
+    let xm = bitfield_extract(v.0, 0, XYZ13E6_MANTISSA_BITSU);
+    let ym = bitfield_extract(v.0, 13, XYZ13E6_MANTISSA_BITSU);
+    let zm = bitfield_extract_u16(v.1, 0, XYZ13E6_MANTISSA_BITSU as u16) as u32;
+
+    let xsign = bitfield_extract_u16(v.1, 13, 1);
+    let ysign = bitfield_extract_u16(v.1, 14, 1);
+    let zsign = bitfield_extract_u16(v.1, 15, 1);
+
+    let (exp, shift) = canonical_exponent(exp, xm | ym | zm, XYZ13E6_MANTISSA_BITSU);
+
+    #[allow(clippy::identity_op)]
+    let out_a = (exp << 26) | (ym << shift << 13) | (xm << shift << 0);
+    #[allow(clippy::identity_op)]
+    let out_b = (zsign << 15) | (ysign << 14) | (xsign << 13) | ((zm << shift) as u16) << 0;
+
+    (out_a, out_b)
+}
+
+#[inline]
+pub const fn is_canonical_xyz13e6(v: (u32, u16)) -> bool {
+    let c = canonicalize_xyz13e6(v);
+    c.0 == v.0 && c.1 == v.1
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_xyz13e6(v: (u32, u16)) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v.0, 26, XYZ13E6_EXPONENT_BITS as u32),
+        mantissas: [
+            bitfield_extract(v.0, 0, XYZ13E6_MANTISSA_BITSU),
+            bitfield_extract(v.0, 13, XYZ13E6_MANTISSA_BITSU),
+            bitfield_extract_u16(v.1, 0, XYZ13E6_MANTISSA_BITSU as u16) as u32,
+        ],
+        signs: [
+            bitfield_extract_u16(v.1, 13, 1) != 0,
+            bitfield_extract_u16(v.1, 14, 1) != 0,
+            bitfield_extract_u16(v.1, 15, 1) != 0,
+        ],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_xyz13e6(parts: Parts) -> Result<(u32, u16), PartsError> {
+    match parts.check(XYZ13E6_EXPONENT_BITS as u32, XYZ13E6_MANTISSA_BITSU, SIGNED) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> (u32, u16) {
+    let [xm, ym, zm] = parts.mantissas;
+    let [xsign, ysign, zsign] = parts.signs;
+
+    #[allow(clippy::identity_op)]
+    let out_a = (parts.exponent << 26) | (ym << 13) | (xm << 0);
+    #[allow(clippy::identity_op)]
+    let out_b =
+        (zsign as u16) << 15 | (ysign as u16) << 14 | (xsign as u16) << 13 | (zm as u16) << 0;
+
+    (out_a, out_b)
+}
+
+const XYZ13E6_SIGN_BITS: u16 = 1 << 13 | 1 << 14 | 1 << 15;
+
+// -v by flipping the sign bits. Like negating a float, zero becomes -0.0 and the other way around.
+#[inline]
+pub const fn negate_xyz13e6(v: (u32, u16)) -> (u32, u16) {
+    (v.0, v.1 ^ XYZ13E6_SIGN_BITS)
+}
+
+#[inline]
+pub const fn abs_xyz13e6(v: (u32, u16)) -> (u32, u16) {
+    (v.0, v.1 & !XYZ13E6_SIGN_BITS)
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_xyz13e6` on the scaled
+// decoded value. Components past MAX_XYZ13E6 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_xyz13e6(v: (u32, u16), k: i32) -> (u32, u16) {
+    pack_parts(decompose_xyz13e6(v).ldexp(k, XYZ13E6_EXPONENT_BITS as u32, XYZ13E6_MANTISSA_BITSU))
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use glam::Vec3;
+    use rand::Rng;
 
     use crate::evaluate::test_util::{Report, DEFUALT_ITERATIONS};
 
@@ -183,4 +276,89 @@ pub mod tests {
             xyz13e6_to_vec3(vec3_to_xyz13e6((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            let c = canonicalize_xyz13e6(v);
+            assert!(is_canonical_xyz13e6(c));
+            assert_eq!(is_canonical_xyz13e6(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = xyz13e6_to_vec3(v);
+            assert_eq!(
+                xyz13e6_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_xyz13e6(decoded));
+        }
+
+        // 1.0 as 2048 * 2^(33 - 31 - 13) instead of 4096 * 2^(32 - 31 - 13).
+        assert_eq!(
+            canonicalize_xyz13e6((33 << 26 | 2048, 0)),
+            (32 << 26 | 4096, 0)
+        );
+        assert_eq!(
+            canonicalize_xyz13e6((33 << 26 | 2048, 0)),
+            vec3_to_xyz13e6([1.0, 0.0, 0.0])
+        );
+        assert_eq!(canonicalize_xyz13e6((63 << 26, 0)), (0, 0));
+        // Encoded -0.0 keeps its sign, at the smallest exponent.
+        assert!(is_canonical_xyz13e6(vec3_to_xyz13e6([-0.0; 3])));
+        assert_eq!(canonicalize_xyz13e6((63 << 26, 1 << 13)), (0, 1 << 13));
+        assert_eq!(
+            canonicalize_xyz13e6((63 << 26, 1 << 13)),
+            vec3_to_xyz13e6([-0.0, 0.0, 0.0])
+        );
+        assert!(is_canonical_xyz13e6(vec3_to_xyz13e6([-1e-20, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            assert_eq!(compose_xyz13e6(decompose_xyz13e6(v)), Ok(v));
+
+            let xyz = xyz13e6_to_vec3(v);
+            let negated = xyz13e6_to_vec3(negate_xyz13e6(v)).map(f32::to_bits);
+            assert_eq!(negated, xyz.map(|x| (-x).to_bits()));
+            let abs = xyz13e6_to_vec3(abs_xyz13e6(v)).map(f32::to_bits);
+            assert_eq!(abs, xyz.map(|x| x.abs().to_bits()));
+            assert_eq!(negate_xyz13e6(negate_xyz13e6(v)), v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_xyz13e6(v, k), vec3_to_xyz13e6(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_xyz13e6(vec3_to_xyz13e6([1.0, 0.0, 0.0]));
+        assert_eq!(one.mantissas, [1 << 12, 0, 0]);
+        assert_eq!(
+            compose_xyz13e6(Parts {
+                exponent: 64,
+                ..one
+            }),
+            Err(PartsError::Exponent { exponent: 64 })
+        );
+        assert_eq!(
+            compose_xyz13e6(Parts {
+                mantissas: [0, 0, 1 << 13],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 13
+            })
+        );
+        assert_eq!(
+            ldexp_xyz13e6(vec3_to_xyz13e6([MAX_XYZ13E6; 3]), 1),
+            vec3_to_xyz13e6([MAX_XYZ13E6; 3])
+        );
+        assert_eq!(
+            ldexp_xyz13e6(vec3_to_xyz13e6([1.0; 3]), -1000),
+            vec3_to_xyz13e6([0.0; 3])
+        );
+    }
 }
@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "xyz14e3";
 pub const BYTES: u8 = 6;
@@ -10,17 +12,8 @@ pub const XYZ14E3_MANTISSA_BITSU: u32 = 14;
 pub const XYZ14E3_EXP_BIAS: i32 = 3;
 pub const XYZ14E3_MAX_VALID_BIASED_EXP: i32 = 7;
 
-// MAX_XYZ9E2 would be 15.999023, this is used to scale to exactly 16.0
-pub const NORM_MULT: f32 = 1.000061;
-
-/*
-pub const MAX_XYZ14E3_EXP: u64 = 4;
-pub const XYZ14E3_MANTISSA_VALUES: i32 = 16384;
-pub const MAX_XYZ14E3_MANTISSA: i32 = 16383;
-pub const MAX_XYZ14E3_MANTISSAU: u32 = 16383;
-pub const MAX_XYZ14E3: f32 = 16.0;
-pub const EPSILON_XYZ14E3: f32 = 7.6293945e-6;
-*/
+// MAX_XYZ14E3 would be 15.999023, this is used to scale to exactly 16.0
+pub const NORM_MULT: f32 = XYZ14E3_MANTISSA_VALUES as f32 / MAX_XYZ14E3_MANTISSA as f32;
 
 pub const MAX_XYZ14E3_EXP: u64 = XYZ14E3_MAX_VALID_BIASED_EXP as u64 - XYZ14E3_EXP_BIAS as u64;
 pub const XYZ14E3_MANTISSA_VALUES: i32 = 1 << XYZ14E3_MANTISSA_BITS;
@@ -32,9 +25,17 @@ pub const MAX_XYZ14E3: f32 = (MAX_XYZ14E3_MANTISSA as f32) / XYZ14E3_MANTISSA_VA
 pub const EPSILON_XYZ14E3: f32 =
     (1.0 / XYZ14E3_MANTISSA_VALUES as f32) / (1 << XYZ14E3_EXP_BIAS) as f32;
 
+const _: () = assert!(NORM_MULT == 1.000061);
+const _: () = assert!(MAX_XYZ14E3_EXP == 4);
+const _: () = assert!(XYZ14E3_MANTISSA_VALUES == 16384);
+const _: () = assert!(MAX_XYZ14E3_MANTISSA == 16383);
+const _: () = assert!(MAX_XYZ14E3_MANTISSAU == 16383);
+const _: () = assert!(MAX_XYZ14E3 == 16.0);
+const _: () = assert!(EPSILON_XYZ14E3 == 7.6293945e-6);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
+pub const fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
     let xsign = xyz[0].is_sign_negative() as u32;
     let ysign = xyz[1].is_sign_negative() as u32;
     let zsign = xyz[2].is_sign_negative() as u32;
@@ -45,14 +46,15 @@ pub fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
 
     let maxxyz = xc.max(yc).max(zc);
     let mut exp_shared =
-        (-XYZ14E3_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ14E3_EXP_BIAS;
+        floor_log2f(maxxyz.max(pow2f(-XYZ14E3_EXP_BIAS - 1))) + 1 + XYZ14E3_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ14E3_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ14E3_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -61,9 +63,9 @@ pub fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
         debug_assert!(maxm <= MAX_XYZ14E3_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ14E3_MANTISSA);
     debug_assert!(ym <= MAX_XYZ14E3_MANTISSA);
@@ -72,9 +74,9 @@ pub fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ14E3_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ14E3_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ14E3_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ14E3_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ14E3_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ14E3_MANTISSAU);
 
     let exp_shared = exp_shared as u32;
 
@@ -96,22 +98,22 @@ pub fn vec3_to_xyz14e3(xyz: [f32; 3]) -> (u32, u16) {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1 << bits) - 1;
     (value >> offset) & mask
 }
 
 #[inline]
-fn bitfield_extract_u16(value: u16, offset: u16, bits: u16) -> u16 {
+const fn bitfield_extract_u16(value: u16, offset: u16, bits: u16) -> u16 {
     let mask = (1 << bits) - 1;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz14e3_to_vec3(v: (u32, u16)) -> [f32; 3] {
+pub const fn xyz14e3_to_vec3(v: (u32, u16)) -> [f32; 3] {
     let exp = bitfield_extract(v.0, 31, 1) | ((bitfield_extract_u16(v.1, 14, 2) as u32) << 1);
     let exponent = exp as i32 - XYZ14E3_EXP_BIAS - XYZ14E3_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2() * NORM_MULT;
+    let scale = pow2f(exponent) * NORM_MULT;
 
     let xb = bitfield_extract(v.0, 0, XYZ14E3_MANTISSA_BITSU);
     let yb = bitfield_extract(v.0, 14, XYZ14E3_MANTISSA_BITSU);
@@ -130,10 +132,111 @@ pub fn xyz14e3_to_vec3(v: (u32, u16)) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_xyz14e3` gives for the value `v` decodes to. The same value can also be stored
+// with a larger exponent and smaller mantissas, these are moved to the smallest exponent. Signs
+// are kept as they are, also on zero mantissas, as the encoder keeps the sign of -0.0.
+#[inline]
+pub const fn canonicalize_xyz14e3(v: (u32, u16)) -> (u32, u16) {
+    let exp = bitfield_extract(v.0, 31, 1) | ((bitfield_extract_u16(v.1, 14, 2) as u32) << 1);
+    let xm = bitfield_extract(v.0, 0, XYZ14E3_MANTISSA_BITSU);
+    let ym = bitfield_extract(v.0, 14, XYZ14E3_MANTISSA_BITSU);
+    let zm = bitfield_extract_u16(v.1, 0, XYZ14E3_MANTISSA_BITSU as u16) as u32;
+
+    let xsign = bitfield_extract(v.0, 28, 1);
+    let ysign = bitfield_extract(v.0, 29, 1);
+    let zsign = bitfield_extract(v.0, 30, 1);
+
+    let (exp, shift) = canonical_exponent(exp, xm | ym | zm, XYZ14E3_MANTISSA_BITSU);
+
+    #[allow(clippy::identity_op)]
+    let out_a = ((exp & 1) << 31)
+        | (zsign << 30)
+        | (ysign << 29)
+        | (xsign << 28)
+        | (ym << shift << 14)
+        | (xm << shift << 0);
+    #[allow(clippy::identity_op)]
+    let out_b = ((exp & 6) << 13) as u16 | ((zm << shift) as u16) << 0;
+
+    (out_a, out_b)
+}
+
+#[inline]
+pub const fn is_canonical_xyz14e3(v: (u32, u16)) -> bool {
+    let c = canonicalize_xyz14e3(v);
+    c.0 == v.0 && c.1 == v.1
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_xyz14e3(v: (u32, u16)) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v.0, 31, 1) | ((bitfield_extract_u16(v.1, 14, 2) as u32) << 1),
+        mantissas: [
+            bitfield_extract(v.0, 0, XYZ14E3_MANTISSA_BITSU),
+            bitfield_extract(v.0, 14, XYZ14E3_MANTISSA_BITSU),
+            bitfield_extract_u16(v.1, 0, XYZ14E3_MANTISSA_BITSU as u16) as u32,
+        ],
+        signs: [
+            bitfield_extract(v.0, 28, 1) != 0,
+            bitfield_extract(v.0, 29, 1) != 0,
+            bitfield_extract(v.0, 30, 1) != 0,
+        ],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_xyz14e3(parts: Parts) -> Result<(u32, u16), PartsError> {
+    match parts.check(XYZ14E3_EXPONENT_BITS as u32, XYZ14E3_MANTISSA_BITSU, SIGNED) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> (u32, u16) {
+    let [xm, ym, zm] = parts.mantissas;
+    let [xsign, ysign, zsign] = parts.signs;
+
+    #[allow(clippy::identity_op)]
+    let out_a = ((parts.exponent & 1) << 31)
+        | (zsign as u32) << 30
+        | (ysign as u32) << 29
+        | (xsign as u32) << 28
+        | (ym << 14)
+        | (xm << 0);
+    #[allow(clippy::identity_op)]
+    let out_b = ((parts.exponent & 6) << 13) as u16 | (zm as u16) << 0;
+
+    (out_a, out_b)
+}
+
+const XYZ14E3_SIGN_BITS: u32 = 1 << 28 | 1 << 29 | 1 << 30;
+
+// -v by flipping the sign bits. Like negating a float, zero becomes -0.0 and the other way around.
+#[inline]
+pub const fn negate_xyz14e3(v: (u32, u16)) -> (u32, u16) {
+    (v.0 ^ XYZ14E3_SIGN_BITS, v.1)
+}
+
+#[inline]
+pub const fn abs_xyz14e3(v: (u32, u16)) -> (u32, u16) {
+    (v.0 & !XYZ14E3_SIGN_BITS, v.1)
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_xyz14e3` on the scaled
+// decoded value. Components past MAX_XYZ14E3 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_xyz14e3(v: (u32, u16), k: i32) -> (u32, u16) {
+    pack_parts(decompose_xyz14e3(v).ldexp(k, XYZ14E3_EXPONENT_BITS as u32, XYZ14E3_MANTISSA_BITSU))
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use glam::Vec3;
+    use rand::Rng;
 
     use super::*;
 
@@ -172,4 +275,83 @@ pub mod tests {
             xyz14e3_to_vec3(vec3_to_xyz14e3((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            let c = canonicalize_xyz14e3(v);
+            assert!(is_canonical_xyz14e3(c));
+            assert_eq!(is_canonical_xyz14e3(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = xyz14e3_to_vec3(v);
+            assert_eq!(
+                xyz14e3_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_xyz14e3(decoded));
+        }
+
+        // Exponent 4 with a mantissa of 100 can go down to exponent 0.
+        assert_eq!(canonicalize_xyz14e3((100, 2 << 14)), (1600, 0));
+        assert_eq!(canonicalize_xyz14e3((1 << 31, 3 << 14)), (0, 0));
+        // Encoded -0.0 keeps its sign, at the smallest exponent.
+        assert!(is_canonical_xyz14e3(vec3_to_xyz14e3([-0.0; 3])));
+        assert_eq!(
+            canonicalize_xyz14e3((1 << 31 | 1 << 28, 3 << 14)),
+            (1 << 28, 0)
+        );
+        assert_eq!(
+            canonicalize_xyz14e3((1 << 31 | 1 << 28, 3 << 14)),
+            vec3_to_xyz14e3([-0.0, 0.0, 0.0])
+        );
+        assert!(is_canonical_xyz14e3(vec3_to_xyz14e3([-1e-9, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            assert_eq!(compose_xyz14e3(decompose_xyz14e3(v)), Ok(v));
+
+            let xyz = xyz14e3_to_vec3(v);
+            let negated = xyz14e3_to_vec3(negate_xyz14e3(v)).map(f32::to_bits);
+            assert_eq!(negated, xyz.map(|x| (-x).to_bits()));
+            let abs = xyz14e3_to_vec3(abs_xyz14e3(v)).map(f32::to_bits);
+            assert_eq!(abs, xyz.map(|x| x.abs().to_bits()));
+            assert_eq!(negate_xyz14e3(negate_xyz14e3(v)), v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_xyz14e3(v, k), vec3_to_xyz14e3(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_xyz14e3(vec3_to_xyz14e3([1.0, 0.0, 0.0]));
+        // 1.0 is the largest mantissa, scaled by NORM_MULT.
+        assert_eq!(one.mantissas, [MAX_XYZ14E3_MANTISSAU, 0, 0]);
+        assert_eq!(
+            compose_xyz14e3(Parts { exponent: 8, ..one }),
+            Err(PartsError::Exponent { exponent: 8 })
+        );
+        assert_eq!(
+            compose_xyz14e3(Parts {
+                mantissas: [0, 0, 1 << 14],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 14
+            })
+        );
+        assert_eq!(
+            ldexp_xyz14e3(vec3_to_xyz14e3([MAX_XYZ14E3; 3]), 1),
+            vec3_to_xyz14e3([MAX_XYZ14E3; 3])
+        );
+        assert_eq!(
+            ldexp_xyz14e3(vec3_to_xyz14e3([1.0; 3]), -1000),
+            vec3_to_xyz14e3([0.0; 3])
+        );
+    }
 }
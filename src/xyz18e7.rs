@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "xyz18e7";
 pub const BYTES: u8 = 8;
@@ -10,15 +12,6 @@ pub const XYZ18E7_MANTISSA_BITSU: u32 = 18;
 pub const XYZ18E7_EXP_BIAS: i32 = 63;
 pub const XYZ18E7_MAX_VALID_BIASED_EXP: i32 = 127;
 
-/*
-pub const MAX_XYZ18E7_EXP: i32 = 64;
-pub const XYZ18E7_MANTISSA_VALUES: i32 = 262144;
-pub const MAX_XYZ18E7_MANTISSA: i32 = 262143;
-pub const MAX_XYZ18E7_MANTISSAU: u32 = 262143;
-pub const MAX_XYZ18E7: f32 = 1.8446674e19;
-pub const EPSILON_XYZ18E7: f32 = 4.135903e-25;
-*/
-
 pub const MAX_XYZ18E7_EXP: u64 = XYZ18E7_MAX_VALID_BIASED_EXP as u64 - XYZ18E7_EXP_BIAS as u64;
 pub const XYZ18E7_MANTISSA_VALUES: i32 = 1 << XYZ18E7_MANTISSA_BITS;
 pub const MAX_XYZ18E7_MANTISSA: i32 = XYZ18E7_MANTISSA_VALUES - 1;
@@ -28,9 +21,16 @@ pub const MAX_XYZ18E7: f32 = (MAX_XYZ18E7_MANTISSA as f32) / XYZ18E7_MANTISSA_VA
 pub const EPSILON_XYZ18E7: f32 =
     (1.0 / XYZ18E7_MANTISSA_VALUES as f32) / (1u64 << XYZ18E7_EXP_BIAS) as f32;
 
+const _: () = assert!(MAX_XYZ18E7_EXP == 64);
+const _: () = assert!(XYZ18E7_MANTISSA_VALUES == 262144);
+const _: () = assert!(MAX_XYZ18E7_MANTISSA == 262143);
+const _: () = assert!(MAX_XYZ18E7_MANTISSAU == 262143);
+const _: () = assert!(MAX_XYZ18E7 == 1.8446674e19);
+const _: () = assert!(EPSILON_XYZ18E7 == 4.135903e-25);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
+pub const fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
     let xsign = xyz[0].is_sign_negative() as u32;
     let ysign = xyz[1].is_sign_negative() as u32;
     let zsign = xyz[2].is_sign_negative() as u32;
@@ -41,14 +41,15 @@ pub fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
 
     let maxxyz = xc.max(yc).max(zc);
     let mut exp_shared =
-        (-XYZ18E7_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ18E7_EXP_BIAS;
+        floor_log2f(maxxyz.max(pow2f(-XYZ18E7_EXP_BIAS - 1))) + 1 + XYZ18E7_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ18E7_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ18E7_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -57,9 +58,9 @@ pub fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
         debug_assert!(maxm <= MAX_XYZ18E7_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ18E7_MANTISSA);
     debug_assert!(ym <= MAX_XYZ18E7_MANTISSA);
@@ -68,9 +69,9 @@ pub fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ18E7_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ18E7_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ18E7_MANTISSAU);
 
     let exp_shared = exp_shared as u32;
 
@@ -88,17 +89,17 @@ pub fn vec3_to_xyz18e7(xyz: [f32; 3]) -> (u32, u32) {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz18e7_to_vec3(v: (u32, u32)) -> [f32; 3] {
+pub const fn xyz18e7_to_vec3(v: (u32, u32)) -> [f32; 3] {
     let exponent = bitfield_extract(v.1, 25, XYZ18E7_EXPONENT_BITS as u32) as i32
         - XYZ18E7_EXP_BIAS
         - XYZ18E7_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2();
+    let scale = pow2f(exponent);
 
     let xb = bitfield_extract(v.0, 0, XYZ18E7_MANTISSA_BITSU);
     let yb = bitfield_extract(v.0, 18, XYZ18E7_MANTISSA_BITSU) | bitfield_extract(v.1, 0, 4) << 14;
@@ -117,10 +118,110 @@ pub fn xyz18e7_to_vec3(v: (u32, u32)) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_xyz18e7` gives for the value `v` decodes to. The same value can also be stored
+// with a larger exponent and smaller mantissas, these are moved to the smallest exponent. Signs
+// are kept as they are, also on zero mantissas, as the encoder keeps the sign of -0.0.
+#[inline]
+pub const fn canonicalize_xyz18e7(v: (u32, u32)) -> (u32, u32) {
+    let exp = bitfield_extract(v.1, 25, XYZ18E7_EXPONENT_BITS as u32);
+    let xm = bitfield_extract(v.0, 0, XYZ18E7_MANTISSA_BITSU);
+    let ym = bitfield_extract(v.0, 18, 14) | bitfield_extract(v.1, 0, 4) << 14;
+    let zm = bitfield_extract(v.1, 4, XYZ18E7_MANTISSA_BITSU);
+
+    let xsign = bitfield_extract(v.1, 22, 1);
+    let ysign = bitfield_extract(v.1, 23, 1);
+    let zsign = bitfield_extract(v.1, 24, 1);
+
+    let (exp, shift) = canonical_exponent(exp, xm | ym | zm, XYZ18E7_MANTISSA_BITSU);
+    let xm = xm << shift;
+    let ym = ym << shift;
+    let zm = zm << shift;
+
+    #[allow(clippy::identity_op)]
+    let out_a = (ym << 18) | (xm << 0);
+    #[allow(clippy::identity_op)]
+    let out_b =
+        (exp << 25) | (zsign << 24) | (ysign << 23) | (xsign << 22) | (zm << 4) | (ym >> 14);
+
+    (out_a, out_b)
+}
+
+#[inline]
+pub const fn is_canonical_xyz18e7(v: (u32, u32)) -> bool {
+    let c = canonicalize_xyz18e7(v);
+    c.0 == v.0 && c.1 == v.1
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_xyz18e7(v: (u32, u32)) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v.1, 25, XYZ18E7_EXPONENT_BITS as u32),
+        mantissas: [
+            bitfield_extract(v.0, 0, XYZ18E7_MANTISSA_BITSU),
+            bitfield_extract(v.0, 18, 14) | bitfield_extract(v.1, 0, 4) << 14,
+            bitfield_extract(v.1, 4, XYZ18E7_MANTISSA_BITSU),
+        ],
+        signs: [
+            bitfield_extract(v.1, 22, 1) != 0,
+            bitfield_extract(v.1, 23, 1) != 0,
+            bitfield_extract(v.1, 24, 1) != 0,
+        ],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_xyz18e7(parts: Parts) -> Result<(u32, u32), PartsError> {
+    match parts.check(XYZ18E7_EXPONENT_BITS as u32, XYZ18E7_MANTISSA_BITSU, SIGNED) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> (u32, u32) {
+    let [xm, ym, zm] = parts.mantissas;
+    let [xsign, ysign, zsign] = parts.signs;
+
+    #[allow(clippy::identity_op)]
+    let out_a = (ym << 18) | (xm << 0);
+    #[allow(clippy::identity_op)]
+    let out_b = (parts.exponent << 25)
+        | (zsign as u32) << 24
+        | (ysign as u32) << 23
+        | (xsign as u32) << 22
+        | (zm << 4)
+        | (ym >> 14);
+
+    (out_a, out_b)
+}
+
+const XYZ18E7_SIGN_BITS: u32 = 1 << 22 | 1 << 23 | 1 << 24;
+
+// -v by flipping the sign bits. Like negating a float, zero becomes -0.0 and the other way around.
+#[inline]
+pub const fn negate_xyz18e7(v: (u32, u32)) -> (u32, u32) {
+    (v.0, v.1 ^ XYZ18E7_SIGN_BITS)
+}
+
+#[inline]
+pub const fn abs_xyz18e7(v: (u32, u32)) -> (u32, u32) {
+    (v.0, v.1 & !XYZ18E7_SIGN_BITS)
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_xyz18e7` on the scaled
+// decoded value. Components past MAX_XYZ18E7 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_xyz18e7(v: (u32, u32), k: i32) -> (u32, u32) {
+    pack_parts(decompose_xyz18e7(v).ldexp(k, XYZ18E7_EXPONENT_BITS as u32, XYZ18E7_MANTISSA_BITSU))
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use glam::Vec3;
+    use rand::Rng;
 
     use crate::evaluate::test_util::{Report, DEFUALT_ITERATIONS};
 
@@ -178,4 +279,89 @@ pub mod tests {
             xyz18e7_to_vec3(vec3_to_xyz18e7((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            let c = canonicalize_xyz18e7(v);
+            assert!(is_canonical_xyz18e7(c));
+            assert_eq!(is_canonical_xyz18e7(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = xyz18e7_to_vec3(v);
+            assert_eq!(
+                xyz18e7_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_xyz18e7(decoded));
+        }
+
+        // 1.0 as 2^16 * 2^(65 - 63 - 18) instead of 2^17 * 2^(64 - 63 - 18).
+        assert_eq!(
+            canonicalize_xyz18e7((1 << 16, 65 << 25)),
+            (1 << 17, 64 << 25)
+        );
+        assert_eq!(
+            canonicalize_xyz18e7((1 << 16, 65 << 25)),
+            vec3_to_xyz18e7([1.0, 0.0, 0.0])
+        );
+        assert_eq!(canonicalize_xyz18e7((0, 127 << 25)), (0, 0));
+        // Encoded -0.0 keeps its sign, at the smallest exponent.
+        assert!(is_canonical_xyz18e7(vec3_to_xyz18e7([-0.0; 3])));
+        assert_eq!(canonicalize_xyz18e7((0, 127 << 25 | 1 << 22)), (0, 1 << 22));
+        assert_eq!(
+            canonicalize_xyz18e7((0, 127 << 25 | 1 << 22)),
+            vec3_to_xyz18e7([-0.0, 0.0, 0.0])
+        );
+        assert!(is_canonical_xyz18e7(vec3_to_xyz18e7([-1e-30, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = (rng.gen(), rng.gen());
+            assert_eq!(compose_xyz18e7(decompose_xyz18e7(v)), Ok(v));
+
+            let xyz = xyz18e7_to_vec3(v);
+            let negated = xyz18e7_to_vec3(negate_xyz18e7(v)).map(f32::to_bits);
+            assert_eq!(negated, xyz.map(|x| (-x).to_bits()));
+            let abs = xyz18e7_to_vec3(abs_xyz18e7(v)).map(f32::to_bits);
+            assert_eq!(abs, xyz.map(|x| x.abs().to_bits()));
+            assert_eq!(negate_xyz18e7(negate_xyz18e7(v)), v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_xyz18e7(v, k), vec3_to_xyz18e7(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_xyz18e7(vec3_to_xyz18e7([1.0, 0.0, 0.0]));
+        assert_eq!(one.mantissas, [1 << 17, 0, 0]);
+        assert_eq!(
+            compose_xyz18e7(Parts {
+                exponent: 128,
+                ..one
+            }),
+            Err(PartsError::Exponent { exponent: 128 })
+        );
+        assert_eq!(
+            compose_xyz18e7(Parts {
+                mantissas: [0, 0, 1 << 18],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 18
+            })
+        );
+        assert_eq!(
+            ldexp_xyz18e7(vec3_to_xyz18e7([MAX_XYZ18E7; 3]), 1),
+            vec3_to_xyz18e7([MAX_XYZ18E7; 3])
+        );
+        assert_eq!(
+            ldexp_xyz18e7(vec3_to_xyz18e7([1.0; 3]), -1000),
+            vec3_to_xyz18e7([0.0; 3])
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::math::{floor_log2, pow2};
 use crate::nan_to_zero64;
 
 pub const NAME: &str = "xyz18e7";
@@ -10,15 +11,6 @@ pub const XYZ18E7_MANTISSA_BITSU: u32 = 18;
 pub const XYZ18E7_EXP_BIAS: i32 = 63;
 pub const XYZ18E7_MAX_VALID_BIASED_EXP: i32 = 127;
 
-/*
-pub const MAX_XYZ18E7_EXP: i32 = 32;
-pub const XYZ18E7_MANTISSA_VALUES: i32 = 8192;
-pub const MAX_XYZ18E7_MANTISSA: i32 = 8191;
-pub const MAX_XYZ18E7_MANTISSAU: u32 = 8191;
-pub const MAX_XYZ18E7: f64 = 4294443000.0;
-pub const EPSILON_XYZ18E7: f64 = -5.684342e-14;
-*/
-
 pub const MAX_XYZ18E7_EXP: u64 = XYZ18E7_MAX_VALID_BIASED_EXP as u64 - XYZ18E7_EXP_BIAS as u64;
 pub const XYZ18E7_MANTISSA_VALUES: i32 = 1 << XYZ18E7_MANTISSA_BITS;
 pub const MAX_XYZ18E7_MANTISSA: i32 = XYZ18E7_MANTISSA_VALUES - 1;
@@ -28,9 +20,16 @@ pub const MAX_XYZ18E7: f64 = (MAX_XYZ18E7_MANTISSA as f64) / XYZ18E7_MANTISSA_VA
 pub const EPSILON_XYZ18E7: f64 =
     (1.0 / XYZ18E7_MANTISSA_VALUES as f64) / (1u64 << XYZ18E7_EXP_BIAS) as f64;
 
+const _: () = assert!(MAX_XYZ18E7_EXP == 64);
+const _: () = assert!(XYZ18E7_MANTISSA_VALUES == 262144);
+const _: () = assert!(MAX_XYZ18E7_MANTISSA == 262143);
+const _: () = assert!(MAX_XYZ18E7_MANTISSAU == 262143);
+const _: () = assert!(MAX_XYZ18E7 == 1.8446673704965374e19);
+const _: () = assert!(EPSILON_XYZ18E7 == 4.1359030627651384e-25);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
+pub const fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
     let xsign = xyz[0].is_sign_negative() as u32;
     let ysign = xyz[1].is_sign_negative() as u32;
     let zsign = xyz[2].is_sign_negative() as u32;
@@ -40,15 +39,15 @@ pub fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
     let zc = nan_to_zero64(xyz[2].abs()).min(MAX_XYZ18E7);
 
     let maxxyz = xc.max(yc).max(zc);
-    let mut exp_shared =
-        (-XYZ18E7_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ18E7_EXP_BIAS;
+    let mut exp_shared = floor_log2(maxxyz.max(pow2(-XYZ18E7_EXP_BIAS - 1))) + 1 + XYZ18E7_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ18E7_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS) as f64).exp2();
+    let mut denom = pow2(exp_shared - XYZ18E7_EXP_BIAS - XYZ18E7_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ18E7_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -57,9 +56,9 @@ pub fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
         debug_assert!(maxm <= MAX_XYZ18E7_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ18E7_MANTISSA);
     debug_assert!(ym <= MAX_XYZ18E7_MANTISSA);
@@ -68,9 +67,9 @@ pub fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ18E7_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ18E7_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ18E7_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ18E7_MANTISSAU);
 
     let exp_shared = exp_shared as u32;
 
@@ -88,17 +87,17 @@ pub fn vec3_to_xyz18e7(xyz: [f64; 3]) -> (u32, u32) {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz18e7_to_vec3(v: (u32, u32)) -> [f64; 3] {
+pub const fn xyz18e7_to_vec3(v: (u32, u32)) -> [f64; 3] {
     let exponent = bitfield_extract(v.1, 25, XYZ18E7_EXPONENT_BITS as u32) as i32
         - XYZ18E7_EXP_BIAS
         - XYZ18E7_MANTISSA_BITS;
-    let scale = (exponent as f64).exp2();
+    let scale = pow2(exponent);
 
     let xsign = (bitfield_extract(v.1, 22, 1) << 1) as f64 - 1.0;
     let ysign = (bitfield_extract(v.1, 23, 1) << 1) as f64 - 1.0;
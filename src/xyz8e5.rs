@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "xyz8e5";
 pub const BYTES: u8 = 4;
@@ -10,15 +12,6 @@ pub const XYZ8E5_MANTISSA_BITSU: u32 = 8;
 pub const XYZ8E5_EXP_BIAS: i32 = 15;
 pub const XYZ8E5_MAX_VALID_BIASED_EXP: i32 = 31;
 
-/*
-pub const MAX_XYZ8E5_EXP: i32 = 16;
-pub const XYZ8E5_MANTISSA_VALUES: i32 = 256;
-pub const MAX_XYZ8E5_MANTISSA: i32 = 255;
-pub const MAX_XYZ8E5_MANTISSAU: u32 = 255;
-pub const MAX_XYZ8E5: f32 = 65280.0;
-pub const EPSILON_XYZ8E5: f32 = 1.1920929e-7;
-*/
-
 pub const MAX_XYZ8E5_EXP: i32 = XYZ8E5_MAX_VALID_BIASED_EXP - XYZ8E5_EXP_BIAS;
 pub const XYZ8E5_MANTISSA_VALUES: i32 = 1 << XYZ8E5_MANTISSA_BITS;
 pub const MAX_XYZ8E5_MANTISSA: i32 = XYZ8E5_MANTISSA_VALUES - 1;
@@ -28,9 +21,16 @@ pub const MAX_XYZ8E5: f32 =
 pub const EPSILON_XYZ8E5: f32 =
     (1.0 / XYZ8E5_MANTISSA_VALUES as f32) / (1 << XYZ8E5_EXP_BIAS) as f32;
 
+const _: () = assert!(MAX_XYZ8E5_EXP == 16);
+const _: () = assert!(XYZ8E5_MANTISSA_VALUES == 256);
+const _: () = assert!(MAX_XYZ8E5_MANTISSA == 255);
+const _: () = assert!(MAX_XYZ8E5_MANTISSAU == 255);
+const _: () = assert!(MAX_XYZ8E5 == 65280.0);
+const _: () = assert!(EPSILON_XYZ8E5 == 1.1920929e-7);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
+pub const fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
     let xsign = xyz[0].is_sign_negative() as u32;
     let ysign = xyz[1].is_sign_negative() as u32;
     let zsign = xyz[2].is_sign_negative() as u32;
@@ -40,15 +40,15 @@ pub fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
     let zc = nan_to_zero(xyz[2].abs()).min(MAX_XYZ8E5);
 
     let maxxyz = xc.max(yc).max(zc);
-    let mut exp_shared =
-        (-XYZ8E5_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ8E5_EXP_BIAS;
+    let mut exp_shared = floor_log2f(maxxyz.max(pow2f(-XYZ8E5_EXP_BIAS - 1))) + 1 + XYZ8E5_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ8E5_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ8E5_EXP_BIAS - XYZ8E5_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - XYZ8E5_EXP_BIAS - XYZ8E5_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ8E5_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -57,9 +57,9 @@ pub fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
         debug_assert!(maxm <= MAX_XYZ8E5_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ8E5_MANTISSA);
     debug_assert!(ym <= MAX_XYZ8E5_MANTISSA);
@@ -68,9 +68,9 @@ pub fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ8E5_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ8E5_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ8E5_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ8E5_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ8E5_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ8E5_MANTISSAU);
 
     let xm = xm as u32 | xsign << 8;
     let ym = ym as u32 | ysign << 8;
@@ -84,17 +84,17 @@ pub fn vec3_to_xyz8e5(xyz: [f32; 3]) -> u32 {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz8e5_to_vec3(v: u32) -> [f32; 3] {
+pub const fn xyz8e5_to_vec3(v: u32) -> [f32; 3] {
     let exponent = bitfield_extract(v, 27, XYZ8E5_EXPONENT_BITS as u32) as i32
         - XYZ8E5_EXP_BIAS
         - XYZ8E5_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2();
+    let scale = pow2f(exponent);
 
     // Extract both the mantissa and sign at the same time.
     let xb = bitfield_extract(v, 0, XYZ8E5_MANTISSA_BITSU + 1);
@@ -115,10 +115,104 @@ pub fn xyz8e5_to_vec3(v: u32) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_xyz8e5` gives for the value `v` decodes to. The same value can also be stored
+// with a larger exponent and smaller mantissas, these are moved to the smallest exponent. Signs
+// are kept as they are, also on zero mantissas, as the encoder keeps the sign of -0.0.
+#[inline]
+pub const fn canonicalize_xyz8e5(v: u32) -> u32 {
+    let exp = bitfield_extract(v, 27, XYZ8E5_EXPONENT_BITS as u32);
+    let xm = bitfield_extract(v, 0, XYZ8E5_MANTISSA_BITSU);
+    let ym = bitfield_extract(v, 9, XYZ8E5_MANTISSA_BITSU);
+    let zm = bitfield_extract(v, 18, XYZ8E5_MANTISSA_BITSU);
+
+    let xsign = bitfield_extract(v, 8, 1);
+    let ysign = bitfield_extract(v, 17, 1);
+    let zsign = bitfield_extract(v, 26, 1);
+
+    let (exp, shift) = canonical_exponent(exp, xm | ym | zm, XYZ8E5_MANTISSA_BITSU);
+
+    let xm = xm << shift | xsign << 8;
+    let ym = ym << shift | ysign << 8;
+    let zm = zm << shift | zsign << 8;
+
+    #[allow(clippy::identity_op)]
+    let ret = (exp << 27) | (zm << 18) | (ym << 9) | (xm << 0);
+
+    ret
+}
+
+#[inline]
+pub const fn is_canonical_xyz8e5(v: u32) -> bool {
+    canonicalize_xyz8e5(v) == v
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_xyz8e5(v: u32) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v, 27, XYZ8E5_EXPONENT_BITS as u32),
+        mantissas: [
+            bitfield_extract(v, 0, XYZ8E5_MANTISSA_BITSU),
+            bitfield_extract(v, 9, XYZ8E5_MANTISSA_BITSU),
+            bitfield_extract(v, 18, XYZ8E5_MANTISSA_BITSU),
+        ],
+        signs: [
+            bitfield_extract(v, 8, 1) != 0,
+            bitfield_extract(v, 17, 1) != 0,
+            bitfield_extract(v, 26, 1) != 0,
+        ],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_xyz8e5(parts: Parts) -> Result<u32, PartsError> {
+    match parts.check(XYZ8E5_EXPONENT_BITS as u32, XYZ8E5_MANTISSA_BITSU, SIGNED) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> u32 {
+    let [xm, ym, zm] = parts.mantissas;
+    let [xsign, ysign, zsign] = parts.signs;
+
+    let xm = xm | (xsign as u32) << 8;
+    let ym = ym | (ysign as u32) << 8;
+    let zm = zm | (zsign as u32) << 8;
+
+    #[allow(clippy::identity_op)]
+    let ret = (parts.exponent << 27) | (zm << 18) | (ym << 9) | (xm << 0);
+
+    ret
+}
+
+const XYZ8E5_SIGN_BITS: u32 = 1 << 8 | 1 << 17 | 1 << 26;
+
+// -v by flipping the sign bits. Like negating a float, zero becomes -0.0 and the other way around.
+#[inline]
+pub const fn negate_xyz8e5(v: u32) -> u32 {
+    v ^ XYZ8E5_SIGN_BITS
+}
+
+#[inline]
+pub const fn abs_xyz8e5(v: u32) -> u32 {
+    v & !XYZ8E5_SIGN_BITS
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_xyz8e5` on the scaled
+// decoded value. Components past MAX_XYZ8E5 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_xyz8e5(v: u32, k: i32) -> u32 {
+    pack_parts(decompose_xyz8e5(v).ldexp(k, XYZ8E5_EXPONENT_BITS as u32, XYZ8E5_MANTISSA_BITSU))
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use glam::Vec3;
+    use rand::Rng;
 
     use crate::evaluate::test_util::{Report, DEFUALT_ITERATIONS};
 
@@ -177,4 +271,86 @@ pub mod tests {
             xyz8e5_to_vec3(vec3_to_xyz8e5((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            let c = canonicalize_xyz8e5(v);
+            assert!(is_canonical_xyz8e5(c));
+            assert_eq!(is_canonical_xyz8e5(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = xyz8e5_to_vec3(v);
+            assert_eq!(
+                xyz8e5_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_xyz8e5(decoded));
+        }
+
+        // 1.0 as 64 * 2^(17 - 15 - 8) instead of 128 * 2^(16 - 15 - 8).
+        assert_eq!(canonicalize_xyz8e5(17 << 27 | 64), 16 << 27 | 128);
+        assert_eq!(
+            canonicalize_xyz8e5(17 << 27 | 64),
+            vec3_to_xyz8e5([1.0, 0.0, 0.0])
+        );
+        assert_eq!(canonicalize_xyz8e5(31 << 27), 0);
+        // Encoded -0.0 keeps its sign, at the smallest exponent.
+        assert!(is_canonical_xyz8e5(vec3_to_xyz8e5([-0.0; 3])));
+        assert_eq!(canonicalize_xyz8e5(31 << 27 | 1 << 8), 1 << 8);
+        assert_eq!(
+            canonicalize_xyz8e5(31 << 27 | 1 << 8),
+            vec3_to_xyz8e5([-0.0, 0.0, 0.0])
+        );
+        assert!(is_canonical_xyz8e5(vec3_to_xyz8e5([-1e-9, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            assert_eq!(compose_xyz8e5(decompose_xyz8e5(v)), Ok(v));
+
+            let xyz = xyz8e5_to_vec3(v);
+            let negated = xyz8e5_to_vec3(negate_xyz8e5(v)).map(f32::to_bits);
+            assert_eq!(negated, xyz.map(|x| (-x).to_bits()));
+            let abs = xyz8e5_to_vec3(abs_xyz8e5(v)).map(f32::to_bits);
+            assert_eq!(abs, xyz.map(|x| x.abs().to_bits()));
+            assert_eq!(negate_xyz8e5(negate_xyz8e5(v)), v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_xyz8e5(v, k), vec3_to_xyz8e5(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_xyz8e5(vec3_to_xyz8e5([1.0, 0.0, 0.0]));
+        assert_eq!(one.mantissas, [1 << 7, 0, 0]);
+        assert_eq!(
+            compose_xyz8e5(Parts {
+                exponent: 32,
+                ..one
+            }),
+            Err(PartsError::Exponent { exponent: 32 })
+        );
+        assert_eq!(
+            compose_xyz8e5(Parts {
+                mantissas: [0, 0, 1 << 8],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 8
+            })
+        );
+        assert_eq!(
+            ldexp_xyz8e5(vec3_to_xyz8e5([MAX_XYZ8E5; 3]), 1),
+            vec3_to_xyz8e5([MAX_XYZ8E5; 3])
+        );
+        assert_eq!(
+            ldexp_xyz8e5(vec3_to_xyz8e5([1.0; 3]), -1000),
+            vec3_to_xyz8e5([0.0; 3])
+        );
+    }
 }
@@ -1,4 +1,6 @@
-use crate::nan_to_zero;
+use crate::math::{floor_log2f, pow2f};
+use crate::parts::{Parts, PartsError};
+use crate::{canonical_exponent, nan_to_zero};
 
 pub const NAME: &str = "xyz9e2";
 pub const BYTES: u8 = 4;
@@ -11,16 +13,7 @@ pub const XYZ9E2_EXP_BIAS: i32 = 3;
 pub const XYZ9E2_MAX_VALID_BIASED_EXP: i32 = 3;
 
 // MAX_XYZ9E2 would be 0.9980469, this is used to scale to exactly 1.0
-pub const NORM_MULT: f32 = 1.0019569;
-
-/*
-pub const MAX_XYZ9E2_EXP: i32 = 0;
-pub const XYZ9E2_MANTISSA_VALUES: i32 = 512;
-pub const MAX_XYZ9E2_MANTISSA: i32 = 511;
-pub const MAX_XYZ9E2_MANTISSAU: u32 = 511;
-pub const MAX_XYZ9E2: f32 = 1.0;
-pub const EPSILON_XYZ9E2: f32 = 0.00024414063;
-*/
+pub const NORM_MULT: f32 = XYZ9E2_MANTISSA_VALUES as f32 / MAX_XYZ9E2_MANTISSA as f32;
 
 pub const MAX_XYZ9E2_EXP: i32 = XYZ9E2_MAX_VALID_BIASED_EXP - XYZ9E2_EXP_BIAS;
 pub const XYZ9E2_MANTISSA_VALUES: i32 = 1 << XYZ9E2_MANTISSA_BITS;
@@ -32,9 +25,17 @@ pub const MAX_XYZ9E2: f32 = (MAX_XYZ9E2_MANTISSA as f32) / XYZ9E2_MANTISSA_VALUE
 pub const EPSILON_XYZ9E2: f32 =
     (1.0 / XYZ9E2_MANTISSA_VALUES as f32) / (1 << XYZ9E2_EXP_BIAS) as f32;
 
+const _: () = assert!(NORM_MULT == 1.0019569);
+const _: () = assert!(MAX_XYZ9E2_EXP == 0);
+const _: () = assert!(XYZ9E2_MANTISSA_VALUES == 512);
+const _: () = assert!(MAX_XYZ9E2_MANTISSA == 511);
+const _: () = assert!(MAX_XYZ9E2_MANTISSAU == 511);
+const _: () = assert!(MAX_XYZ9E2 == 1.0);
+const _: () = assert!(EPSILON_XYZ9E2 == 0.00024414063);
+
 // Similar to https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
 #[inline]
-pub fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
+pub const fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
     let xsign = xyz[0].is_sign_negative() as u32;
     let ysign = xyz[1].is_sign_negative() as u32;
     let zsign = xyz[2].is_sign_negative() as u32;
@@ -44,15 +45,15 @@ pub fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
     let zc = nan_to_zero(xyz[2].abs()).min(MAX_XYZ9E2) / NORM_MULT;
 
     let maxxyz = xc.max(yc).max(zc);
-    let mut exp_shared =
-        (-XYZ9E2_EXP_BIAS - 1).max(maxxyz.log2().floor() as i32) + 1 + XYZ9E2_EXP_BIAS;
+    let mut exp_shared = floor_log2f(maxxyz.max(pow2f(-XYZ9E2_EXP_BIAS - 1))) + 1 + XYZ9E2_EXP_BIAS;
 
     debug_assert!(exp_shared <= XYZ9E2_MAX_VALID_BIASED_EXP);
     debug_assert!(exp_shared >= 0);
 
-    let mut denom = ((exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS) as f32).exp2();
+    let mut denom = pow2f(exp_shared - XYZ9E2_EXP_BIAS - XYZ9E2_MANTISSA_BITS);
 
-    let maxm = (maxxyz / denom + 0.5).floor() as i32;
+    // Everything is positive, so truncating with `as` is the same as floor.
+    let maxm = (maxxyz / denom + 0.5) as i32;
     if maxm == MAX_XYZ9E2_MANTISSA + 1 {
         denom *= 2.0;
         exp_shared += 1;
@@ -61,9 +62,9 @@ pub fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
         debug_assert!(maxm <= MAX_XYZ9E2_MANTISSA);
     }
 
-    let xm = (xc / denom + 0.5).floor() as i32;
-    let ym = (yc / denom + 0.5).floor() as i32;
-    let zm = (zc / denom + 0.5).floor() as i32;
+    let xm = (xc / denom + 0.5) as i32;
+    let ym = (yc / denom + 0.5) as i32;
+    let zm = (zc / denom + 0.5) as i32;
 
     debug_assert!(xm <= MAX_XYZ9E2_MANTISSA);
     debug_assert!(ym <= MAX_XYZ9E2_MANTISSA);
@@ -72,9 +73,9 @@ pub fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
     debug_assert!(ym >= 0);
     debug_assert!(zm >= 0);
 
-    debug_assert_eq!(xm as u32, xm as u32 & MAX_XYZ9E2_MANTISSAU);
-    debug_assert_eq!(ym as u32, ym as u32 & MAX_XYZ9E2_MANTISSAU);
-    debug_assert_eq!(zm as u32, zm as u32 & MAX_XYZ9E2_MANTISSAU);
+    debug_assert!(xm as u32 == xm as u32 & MAX_XYZ9E2_MANTISSAU);
+    debug_assert!(ym as u32 == ym as u32 & MAX_XYZ9E2_MANTISSAU);
+    debug_assert!(zm as u32 == zm as u32 & MAX_XYZ9E2_MANTISSAU);
 
     let xm = xm as u32 | xsign << 9;
     let ym = ym as u32 | ysign << 9;
@@ -88,17 +89,17 @@ pub fn vec3_to_xyz9e2(xyz: [f32; 3]) -> u32 {
 }
 
 #[inline]
-fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
+const fn bitfield_extract(value: u32, offset: u32, bits: u32) -> u32 {
     let mask = (1u32 << bits) - 1u32;
     (value >> offset) & mask
 }
 
 #[inline]
-pub fn xyz9e2_to_vec3(v: u32) -> [f32; 3] {
+pub const fn xyz9e2_to_vec3(v: u32) -> [f32; 3] {
     let exponent = bitfield_extract(v, 30, XYZ9E2_EXPONENT_BITS as u32) as i32
         - XYZ9E2_EXP_BIAS
         - XYZ9E2_MANTISSA_BITS;
-    let scale = (exponent as f32).exp2() * NORM_MULT;
+    let scale = pow2f(exponent) * NORM_MULT;
 
     // Extract both the mantissa and sign at the same time.
     let xb = bitfield_extract(v, 0, XYZ9E2_MANTISSA_BITSU + 1);
@@ -119,11 +120,105 @@ pub fn xyz9e2_to_vec3(v: u32) -> [f32; 3] {
     ]
 }
 
+// The code `vec3_to_xyz9e2` gives for the value `v` decodes to. The same value can also be stored
+// with a larger exponent and smaller mantissas, these are moved to the smallest exponent. Signs
+// are kept as they are, also on zero mantissas, as the encoder keeps the sign of -0.0.
+#[inline]
+pub const fn canonicalize_xyz9e2(v: u32) -> u32 {
+    let exp = bitfield_extract(v, 30, XYZ9E2_EXPONENT_BITS as u32);
+    let xm = bitfield_extract(v, 0, XYZ9E2_MANTISSA_BITSU);
+    let ym = bitfield_extract(v, 10, XYZ9E2_MANTISSA_BITSU);
+    let zm = bitfield_extract(v, 20, XYZ9E2_MANTISSA_BITSU);
+
+    let xsign = bitfield_extract(v, 9, 1);
+    let ysign = bitfield_extract(v, 19, 1);
+    let zsign = bitfield_extract(v, 29, 1);
+
+    let (exp, shift) = canonical_exponent(exp, xm | ym | zm, XYZ9E2_MANTISSA_BITSU);
+
+    let xm = xm << shift | xsign << 9;
+    let ym = ym << shift | ysign << 9;
+    let zm = zm << shift | zsign << 9;
+
+    #[allow(clippy::identity_op)]
+    let ret = (exp << 30) | (zm << 20) | (ym << 10) | (xm << 0);
+
+    ret
+}
+
+#[inline]
+pub const fn is_canonical_xyz9e2(v: u32) -> bool {
+    canonicalize_xyz9e2(v) == v
+}
+
+// The biased exponent, mantissas and signs of `v` as stored, without decoding it.
+#[inline]
+pub const fn decompose_xyz9e2(v: u32) -> Parts {
+    Parts {
+        exponent: bitfield_extract(v, 30, XYZ9E2_EXPONENT_BITS as u32),
+        mantissas: [
+            bitfield_extract(v, 0, XYZ9E2_MANTISSA_BITSU),
+            bitfield_extract(v, 10, XYZ9E2_MANTISSA_BITSU),
+            bitfield_extract(v, 20, XYZ9E2_MANTISSA_BITSU),
+        ],
+        signs: [
+            bitfield_extract(v, 9, 1) != 0,
+            bitfield_extract(v, 19, 1) != 0,
+            bitfield_extract(v, 29, 1) != 0,
+        ],
+    }
+}
+
+// Packs the parts into a value, or gives the first field that doesn't fit.
+#[inline]
+pub const fn compose_xyz9e2(parts: Parts) -> Result<u32, PartsError> {
+    match parts.check(XYZ9E2_EXPONENT_BITS as u32, XYZ9E2_MANTISSA_BITSU, SIGNED) {
+        Ok(parts) => Ok(pack_parts(parts)),
+        Err(error) => Err(error),
+    }
+}
+
+#[inline]
+pub(crate) const fn pack_parts(parts: Parts) -> u32 {
+    let [xm, ym, zm] = parts.mantissas;
+    let [xsign, ysign, zsign] = parts.signs;
+
+    let xm = xm | (xsign as u32) << 9;
+    let ym = ym | (ysign as u32) << 9;
+    let zm = zm | (zsign as u32) << 9;
+
+    #[allow(clippy::identity_op)]
+    let ret = (parts.exponent << 30) | (zm << 20) | (ym << 10) | (xm << 0);
+
+    ret
+}
+
+const XYZ9E2_SIGN_BITS: u32 = 1 << 9 | 1 << 19 | 1 << 29;
+
+// -v by flipping the sign bits. Like negating a float, zero becomes -0.0 and the other way around.
+#[inline]
+pub const fn negate_xyz9e2(v: u32) -> u32 {
+    v ^ XYZ9E2_SIGN_BITS
+}
+
+#[inline]
+pub const fn abs_xyz9e2(v: u32) -> u32 {
+    v & !XYZ9E2_SIGN_BITS
+}
+
+// v * 2^k by changing the exponent, giving the same value as `vec3_to_xyz9e2` on the scaled
+// decoded value. Components past MAX_XYZ9E2 saturate and small ones round to zero.
+#[inline]
+pub const fn ldexp_xyz9e2(v: u32, k: i32) -> u32 {
+    pack_parts(decompose_xyz9e2(v).ldexp(k, XYZ9E2_EXPONENT_BITS as u32, XYZ9E2_MANTISSA_BITSU))
+}
+
 #[cfg(test)]
 pub mod tests {
 
     use super::*;
     use glam::Vec3;
+    use rand::Rng;
 
     #[test]
     fn test_edge_cases() {
@@ -164,4 +259,79 @@ pub mod tests {
             xyz9e2_to_vec3(vec3_to_xyz9e2((Vec3::NAN).into())).into()
         );
     }
+
+    #[test]
+    fn test_canonical() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            let c = canonicalize_xyz9e2(v);
+            assert!(is_canonical_xyz9e2(c));
+            assert_eq!(is_canonical_xyz9e2(v), v == c);
+            // The same value, with the bits the encoder gives for it.
+            let decoded = xyz9e2_to_vec3(v);
+            assert_eq!(
+                xyz9e2_to_vec3(c).map(f32::to_bits),
+                decoded.map(f32::to_bits)
+            );
+            assert_eq!(c, vec3_to_xyz9e2(decoded));
+        }
+
+        assert_eq!(canonicalize_xyz9e2(3 << 30 | 128), 2 << 30 | 256);
+        assert_eq!(canonicalize_xyz9e2(3 << 30), 0);
+        // Encoded -0.0 keeps its sign, at the smallest exponent.
+        assert!(is_canonical_xyz9e2(vec3_to_xyz9e2([-0.0; 3])));
+        assert_eq!(canonicalize_xyz9e2(3 << 30 | 1 << 9), 1 << 9);
+        assert_eq!(
+            canonicalize_xyz9e2(3 << 30 | 1 << 9),
+            vec3_to_xyz9e2([-0.0, 0.0, 0.0])
+        );
+        assert!(is_canonical_xyz9e2(vec3_to_xyz9e2([-1e-9, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn test_parts() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000000 {
+            let v = rng.gen();
+            assert_eq!(compose_xyz9e2(decompose_xyz9e2(v)), Ok(v));
+
+            let xyz = xyz9e2_to_vec3(v);
+            let negated = xyz9e2_to_vec3(negate_xyz9e2(v)).map(f32::to_bits);
+            assert_eq!(negated, xyz.map(|x| (-x).to_bits()));
+            let abs = xyz9e2_to_vec3(abs_xyz9e2(v)).map(f32::to_bits);
+            assert_eq!(abs, xyz.map(|x| x.abs().to_bits()));
+            assert_eq!(negate_xyz9e2(negate_xyz9e2(v)), v);
+
+            let k = rng.gen_range(-40..40);
+            let scaled = xyz.map(|x| x * pow2f(k));
+            assert_eq!(ldexp_xyz9e2(v, k), vec3_to_xyz9e2(scaled), "{v:?} {k}");
+        }
+
+        let one = decompose_xyz9e2(vec3_to_xyz9e2([1.0, 0.0, 0.0]));
+        // 1.0 is the largest mantissa, scaled by NORM_MULT.
+        assert_eq!(one.mantissas, [MAX_XYZ9E2_MANTISSAU, 0, 0]);
+        assert_eq!(
+            compose_xyz9e2(Parts { exponent: 4, ..one }),
+            Err(PartsError::Exponent { exponent: 4 })
+        );
+        assert_eq!(
+            compose_xyz9e2(Parts {
+                mantissas: [0, 0, 1 << 9],
+                ..one
+            }),
+            Err(PartsError::Mantissa {
+                component: 2,
+                mantissa: 1 << 9
+            })
+        );
+        assert_eq!(
+            ldexp_xyz9e2(vec3_to_xyz9e2([MAX_XYZ9E2; 3]), 1),
+            vec3_to_xyz9e2([MAX_XYZ9E2; 3])
+        );
+        assert_eq!(
+            ldexp_xyz9e2(vec3_to_xyz9e2([1.0; 3]), -1000),
+            vec3_to_xyz9e2([0.0; 3])
+        );
+    }
 }